@@ -0,0 +1,120 @@
+//! Compares the two ways `Game` finds captured regions on large boards.
+//!
+//! Each iteration times the single `update` that completes a trail cutting
+//! the board in two, after an earlier capture has warmed any cached regions.
+//! The fragmented case adds hundreds of small walled-off pockets, each kept
+//! empty by a ball, that every capture has to account for.
+
+use axion::entity::{Ball, Direction};
+use axion::{Cell, FillAlgorithm, Game};
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+
+/// A game one tick away from a capture
+fn about_to_capture(size: i32, fill_algorithm: FillAlgorithm) -> Game {
+    let mut game = Game::with_seed(size, size, 1, 7);
+    game.set_fill_algorithm(fill_algorithm);
+    game.balls.clear();
+
+    // Warm-up capture: a small pocket in the top-left corner
+    for _ in 0..3 {
+        game.update();
+    }
+    game.set_direction(Direction::Up);
+    while game.players[0].is_drawing {
+        game.update();
+    }
+
+    // Along the top border, then down towards the bottom one
+    game.set_direction(Direction::Right);
+    for _ in 0..size / 3 {
+        game.update();
+    }
+    game.set_direction(Direction::Down);
+    while game.players[0].position.y < size - 2 {
+        game.update();
+    }
+    game
+}
+
+/// Pocket size, walls included
+const POCKET: i32 = 10;
+
+/// A game one tick away from a capture, with the top half of the board
+/// walled into pockets that each hold a ball
+fn fragmented_about_to_capture(size: i32, fill_algorithm: FillAlgorithm) -> Game {
+    let mut game = Game::with_seed(size, size, 1, 7);
+    game.set_fill_algorithm(fill_algorithm);
+    game.balls.clear();
+
+    let pockets_bottom = size / 2 - 5;
+    for y in 1..=pockets_bottom {
+        for x in 1..size - 1 {
+            if x % POCKET == 0 || y % POCKET == 0 || y == pockets_bottom {
+                game.board.set(x, y, Cell::Filled);
+            }
+        }
+    }
+    for top in (0..pockets_bottom - POCKET).step_by(POCKET as usize) {
+        for left in (0..size - POCKET).step_by(POCKET as usize) {
+            game.balls.push(Ball::new(left + POCKET / 2, top + POCKET / 2, 1, 1));
+        }
+    }
+
+    // Warm-up capture: a sliver along the left edge of the open bottom half
+    for _ in 0..3 {
+        game.update();
+    }
+    game.set_direction(Direction::Down);
+    while game.players[0].is_drawing {
+        game.update();
+    }
+
+    // Along the bottom border, then up towards the pockets
+    game.set_direction(Direction::Right);
+    for _ in 0..3 {
+        game.update();
+    }
+    game.set_direction(Direction::Up);
+    while game.players[0].position.y > pockets_bottom + 1 {
+        game.update();
+    }
+    game
+}
+
+fn bench_capture(c: &mut Criterion) {
+    let mut group = c.benchmark_group("capture");
+    group.sample_size(20);
+    for size in [200, 1000] {
+        for (name, fill_algorithm) in [
+            ("incremental", FillAlgorithm::Incremental),
+            ("full_scan", FillAlgorithm::FullScan),
+        ] {
+            group.bench_with_input(BenchmarkId::new(name, size), &size, |b, &size| {
+                b.iter_batched(
+                    || about_to_capture(size, fill_algorithm),
+                    |mut game| {
+                        game.update();
+                        assert!(!game.players[0].is_drawing);
+                        game
+                    },
+                    BatchSize::LargeInput,
+                )
+            });
+            group.bench_with_input(BenchmarkId::new(format!("{}_fragmented", name), size), &size, |b, &size| {
+                b.iter_batched(
+                    || fragmented_about_to_capture(size, fill_algorithm),
+                    |mut game| {
+                        game.update();
+                        assert!(!game.players[0].is_drawing);
+                        game
+                    },
+                    BatchSize::LargeInput,
+                )
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_capture);
+criterion_main!(benches);
@@ -0,0 +1,44 @@
+//! `CliRenderer` frame composition into memory, without a terminal.
+
+use axion::{CliRenderer, Game};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+fn bench_compose(c: &mut Criterion) {
+    let mut renderer = CliRenderer::new();
+    let mut group = c.benchmark_group("compose_frame");
+    for (width, height) in [(80, 24), (200, 60)] {
+        let advanced = |ticks| {
+            let mut game = Game::with_seed(width, height, 2, 19);
+            for _ in 0..ticks {
+                game.update();
+            }
+            game
+        };
+        let (game, next) = (advanced(10), advanced(11));
+        let size = format!("{}x{}", width, height);
+        let mut frame = Vec::new();
+
+        // Every cell, as after a resize
+        group.bench_with_input(BenchmarkId::new("full", &size), &game, |b, game| {
+            b.iter(|| {
+                frame.clear();
+                renderer.invalidate();
+                renderer.compose_frame(game, &mut frame).unwrap();
+            })
+        });
+
+        // Only what one tick changed
+        group.bench_with_input(BenchmarkId::new("tick", &size), &[game, next], |b, games| {
+            let mut which = 0;
+            b.iter(|| {
+                frame.clear();
+                which ^= 1;
+                renderer.compose_frame(&games[which], &mut frame).unwrap();
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_compose);
+criterion_main!(benches);
@@ -0,0 +1,45 @@
+//! `Game::spawn_balls` as the board fills up.
+//!
+//! Spawning lists every free cell clear of the players before picking, so
+//! its cost should follow the board size rather than how crowded it is.
+
+use axion::{Cell, Game};
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+
+const SIZE: i32 = 200;
+const BALLS: usize = 50;
+
+/// A ball-free game with `percent` of the playable area filled in
+fn crowded(percent: i32) -> Game {
+    let mut game = Game::with_seed(SIZE, SIZE, 1, 17);
+    game.balls.clear();
+    for y in 1..SIZE - 1 {
+        for x in 1..SIZE - 1 {
+            // Scattered rather than in blocks, so free cells are everywhere
+            if (x * 7 + y * 13) % 100 < percent {
+                game.board.set(x, y, Cell::Filled);
+            }
+        }
+    }
+    game
+}
+
+fn bench_spawn(c: &mut Criterion) {
+    let mut group = c.benchmark_group("spawn_balls");
+    for percent in [0, 50, 90, 99] {
+        group.bench_with_input(BenchmarkId::from_parameter(percent), &percent, |b, &percent| {
+            b.iter_batched(
+                || crowded(percent),
+                |mut game| {
+                    game.spawn_balls(BALLS).unwrap();
+                    game
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_spawn);
+criterion_main!(benches);
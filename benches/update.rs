@@ -0,0 +1,76 @@
+//! Cost of one `Game::update` as balls and trails grow.
+//!
+//! Ball collisions are resolved with board lookups, so the tick time should
+//! grow with the number of balls but barely move as the trail grows.
+
+use axion::entity::Ball;
+use axion::Game;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+
+const SIZE: i32 = 1000;
+const BALLS: i32 = 4000;
+
+/// The player `trail_len` cells into the board, with balls scattered above
+/// and below the trail but too far away to reach it this tick
+fn drawing_with_balls(trail_len: i32) -> Game {
+    let mut game = Game::with_seed(SIZE, SIZE, 1, 11);
+    game.balls.clear();
+    for _ in 0..trail_len {
+        game.update();
+    }
+
+    game.balls = (0..BALLS)
+        .map(|i| {
+            let x = 2 + i * 37 % (SIZE - 4);
+            let y = if i % 2 == 0 { 2 + i % (SIZE / 2 - 10) } else { SIZE - 3 - i % (SIZE / 2 - 10) };
+            let velocity = if i % 3 == 0 { -1 } else { 1 };
+            Ball::new(x, y, velocity, -velocity)
+        })
+        .collect();
+    game
+}
+
+/// A fresh game with `count` balls spread over the board
+fn with_balls(count: usize) -> Game {
+    let mut game = Game::with_seed(SIZE, SIZE, 1, 13);
+    game.balls.clear();
+    game.spawn_balls(count).unwrap();
+    game
+}
+
+fn bench_many_balls(c: &mut Criterion) {
+    let mut group = c.benchmark_group("update_many_balls");
+    for count in [100, 1_000, 10_000] {
+        group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, &count| {
+            b.iter_batched(
+                || with_balls(count),
+                |mut game| {
+                    game.update();
+                    game
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_update(c: &mut Criterion) {
+    let mut group = c.benchmark_group("update_long_trail");
+    for trail_len in [10, 100, 900] {
+        group.bench_with_input(BenchmarkId::from_parameter(trail_len), &trail_len, |b, &trail_len| {
+            b.iter_batched(
+                || drawing_with_balls(trail_len),
+                |mut game| {
+                    game.update();
+                    game
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_many_balls, bench_update);
+criterion_main!(benches);
@@ -9,6 +9,12 @@ pub struct BallTrail {
     last_ball_position: Option<(i32, i32)>, // Grid position of ball at last game update
 }
 
+impl Default for BallTrail {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl BallTrail {
     pub fn new() -> Self {
         Self {
@@ -0,0 +1,389 @@
+//! Flat bitset storage for the playing field.
+//!
+//! Cells live in row-major bitplanes (filled, trail, and slow-captured) so a
+//! 1000x1000 board is a few hundred KB and whole-board scans stay in cache.
+//! The board also keeps a running count of filled cells off the border, which
+//! is all the fill percentage needs, and a count of changes to filled cells
+//! so cached region searches can tell when the board moved under them.
+
+use crate::entity::DrawSpeed;
+use crate::game::Cell;
+
+/// One bit per cell, row-major
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BitPlane {
+    words: Vec<u64>,
+}
+
+impl BitPlane {
+    fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    fn get(&self, i: usize) -> bool {
+        self.words[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    fn set(&mut self, i: usize, value: bool) {
+        if value {
+            self.words[i / 64] |= 1 << (i % 64);
+        } else {
+            self.words[i / 64] &= !(1 << (i % 64));
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Board {
+    width: i32,
+    height: i32,
+    filled: BitPlane,
+    trail: BitPlane,
+    /// Captured with slow draw; only meaningful for filled cells
+    slow: BitPlane,
+    interior_filled: usize,
+    /// Bumped whenever a cell becomes filled or stops being filled
+    filled_changes: u64,
+}
+
+/// Equal when the cells are, however they got there
+impl PartialEq for Board {
+    fn eq(&self, other: &Self) -> bool {
+        self.width == other.width
+            && self.height == other.height
+            && self.filled == other.filled
+            && self.trail == other.trail
+            && self.slow == other.slow
+    }
+}
+
+impl Eq for Board {}
+
+impl Board {
+    /// An all-empty board
+    pub fn new(width: i32, height: i32) -> Self {
+        let len = (width.max(0) * height.max(0)) as usize;
+        Self {
+            width,
+            height,
+            filled: BitPlane::new(len),
+            trail: BitPlane::new(len),
+            slow: BitPlane::new(len),
+            interior_filled: 0,
+            filled_changes: 0,
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < self.width && y < self.height
+    }
+
+    fn index(&self, x: i32, y: i32) -> usize {
+        debug_assert!(self.in_bounds(x, y), "({}, {}) is off the board", x, y);
+        (y * self.width + x) as usize
+    }
+
+    fn on_border(&self, x: i32, y: i32) -> bool {
+        x == 0 || y == 0 || x == self.width - 1 || y == self.height - 1
+    }
+
+    /// The cell at (x, y), which must be on the board
+    pub fn get(&self, x: i32, y: i32) -> Cell {
+        let i = self.index(x, y);
+        if self.filled.get(i) {
+            Cell::Filled
+        } else if self.trail.get(i) {
+            Cell::Trail
+        } else {
+            Cell::Empty
+        }
+    }
+
+    pub fn set(&mut self, x: i32, y: i32, cell: Cell) {
+        let i = self.index(x, y);
+        let was_filled = self.filled.get(i);
+        let is_filled = cell == Cell::Filled;
+        if was_filled != is_filled {
+            self.filled_changes += 1;
+            if !self.on_border(x, y) {
+                if is_filled {
+                    self.interior_filled += 1;
+                } else {
+                    self.interior_filled -= 1;
+                }
+            }
+        }
+        self.filled.set(i, is_filled);
+        self.trail.set(i, cell == Cell::Trail);
+    }
+
+    /// Draw speed the cell at (x, y) was captured with
+    pub fn speed(&self, x: i32, y: i32) -> DrawSpeed {
+        if self.slow.get(self.index(x, y)) {
+            DrawSpeed::Slow
+        } else {
+            DrawSpeed::Fast
+        }
+    }
+
+    pub fn set_speed(&mut self, x: i32, y: i32, speed: DrawSpeed) {
+        let i = self.index(x, y);
+        self.slow.set(i, speed == DrawSpeed::Slow);
+    }
+
+    /// Filled cells, not counting the border
+    pub fn interior_filled(&self) -> usize {
+        self.interior_filled
+    }
+
+    /// Changes so far to which cells are filled. Trails coming and going
+    /// don't count, since they never split or join empty regions.
+    pub fn filled_changes(&self) -> u64 {
+        self.filled_changes
+    }
+
+    /// Every cell in row-major order
+    pub fn cells(&self) -> impl Iterator<Item = Cell> + '_ {
+        (0..self.height).flat_map(move |y| (0..self.width).map(move |x| self.get(x, y)))
+    }
+}
+
+/// An empty region found by a search, kept between searches
+#[derive(Debug, Clone)]
+pub(crate) struct CachedRegion {
+    pub label: u32,
+    /// Row-major first cell, as (x, y); regions are kept sorted by it
+    pub first: (i32, i32),
+    pub cells: Vec<(i32, i32)>,
+}
+
+/// The empty regions of a board, plus the buffers used to find them, reused
+/// from search to search so a capture on a large board doesn't allocate a
+/// board-sized `visited` grid.
+///
+/// Regions are 4-connected groups of non-filled cells off the border (a trail
+/// belongs to whatever region it runs through).
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RegionScratch {
+    /// Per cell, the label of the region it belongs to, or 0 if none
+    pub labels: Vec<u32>,
+    pub stack: Vec<(i32, i32)>,
+    pub regions: Vec<CachedRegion>,
+    next_label: u32,
+    /// `Board::filled_changes` as of the last search or capture
+    pub valid_at: Option<u64>,
+}
+
+impl RegionScratch {
+    /// Whether `labels` and `regions` still describe `board`
+    pub fn is_current(&self, board: &Board) -> bool {
+        self.valid_at == Some(board.filled_changes())
+    }
+
+    fn start_over(&mut self, board: &Board) {
+        self.labels.clear();
+        self.labels.resize((board.width * board.height) as usize, 0);
+        self.stack.clear();
+        self.regions.clear();
+        self.next_label = 1;
+    }
+
+    fn new_label(&mut self) -> u32 {
+        let label = self.next_label;
+        self.next_label += 1;
+        label
+    }
+
+    /// Off the border, not filled, and not yet in a region
+    fn is_open(&self, board: &Board, x: i32, y: i32) -> bool {
+        x >= 1
+            && y >= 1
+            && x < board.width - 1
+            && y < board.height - 1
+            && self.labels[board.index(x, y)] == 0
+            && board.get(x, y) != Cell::Filled
+    }
+
+    /// Label the whole board with a cell-by-cell stack flood fill. This is
+    /// the original search, kept as the reference for `scan_all`.
+    pub fn flood_all(&mut self, board: &Board) {
+        self.start_over(board);
+        for y in 1..(board.height - 1) {
+            for x in 1..(board.width - 1) {
+                if !self.is_open(board, x, y) {
+                    continue;
+                }
+
+                let label = self.new_label();
+                let mut cells = Vec::new();
+                self.stack.push((x, y));
+                while let Some((rx, ry)) = self.stack.pop() {
+                    if !self.is_open(board, rx, ry) {
+                        continue;
+                    }
+                    self.labels[board.index(rx, ry)] = label;
+                    cells.push((rx, ry));
+
+                    self.stack.push((rx + 1, ry));
+                    self.stack.push((rx - 1, ry));
+                    self.stack.push((rx, ry + 1));
+                    self.stack.push((rx, ry - 1));
+                }
+                self.regions.push(CachedRegion {
+                    label,
+                    first: (x, y),
+                    cells,
+                });
+            }
+        }
+        self.valid_at = Some(board.filled_changes());
+    }
+
+    /// Label the whole board with scanline fills
+    pub fn scan_all(&mut self, board: &Board) {
+        self.start_over(board);
+        for y in 1..(board.height - 1) {
+            for x in 1..(board.width - 1) {
+                if self.is_open(board, x, y) {
+                    let region = self.scan_region(board, (x, y));
+                    self.regions.push(region);
+                }
+            }
+        }
+        self.valid_at = Some(board.filled_changes());
+    }
+
+    /// Update the labels after `filled` (and nothing else) became filled
+    /// since they were last current. Only the regions those cells were in
+    /// are searched again.
+    pub fn rescan_around(&mut self, board: &Board, filled: &[(i32, i32)]) {
+        let mut touched: Vec<u32> = filled
+            .iter()
+            .filter(|&&(x, y)| board.in_bounds(x, y))
+            .map(|&(x, y)| self.labels[board.index(x, y)])
+            .filter(|&label| label != 0)
+            .collect();
+        touched.sort_unstable();
+        touched.dedup();
+
+        let mut kept = Vec::with_capacity(self.regions.len());
+        for region in std::mem::take(&mut self.regions) {
+            if touched.binary_search(&region.label).is_ok() {
+                for &(x, y) in &region.cells {
+                    self.labels[board.index(x, y)] = 0;
+                }
+            } else {
+                kept.push(region);
+            }
+        }
+        self.regions = kept;
+
+        // Every piece of a split region borders one of the cells that split it
+        for &(x, y) in filled {
+            for (nx, ny) in [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)] {
+                if self.is_open(board, nx, ny) {
+                    let region = self.scan_region(board, (nx, ny));
+                    self.regions.push(region);
+                }
+            }
+        }
+        self.regions.sort_by_key(|region| (region.first.1, region.first.0));
+        self.valid_at = Some(board.filled_changes());
+    }
+
+    /// Scanline fill the open region around `seed` with a new label
+    fn scan_region(&mut self, board: &Board, seed: (i32, i32)) -> CachedRegion {
+        let label = self.new_label();
+        let mut cells = Vec::new();
+        let mut first = seed;
+
+        self.stack.push(seed);
+        while let Some((x, y)) = self.stack.pop() {
+            if !self.is_open(board, x, y) {
+                continue;
+            }
+
+            // Widen to the whole open span of this row
+            let mut left = x;
+            while self.is_open(board, left - 1, y) {
+                left -= 1;
+            }
+            let mut right = x;
+            while self.is_open(board, right + 1, y) {
+                right += 1;
+            }
+            for span_x in left..=right {
+                self.labels[board.index(span_x, y)] = label;
+                cells.push((span_x, y));
+            }
+            if (y, left) < (first.1, first.0) {
+                first = (left, y);
+            }
+
+            // One seed per open run in the rows above and below
+            for next_y in [y - 1, y + 1] {
+                let mut in_run = false;
+                for span_x in left..=right {
+                    let open = self.is_open(board, span_x, next_y);
+                    if open && !in_run {
+                        self.stack.push((span_x, next_y));
+                    }
+                    in_run = open;
+                }
+            }
+        }
+
+        CachedRegion { label, first, cells }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cells_round_trip() {
+        let mut board = Board::new(70, 3);
+        board.set(65, 1, Cell::Trail);
+        board.set(66, 1, Cell::Filled);
+        board.set_speed(66, 1, DrawSpeed::Slow);
+
+        assert_eq!(board.get(65, 1), Cell::Trail);
+        assert_eq!(board.get(66, 1), Cell::Filled);
+        assert_eq!(board.speed(66, 1), DrawSpeed::Slow);
+        assert_eq!(board.speed(65, 1), DrawSpeed::Fast);
+        assert_eq!(board.get(64, 1), Cell::Empty);
+
+        // Trail to filled clears the trail bit
+        board.set(65, 1, Cell::Filled);
+        assert_eq!(board.get(65, 1), Cell::Filled);
+        board.set(65, 1, Cell::Empty);
+        assert_eq!(board.get(65, 1), Cell::Empty);
+    }
+
+    #[test]
+    fn test_interior_filled_ignores_border() {
+        let mut board = Board::new(10, 10);
+        board.set(0, 0, Cell::Filled);
+        board.set(9, 5, Cell::Filled);
+        assert_eq!(board.interior_filled(), 0);
+
+        board.set(3, 3, Cell::Filled);
+        board.set(3, 3, Cell::Filled);
+        board.set(4, 3, Cell::Trail);
+        assert_eq!(board.interior_filled(), 1);
+
+        board.set(3, 3, Cell::Empty);
+        assert_eq!(board.interior_filled(), 0);
+    }
+}
@@ -0,0 +1,288 @@
+//! A renderer that draws into a string instead of a screen.
+//!
+//! Each frame is one character per cell plus a few info lines, so tests can
+//! compare what the player would see against golden files, and a scripted
+//! input queue stands in for the keyboard.
+
+use crate::entity::DrawSpeed;
+use crate::game::{Cell, Game, GameState, TargetMode};
+use crate::key_bindings::KeyBindings;
+use crate::menu::PauseMenu;
+use crate::renderer::{Input, Renderer};
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::io;
+
+/// What each character in a frame stands for
+pub const LEGEND: &str = "1-4 player  o ball  + trail  # filled  % filled (slow draw)  . empty";
+
+#[derive(Debug, Default)]
+pub struct BufferRenderer {
+    frame: String,
+    frames_rendered: usize,
+    inputs: VecDeque<Input>,
+    /// Listed under the info lines while the game is paused
+    menu: Option<PauseMenu>,
+    /// Shown on the menu's controls page
+    bindings: KeyBindings,
+}
+
+impl BufferRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A renderer whose `poll_input` returns `inputs` in order, then nothing
+    pub fn with_inputs(inputs: impl IntoIterator<Item = Input>) -> Self {
+        Self {
+            inputs: inputs.into_iter().collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Queue `input` after any still waiting
+    pub fn push_input(&mut self, input: Input) {
+        self.inputs.push_back(input);
+    }
+
+    /// Show `menu` while the game is paused
+    pub fn set_menu(&mut self, menu: Option<PauseMenu>) {
+        self.menu = menu;
+    }
+
+    pub fn set_key_bindings(&mut self, bindings: KeyBindings) {
+        self.bindings = bindings;
+    }
+
+    /// The last rendered frame, empty before the first
+    pub fn frame(&self) -> &str {
+        &self.frame
+    }
+
+    pub fn frames_rendered(&self) -> usize {
+        self.frames_rendered
+    }
+
+    /// `game` as text: the board, then level, score and fill, then a line
+    /// per player when there are several, then the end-of-game message or
+    /// pause menu if any
+    pub fn draw(game: &Game, menu: Option<&PauseMenu>, bindings: &KeyBindings) -> String {
+        let mut frame = String::with_capacity(((game.width + 1) * (game.height + 4)) as usize);
+        for y in 0..game.height {
+            for x in 0..game.width {
+                frame.push(Self::cell_char(game, x, y));
+            }
+            frame.push('\n');
+        }
+
+        let _ = writeln!(
+            frame,
+            "Level: {}  Score: {}  Filled: {:.1}%  Target: {:.0}%",
+            game.level,
+            game.score,
+            game.filled_percentage * 100.0,
+            game.target_percentage * 100.0
+        );
+        if game.players.len() > 1 {
+            for (idx, player) in game.players.iter().enumerate() {
+                let _ = writeln!(frame, "P{}: {}{}", idx + 1, player.score, if player.alive { "" } else { " (OUT)" });
+            }
+        }
+        match game.state {
+            GameState::Won => {
+                let message = match (game.target_mode, game.winner()) {
+                    (TargetMode::Competitive, Some(idx)) => format!("PLAYER {} WINS!", idx + 1),
+                    (TargetMode::Competitive, None) => "DRAW!".to_string(),
+                    (TargetMode::Shared, _) => "YOU WIN!".to_string(),
+                };
+                frame.push_str(&message);
+                frame.push('\n');
+            }
+            GameState::Lost => frame.push_str("GAME OVER!\n"),
+            GameState::Paused => match menu {
+                Some(menu) => {
+                    let _ = writeln!(frame, "{}", menu.title());
+                    for (idx, &item) in menu.items().iter().enumerate() {
+                        let marker = if idx == menu.selected() { '>' } else { ' ' };
+                        let _ = writeln!(frame, "{} {}", marker, PauseMenu::label(item, game, bindings));
+                    }
+                    if let Some(notice) = menu.notice() {
+                        let _ = writeln!(frame, "{}", notice);
+                    }
+                }
+                None => frame.push_str("PAUSED\n"),
+            },
+            GameState::Playing => {}
+        }
+        frame
+    }
+
+    /// Players over balls over the board, as on screen
+    fn cell_char(game: &Game, x: i32, y: i32) -> char {
+        if let Some(idx) = game
+            .players
+            .iter()
+            .position(|player| player.alive && player.position.x == x && player.position.y == y)
+        {
+            return char::from_digit(idx as u32 + 1, 10).unwrap_or('@');
+        }
+        if game.balls.iter().any(|ball| ball.position.x == x && ball.position.y == y) {
+            return 'o';
+        }
+        match (game.cell_at(x, y), game.capture_speed_at(x, y)) {
+            (Cell::Empty, _) => '.',
+            (Cell::Trail, _) => '+',
+            (Cell::Filled, DrawSpeed::Fast) => '#',
+            (Cell::Filled, DrawSpeed::Slow) => '%',
+        }
+    }
+}
+
+impl Renderer for BufferRenderer {
+    fn init(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn render(&mut self, game: &Game) -> io::Result<()> {
+        self.frame = Self::draw(game, self.menu.as_ref(), &self.bindings);
+        self.frames_rendered += 1;
+        Ok(())
+    }
+
+    fn cleanup(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn poll_input(&mut self) -> io::Result<Option<Input>> {
+        Ok(self.inputs.pop_front())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::{Ball, Direction};
+    use std::path::PathBuf;
+
+    /// Compare `frame` with `tests/golden/<name>.txt`. Run with `UPDATE_GOLDEN=1`
+    /// to write the file instead after an intended change.
+    fn assert_golden(name: &str, frame: &str) {
+        let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "golden", &format!("{}.txt", name)].iter().collect();
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(&path, frame).unwrap();
+            return;
+        }
+        let expected = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("{}: {} (run with UPDATE_GOLDEN=1 to create it)", path.display(), e));
+        assert_eq!(frame, expected, "frame differs from {}", path.display());
+    }
+
+    /// A small board with one ball in a fixed spot, so frames don't depend on spawning
+    fn scripted_game() -> Game {
+        let mut game = Game::with_seed(16, 8, 1, 0);
+        game.balls = vec![Ball::new(11, 5, 1, 1)];
+        game
+    }
+
+    /// Feed `renderer`'s scripted inputs to `game`, one update per input,
+    /// the way the CLI does: the pause menu gets every input while paused
+    fn play(game: &mut Game, renderer: &mut BufferRenderer) {
+        let mut menu = PauseMenu::new();
+        let mut bindings = KeyBindings::default();
+        while let Some(input) = renderer.poll_input().unwrap() {
+            if game.state == GameState::Paused {
+                menu.handle(input, game, &mut bindings);
+            } else {
+                match input {
+                    Input::Direction(direction) => game.set_direction(direction),
+                    Input::Pause | Input::FocusLost => {
+                        game.pause();
+                        menu = PauseMenu::new();
+                    }
+                    _ => {}
+                }
+            }
+            game.update();
+            renderer.set_menu((game.state == GameState::Paused).then_some(menu));
+            renderer.render(game).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_golden_trail_mid_draw() {
+        let mut game = scripted_game();
+        let mut renderer = BufferRenderer::with_inputs([Input::Direction(Direction::Right); 3]);
+        play(&mut game, &mut renderer);
+        assert_eq!(renderer.frames_rendered(), 3);
+        assert_golden("trail_mid_draw", renderer.frame());
+    }
+
+    #[test]
+    fn test_golden_capture_complete() {
+        let mut game = scripted_game();
+        let mut renderer = BufferRenderer::with_inputs(
+            [Input::Direction(Direction::Right); 3]
+                .into_iter()
+                .chain([Input::Direction(Direction::Up); 4]),
+        );
+        play(&mut game, &mut renderer);
+        assert!(!game.players[0].is_drawing);
+        assert_golden("capture_complete", renderer.frame());
+    }
+
+    #[test]
+    fn test_golden_win() {
+        let mut game = scripted_game();
+        game.target_percentage = 0.1;
+        let mut renderer = BufferRenderer::with_inputs(
+            [Input::Direction(Direction::Right); 3]
+                .into_iter()
+                .chain([Input::Direction(Direction::Up); 4]),
+        );
+        play(&mut game, &mut renderer);
+        assert_eq!(game.state, GameState::Won);
+        assert_golden("win", renderer.frame());
+    }
+
+    #[test]
+    fn test_golden_loss() {
+        let mut game = scripted_game();
+        // Heading down-left onto the start of the trail
+        game.balls = vec![Ball::new(3, 2, -1, 1)];
+        let mut renderer = BufferRenderer::with_inputs([Input::Direction(Direction::Right); 3]);
+        play(&mut game, &mut renderer);
+        assert_eq!(game.state, GameState::Lost);
+        assert_golden("loss", renderer.frame());
+    }
+
+    #[test]
+    fn test_golden_paused() {
+        let mut game = scripted_game();
+        let mut renderer = BufferRenderer::with_inputs([
+            Input::Direction(Direction::Right),
+            Input::Direction(Direction::Right),
+            Input::FocusLost,
+            Input::Direction(Direction::Down),
+            Input::Direction(Direction::Down),
+        ]);
+        play(&mut game, &mut renderer);
+        assert_eq!(game.state, GameState::Paused);
+        assert_golden("paused", renderer.frame());
+
+        // Resuming picks up where the trail left off
+        renderer.push_input(Input::Pause);
+        renderer.push_input(Input::Direction(Direction::Right));
+        play(&mut game, &mut renderer);
+        assert_eq!(game.players[0].position.x, 4);
+    }
+
+    #[test]
+    fn test_scripted_input_runs_out() {
+        let mut renderer = BufferRenderer::with_inputs([Input::Quit]);
+        renderer.push_input(Input::Restart);
+        assert_eq!(renderer.poll_input().unwrap(), Some(Input::Quit));
+        assert_eq!(renderer.poll_input().unwrap(), Some(Input::Restart));
+        assert_eq!(renderer.poll_input().unwrap(), None);
+        assert!(renderer.frame().is_empty());
+    }
+}
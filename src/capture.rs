@@ -0,0 +1,302 @@
+/// An empty region of the board found after the player completes a trail.
+///
+/// Regions are 4-connected groups of non-filled cells, discovered in row-major
+/// order of their first cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub cells: Vec<(i32, i32)>,
+    pub ball_count: usize,
+    /// Holds the player who just completed the trail
+    pub contains_player: bool,
+    /// Holds another player who is still drawing; `Game` never fills these
+    pub contains_other_player: bool,
+}
+
+impl Region {
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn has_balls(&self) -> bool {
+        self.ball_count > 0
+    }
+}
+
+/// Decides which regions get captured when a trail is completed.
+///
+/// `Game` finds every empty region and hands them to the rule; the rule only
+/// picks. Balls left inside captured regions are removed from play by `Game`.
+pub trait CaptureRule: Send {
+    /// Short identifier, also accepted by `capture_rule_by_name`
+    fn name(&self) -> &'static str;
+
+    /// Return the indices of `regions` to fill. Only called with two or more regions.
+    fn regions_to_fill(&self, regions: &[Region]) -> Vec<usize>;
+}
+
+/// Index of the largest region. Ties go to the region found last, matching
+/// the original `max_by_key` behavior.
+fn largest_region(regions: &[Region]) -> Option<usize> {
+    regions
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, region)| region.len())
+        .map(|(idx, _)| idx)
+}
+
+/// Classic Xonix behavior (the default).
+///
+/// The player's region (or the largest one, when the player is on filled
+/// territory) is the outside. Every other region is filled unless it contains
+/// a ball, so cornering a ball takes several cuts.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct XonixRule;
+
+impl CaptureRule for XonixRule {
+    fn name(&self) -> &'static str {
+        "xonix"
+    }
+
+    fn regions_to_fill(&self, regions: &[Region]) -> Vec<usize> {
+        let outside_idx = regions
+            .iter()
+            .position(|region| region.contains_player)
+            .or_else(|| largest_region(regions));
+
+        regions
+            .iter()
+            .enumerate()
+            .filter(|&(idx, region)| Some(idx) != outside_idx && !region.has_balls())
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+}
+
+/// Qix behavior: fill every side of the cut that doesn't hold a ball,
+/// regardless of size.
+///
+/// With no balls on the board there is nothing to decide sides by, so the
+/// largest region is kept as the playable area.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct QixRule;
+
+impl CaptureRule for QixRule {
+    fn name(&self) -> &'static str {
+        "qix"
+    }
+
+    fn regions_to_fill(&self, regions: &[Region]) -> Vec<usize> {
+        let keep_idx = if regions.iter().any(Region::has_balls) {
+            None
+        } else {
+            largest_region(regions)
+        };
+
+        regions
+            .iter()
+            .enumerate()
+            .filter(|&(idx, region)| Some(idx) != keep_idx && !region.has_balls())
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+}
+
+/// Always keep only the largest region; everything else is filled, even if
+/// it contains balls (which are then captured).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SmallerSideRule;
+
+impl CaptureRule for SmallerSideRule {
+    fn name(&self) -> &'static str {
+        "smaller"
+    }
+
+    fn regions_to_fill(&self, regions: &[Region]) -> Vec<usize> {
+        let keep_idx = largest_region(regions);
+
+        (0..regions.len())
+            .filter(|&idx| Some(idx) != keep_idx)
+            .collect()
+    }
+}
+
+/// Look up a built-in rule by its `CaptureRule::name`
+pub fn capture_rule_by_name(name: &str) -> Option<Box<dyn CaptureRule>> {
+    match name {
+        "xonix" => Some(Box::new(XonixRule)),
+        "qix" => Some(Box::new(QixRule)),
+        "smaller" => Some(Box::new(SmallerSideRule)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::{Ball, Direction};
+    use crate::game::{Cell, Game, GameState};
+    use proptest::prelude::*;
+
+    fn region(size: usize, ball_count: usize, contains_player: bool) -> Region {
+        Region {
+            cells: (0..size as i32).map(|x| (x, 0)).collect(),
+            ball_count,
+            contains_player,
+            contains_other_player: false,
+        }
+    }
+
+    fn direction_strategy() -> impl Strategy<Value = Direction> {
+        prop_oneof![
+            Just(Direction::Up),
+            Just(Direction::Down),
+            Just(Direction::Left),
+            Just(Direction::Right),
+        ]
+    }
+
+    /// Drive a game with the given rule, calling `check` after every completed trail
+    fn play_with_rule(
+        rule: Box<dyn CaptureRule>,
+        balls: &[(i32, i32, i32, i32)],
+        moves: &[Direction],
+        mut check: impl FnMut(&mut Game) -> Result<(), TestCaseError>,
+    ) -> Result<(), TestCaseError> {
+        let mut game = Game::new(20, 20);
+        game.set_capture_rule(rule);
+        game.balls = balls
+            .iter()
+            .map(|&(x, y, vx, vy)| Ball::new(x, y, vx, vy))
+            .collect();
+
+        for &direction in moves {
+            if game.state != GameState::Playing {
+                break;
+            }
+            let was_drawing = game.players[0].is_drawing;
+            game.set_direction(direction);
+            game.update();
+
+            if was_drawing && !game.players[0].is_drawing {
+                check(&mut game)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn ball_strategy() -> impl Strategy<Value = Vec<(i32, i32, i32, i32)>> {
+        prop::collection::vec(
+            (
+                3i32..17,
+                3i32..17,
+                prop_oneof![Just(-1), Just(1)],
+                prop_oneof![Just(-1), Just(1)],
+            ),
+            0..4,
+        )
+    }
+
+    #[test]
+    fn test_xonix_keeps_player_region() {
+        let regions = vec![region(5, 0, false), region(2, 0, true), region(3, 0, false)];
+        assert_eq!(XonixRule.regions_to_fill(&regions), vec![0, 2]);
+    }
+
+    #[test]
+    fn test_xonix_falls_back_to_largest_and_skips_balls() {
+        let regions = vec![region(5, 0, false), region(9, 0, false), region(3, 1, false)];
+        assert_eq!(XonixRule.regions_to_fill(&regions), vec![0]);
+    }
+
+    #[test]
+    fn test_qix_fills_ball_free_side_even_if_larger() {
+        let regions = vec![region(50, 0, false), region(3, 1, false)];
+        assert_eq!(QixRule.regions_to_fill(&regions), vec![0]);
+    }
+
+    #[test]
+    fn test_qix_without_balls_keeps_largest() {
+        let regions = vec![region(4, 0, false), region(8, 0, false), region(2, 0, false)];
+        assert_eq!(QixRule.regions_to_fill(&regions), vec![0, 2]);
+    }
+
+    #[test]
+    fn test_smaller_side_fills_ball_regions() {
+        let regions = vec![region(4, 2, false), region(8, 0, false), region(2, 0, true)];
+        assert_eq!(SmallerSideRule.regions_to_fill(&regions), vec![0, 2]);
+    }
+
+    #[test]
+    fn test_capture_rule_by_name_round_trips() {
+        for name in ["xonix", "qix", "smaller"] {
+            assert_eq!(capture_rule_by_name(name).unwrap().name(), name);
+        }
+        assert!(capture_rule_by_name("bogus").is_none());
+    }
+
+    #[test]
+    fn test_smaller_side_removes_captured_balls() {
+        let mut game = Game::new(20, 20);
+        game.set_capture_rule(Box::new(SmallerSideRule));
+        game.balls.clear();
+
+        for y in 1..19 {
+            game.board.set(5, y, Cell::Filled);
+        }
+        game.balls.push(Ball::new(2, 10, 1, 1));
+        game.balls.push(Ball::new(12, 10, 1, 1));
+        game.fill_enclosed_areas(0);
+
+        assert_eq!(game.cell_at(2, 10), Cell::Filled);
+        assert_eq!(game.cell_at(12, 10), Cell::Empty);
+        assert_eq!(game.balls.len(), 1);
+        assert_eq!(game.balls[0].position.x, 12);
+    }
+
+    proptest! {
+        /// Xonix: a region holding a ball is never captured
+        #[test]
+        fn prop_xonix_never_captures_balls(
+            balls in ball_strategy(),
+            moves in prop::collection::vec(direction_strategy(), 1..100),
+        ) {
+            let ball_count = balls.len();
+            play_with_rule(Box::new(XonixRule), &balls, &moves, |game| {
+                prop_assert_eq!(game.balls.len(), ball_count);
+                Ok(())
+            })?;
+        }
+
+        /// Qix: balls are never captured and some empty space always survives a cut
+        #[test]
+        fn prop_qix_never_captures_balls_or_whole_board(
+            balls in ball_strategy(),
+            moves in prop::collection::vec(direction_strategy(), 1..100),
+        ) {
+            let ball_count = balls.len();
+            play_with_rule(Box::new(QixRule), &balls, &moves, |game| {
+                prop_assert_eq!(game.balls.len(), ball_count);
+                prop_assert!(!game.find_empty_regions(0).is_empty());
+                Ok(())
+            })?;
+        }
+
+        /// Smaller side: after a cut exactly one empty region remains
+        #[test]
+        fn prop_smaller_side_leaves_single_region(
+            balls in ball_strategy(),
+            moves in prop::collection::vec(direction_strategy(), 1..100),
+        ) {
+            let ball_count = balls.len();
+            play_with_rule(Box::new(SmallerSideRule), &balls, &moves, |game| {
+                prop_assert_eq!(game.find_empty_regions(0).len(), 1);
+                prop_assert!(game.balls.len() <= ball_count);
+                Ok(())
+            })?;
+        }
+    }
+}
@@ -1,202 +1,1275 @@
-use crate::entity::Direction;
-use crate::game::{Cell, Game, GameState};
+use crate::entity::{Direction, DrawSpeed, MovementMode, Position};
+use crate::game::{Cell, Game, GameState, TargetMode};
+use crate::key_bindings::{Key, KeyBindings};
+use crate::menu::PauseMenu;
 use crate::renderer::{Input, Renderer};
+use crate::theme::{Palette, Theme};
 use crossterm::{
     cursor,
-    event::{self, Event, KeyCode, KeyEvent},
+    event::{self, Event, KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind},
     execute, queue,
-    style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+    style::{Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor},
     terminal::{self, ClearType},
 };
 use std::collections::HashSet;
 use std::io::{self, Write};
+use std::ops::Range;
 use std::time::{Duration, Instant};
 
-pub struct CliRenderer {
-    last_render: Instant,
+/// How the board is fitted to a terminal of a different size. Either way
+/// the game itself keeps its dimensions; only the drawing changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BoardFit {
+    /// Full-size cells centered in the terminal. A terminal too small for
+    /// them shows a message instead of the board.
+    #[default]
+    Letterbox,
+    /// Narrow cells to one column, then merge square blocks of cells into
+    /// one character, until the board fits
+    Scale,
+}
+
+/// How many board cells one terminal character shows. The denser modes
+/// draw in one column per character whatever the theme, and draw a player
+/// or ball over the rest of its character so they stay easy to spot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Density {
+    /// One cell per character, or per two with most themes
+    #[default]
+    Normal,
+    /// Two cells stacked in one character with half blocks (`▀`), each in
+    /// its own color
+    HalfBlock,
+    /// A 2x4 grid of cells per character as braille dots, showing the
+    /// trail if there is one and filled cells otherwise
+    Braille,
+}
+
+impl Density {
+    /// Board cells per character, across and down
+    pub fn cells_per_char(self) -> (i32, i32) {
+        match self {
+            Density::Normal => (1, 1),
+            Density::HalfBlock => (1, 2),
+            Density::Braille => (2, 4),
+        }
+    }
+
+    /// Terminal columns per character at full size
+    pub fn char_width(self, theme: Theme) -> u16 {
+        match self {
+            Density::Normal => theme.cell_width(),
+            Density::HalfBlock | Density::Braille => 1,
+        }
+    }
+}
+
+/// Where the board goes on screen and how much of it each character shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Layout {
+    left: u16,
+    top: u16,
+    /// Terminal columns per drawn cell
+    cell_width: u16,
+    /// Board cells per drawn cell along each axis
+    block: i32,
+    /// Drawn cells per character across and down (see `Density`)
+    per_char: (i32, i32),
+    cols: i32,
+    rows: i32,
+    /// Columns available to the info lines
+    info_width: u16,
+}
+
+impl Layout {
+    /// Lay out a `width` x `height` board with `info_height` lines under it,
+    /// `per_char` cells to a character `cell_width` columns wide at full
+    /// size. None if it can't fit `terminal`; no terminal means no limit.
+    fn fit(
+        width: i32,
+        height: i32,
+        info_height: u16,
+        cell_width: u16,
+        per_char: (i32, i32),
+        terminal: Option<(u16, u16)>,
+        fit: BoardFit,
+    ) -> Option<Self> {
+        let full_width = cell_width;
+        let (across, down) = per_char;
+        let Some((term_width, term_height)) = terminal else {
+            return Some(Self {
+                left: 0,
+                top: 0,
+                cell_width: full_width,
+                block: 1,
+                per_char,
+                cols: (width + across - 1) / across,
+                rows: (height + down - 1) / down,
+                info_width: u16::MAX,
+            });
+        };
+
+        let max_block = match fit {
+            BoardFit::Letterbox => 1,
+            BoardFit::Scale => width.max(height).max(1),
+        };
+        for block in 1..=max_block {
+            let cols = (width + block * across - 1) / (block * across);
+            let rows = (height + block * down - 1) / (block * down);
+            for cell_width in (1..=full_width).rev() {
+                if fit == BoardFit::Letterbox && cell_width < full_width {
+                    continue;
+                }
+                let board_width = cols * cell_width as i32;
+                let total_height = rows + info_height as i32;
+                if board_width <= term_width as i32 && total_height <= term_height as i32 {
+                    return Some(Self {
+                        left: ((term_width as i32 - board_width) / 2) as u16,
+                        top: ((term_height as i32 - total_height) / 2) as u16,
+                        cell_width,
+                        block,
+                        per_char,
+                        cols,
+                        rows,
+                        info_width: term_width - ((term_width as i32 - board_width) / 2) as u16,
+                    });
+                }
+            }
+        }
+        None
+    }
+}
+
+/// What one drawn cell shows: the most important thing in its block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Glyph {
+    Player(usize),
+    Ball,
+    Cell(Cell, DrawSpeed),
+}
+
+/// What one character of the board shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Drawn {
+    Glyph(Glyph),
+    /// Top and bottom halves
+    HalfBlock(Glyph, Glyph),
+    /// Cells like `Glyph` as raised dots, one bit per braille dot
+    Braille(Glyph, u8),
+}
+
+/// A clickable label in the info lines
+#[derive(Debug, Clone)]
+struct Button {
+    row: u16,
+    columns: Range<u16>,
+    input: Input,
+}
+
+/// Draws the game as text with escape codes into `W`: stdout by default, or
+/// a file, a pseudo-terminal, an SSH channel or a `Vec<u8>`
+pub struct CliRenderer<W: Write = io::Stdout> {
+    out: W,
+    /// Put the local terminal in raw mode while active and read its size.
+    /// Only makes sense when `out` is that terminal.
+    raw_mode: bool,
+    /// When the last frame was written, if any
+    last_render: Option<Instant>,
     target_frame_time: Duration,
+    /// The next frame, composed in full before it's written in one go
+    frame: Vec<u8>,
+    /// Extra line shown while playing (e.g. network state)
+    status: Option<String>,
+    /// `init` has set the screen up and `cleanup` hasn't restored it yet
+    active: bool,
+    /// Drawn over the board while the game is paused
+    menu: Option<PauseMenu>,
+    /// What each key does, and what the help lines say
+    bindings: KeyBindings,
+    board_fit: BoardFit,
+    theme: Theme,
+    density: Density,
+    /// Clicks steer the player and press buttons; the wheel scrolls menus
+    mouse: bool,
+    /// Buttons in the last frame's info lines
+    buttons: Vec<Button>,
+    /// Columns and rows, once known; kept up to date from resize events
+    terminal_size: Option<(u16, u16)>,
+    /// Layout of the last frame, to clear the screen when it changes
+    last_layout: Option<Option<Layout>>,
+    /// What each character of the board shows on screen, row-major; None
+    /// where that isn't known, so the next frame draws it
+    front: Vec<Option<Drawn>>,
+    /// Escape codes last written for the pause menu and the info lines,
+    /// which are only written again when they change
+    last_menu: Vec<u8>,
+    last_info: Vec<u8>,
+}
+
+/// The colors most recently set while writing a frame, to skip setting them again
+#[derive(Default)]
+struct Pen {
+    background: Option<Color>,
+    foreground: Option<Color>,
+}
+
+impl Pen {
+    /// Switch to the colors given, leaving the rest as they are
+    fn set(&mut self, background: Option<Color>, foreground: Option<Color>, out: &mut impl Write) -> io::Result<()> {
+        if let Some(color) = background.filter(|&color| self.background != Some(color)) {
+            queue!(out, SetBackgroundColor(color))?;
+            self.background = Some(color);
+        }
+        if let Some(color) = foreground.filter(|&color| self.foreground != Some(color)) {
+            queue!(out, SetForegroundColor(color))?;
+            self.foreground = Some(color);
+        }
+        Ok(())
+    }
+}
+
+/// The key behind a terminal key code, for the ones that can be bound
+fn key_for(code: KeyCode) -> Option<Key> {
+    Some(match code {
+        KeyCode::Char(c) => Key::char(c),
+        KeyCode::Up => Key::Up,
+        KeyCode::Down => Key::Down,
+        KeyCode::Left => Key::Left,
+        KeyCode::Right => Key::Right,
+        KeyCode::Home => Key::Home,
+        KeyCode::End => Key::End,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        KeyCode::Enter => Key::Enter,
+        KeyCode::Esc => Key::Esc,
+        KeyCode::Tab => Key::Tab,
+        KeyCode::Backspace => Key::Backspace,
+        _ => return None,
+    })
+}
+
+/// `text` cut to at most `width` characters
+fn clip(text: &str, width: u16) -> &str {
+    match text.char_indices().nth(width as usize) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+impl Default for CliRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CliRenderer {
+    /// A renderer for the terminal on stdout, in raw mode while active
     pub fn new() -> Self {
+        let mut renderer = Self::with_writer(io::stdout());
+        renderer.raw_mode = true;
+        renderer
+    }
+
+    /// Lines under the board for the info display (a blank line, level and
+    /// score, controls, status), plus one for per-player scores
+    pub fn info_height(players: usize) -> u16 {
+        if players > 1 {
+            5
+        } else {
+            4
+        }
+    }
+
+    /// Players over balls over trails over filled cells, within the
+    /// `block` x `block` cells starting at (x, y)
+    fn glyph(game: &Game, ball_positions: &HashSet<(i32, i32)>, x: i32, y: i32, block: i32) -> Glyph {
+        let xs = x..(x + block).min(game.width);
+        let ys = y..(y + block).min(game.height);
+        let contains = |px: i32, py: i32| xs.contains(&px) && ys.contains(&py);
+
+        // Knocked-out players aren't drawn
+        if let Some(idx) = game
+            .players
+            .iter()
+            .position(|player| player.alive && contains(player.position.x, player.position.y))
+        {
+            return Glyph::Player(idx);
+        }
+        if block == 1 {
+            if ball_positions.contains(&(x, y)) {
+                return Glyph::Ball;
+            }
+            return Glyph::Cell(game.cell_at(x, y), game.capture_speed_at(x, y));
+        }
+        if game.balls.iter().any(|ball| contains(ball.position.x, ball.position.y)) {
+            return Glyph::Ball;
+        }
+
+        let mut shown = Glyph::Cell(Cell::Empty, DrawSpeed::Fast);
+        for cy in ys.clone() {
+            for cx in xs.clone() {
+                match game.cell_at(cx, cy) {
+                    Cell::Trail => return Glyph::Cell(Cell::Trail, DrawSpeed::Fast),
+                    Cell::Filled if matches!(shown, Glyph::Cell(Cell::Empty, _)) => {
+                        shown = Glyph::Cell(Cell::Filled, game.capture_speed_at(cx, cy));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        shown
+    }
+}
+
+impl<W: Write> CliRenderer<W> {
+    /// A renderer writing to `out`. Raw mode is off, since `out` needn't be
+    /// the local terminal, and frames aren't clipped until
+    /// `set_terminal_size` says how big the screen is.
+    pub fn with_writer(out: W) -> Self {
         Self {
-            last_render: Instant::now(),
+            out,
+            raw_mode: false,
+            last_render: None,
             // Target 30 FPS for smooth rendering
             target_frame_time: Duration::from_millis(33),
+            frame: Vec::new(),
+            status: None,
+            active: false,
+            menu: None,
+            bindings: KeyBindings::default(),
+            board_fit: BoardFit::Letterbox,
+            theme: Theme::default(),
+            density: Density::Normal,
+            mouse: false,
+            buttons: Vec::new(),
+            terminal_size: None,
+            last_layout: None,
+            front: Vec::new(),
+            last_menu: Vec::new(),
+            last_info: Vec::new(),
+        }
+    }
+
+    /// Whether `init` puts the local terminal in raw mode. Turn it off where
+    /// that fails, e.g. when stdout is a CI log rather than a terminal; the
+    /// keyboard is then read only if there's a terminal to read it from.
+    pub fn set_raw_mode(&mut self, raw_mode: bool) {
+        self.raw_mode = raw_mode;
+    }
+
+    pub fn writer(&self) -> &W {
+        &self.out
+    }
+
+    pub fn writer_mut(&mut self) -> &mut W {
+        &mut self.out
+    }
+
+    /// Show `status` under the controls while the game is in progress
+    pub fn set_status(&mut self, status: Option<String>) {
+        self.status = status;
+    }
+
+    /// Show `menu` over the board while the game is paused
+    pub fn set_menu(&mut self, menu: Option<PauseMenu>) {
+        self.menu = menu;
+    }
+
+    pub fn set_key_bindings(&mut self, bindings: KeyBindings) {
+        self.bindings = bindings;
+    }
+
+    pub fn key_bindings(&self) -> &KeyBindings {
+        &self.bindings
+    }
+
+    /// For the pause menu to rebind keys in
+    pub fn key_bindings_mut(&mut self) -> &mut KeyBindings {
+        &mut self.bindings
+    }
+
+    pub fn set_board_fit(&mut self, board_fit: BoardFit) {
+        self.board_fit = board_fit;
+    }
+
+    /// Draw with `theme` from the next frame on
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.invalidate();
+    }
+
+    /// Show several board cells per character from the next frame on
+    pub fn set_density(&mut self, density: Density) {
+        self.density = density;
+        self.invalidate();
+    }
+
+    /// Take mouse input from `init` on. Off by default, since capturing the
+    /// mouse stops the terminal from selecting text.
+    pub fn set_mouse(&mut self, mouse: bool) {
+        self.mouse = mouse;
+    }
+
+    /// Lay frames out for a terminal this size. Set from the real terminal
+    /// by `init` and on resize; frames composed without one aren't clipped.
+    pub fn set_terminal_size(&mut self, size: Option<(u16, u16)>) {
+        self.terminal_size = size;
+    }
+
+    fn layout(&self, game: &Game) -> Option<Layout> {
+        Layout::fit(
+            game.width,
+            game.height,
+            CliRenderer::info_height(game.players.len()),
+            self.density.char_width(self.theme),
+            self.density.cells_per_char(),
+            self.terminal_size,
+            self.board_fit,
+        )
+    }
+
+    /// Whether `game`'s board can be shown in the terminal as it is now
+    pub fn fits(&self, game: &Game) -> bool {
+        self.layout(game).is_some()
+    }
+
+    /// Forget what's on screen, so the next frame is drawn in full
+    pub fn invalidate(&mut self) {
+        self.last_layout = None;
+    }
+
+    /// Queue the next frame into `stdout` without flushing. Only what changed
+    /// since the last frame is written: board cells that differ, and the
+    /// menu and info lines if their text does. A layout change clears the
+    /// screen and draws everything.
+    pub fn compose_frame(&mut self, game: &Game, stdout: &mut impl Write) -> io::Result<()> {
+        let layout = self.layout(game);
+        if self.last_layout != Some(layout) {
+            queue!(stdout, ResetColor, terminal::Clear(ClearType::All))?;
+            self.last_layout = Some(layout);
+            self.front.clear();
+            self.last_menu.clear();
+            self.last_info.clear();
+        }
+        let Some(layout) = layout else {
+            self.buttons.clear();
+            return self.draw_too_small(game, stdout);
+        };
+        self.front.resize((layout.cols * layout.rows) as usize, None);
+
+        let mut menu = Vec::new();
+        if let (GameState::Paused, Some(pause_menu)) = (game.state, &self.menu) {
+            self.draw_menu(pause_menu, game, &layout, &mut menu)?;
+        }
+        // The old menu covered part of the board; repaint it from scratch
+        if !self.last_menu.is_empty() && menu != self.last_menu {
+            self.front.fill(None);
+        }
+
+        // Pre-compute ball positions for O(1) lookup (avoids O(n) search per cell)
+        let ball_positions: HashSet<(i32, i32)> = game.balls
+            .iter()
+            .map(|ball| (ball.position.x, ball.position.y))
+            .collect();
+
+        // Draw the board cells that changed, moving the cursor only to skip over the rest
+        let mut pen = Pen::default();
+        let mut cursor_at = None;
+        let mut board_changed = false;
+        for row in 0..layout.rows {
+            for col in 0..layout.cols {
+                let drawn = self.drawn(game, &ball_positions, &layout, col, row);
+                let front = &mut self.front[(row * layout.cols + col) as usize];
+                if *front == Some(drawn) {
+                    continue;
+                }
+                *front = Some(drawn);
+                board_changed = true;
+
+                let at = (layout.left + (col as u16) * layout.cell_width, layout.top + row as u16);
+                if cursor_at != Some(at) {
+                    queue!(stdout, cursor::MoveTo(at.0, at.1))?;
+                }
+                self.draw_char(drawn, layout.cell_width, &mut pen, stdout)?;
+                cursor_at = Some((at.0 + layout.cell_width, at.1));
+            }
+        }
+        if board_changed {
+            queue!(stdout, ResetColor)?;
+        }
+
+        if !menu.is_empty() && (board_changed || menu != self.last_menu) {
+            stdout.write_all(&menu)?;
+        }
+        self.last_menu = menu;
+
+        let mut info = Vec::new();
+        let mut buttons = Vec::new();
+        self.draw_info(game, &layout, &mut buttons, &mut info)?;
+        self.buttons = buttons;
+        if info != self.last_info {
+            stdout.write_all(&info)?;
+            self.last_info = info;
+        }
+        Ok(())
+    }
+
+    /// The character at `col`, `row` of `layout`, per the density
+    fn drawn(&self, game: &Game, ball_positions: &HashSet<(i32, i32)>, layout: &Layout, col: i32, row: i32) -> Drawn {
+        let (across, down) = layout.per_char;
+        // Cells below or right of the board are left blank
+        let sub_glyph = |dx: i32, dy: i32| {
+            let x = (col * across + dx) * layout.block;
+            let y = (row * down + dy) * layout.block;
+            if x < game.width && y < game.height {
+                CliRenderer::glyph(game, ball_positions, x, y, layout.block)
+            } else {
+                Glyph::Cell(Cell::Empty, DrawSpeed::Fast)
+            }
+        };
+        match self.density {
+            Density::Normal => Drawn::Glyph(sub_glyph(0, 0)),
+            Density::HalfBlock => Drawn::HalfBlock(sub_glyph(0, 0), sub_glyph(0, 1)),
+            Density::Braille => {
+                // Dots 1-3 and 7 run down the left column, 4-6 and 8 down the right
+                const DOTS: [[u8; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+                let glyphs: [(Glyph, u8); 8] = std::array::from_fn(|idx| {
+                    let (dx, dy) = (idx % 2, idx / 2);
+                    (sub_glyph(dx as i32, dy as i32), DOTS[dx][dy])
+                });
+                if let Some(&(player, _)) = glyphs.iter().find(|(glyph, _)| matches!(glyph, Glyph::Player(_))) {
+                    return Drawn::Glyph(player);
+                }
+                if glyphs.iter().any(|(glyph, _)| *glyph == Glyph::Ball) {
+                    return Drawn::Glyph(Glyph::Ball);
+                }
+                let Some(&(shown @ Glyph::Cell(kind, _), _)) = glyphs
+                    .iter()
+                    .find(|(glyph, _)| matches!(glyph, Glyph::Cell(Cell::Trail, _)))
+                    .or_else(|| glyphs.iter().find(|(glyph, _)| matches!(glyph, Glyph::Cell(Cell::Filled, _))))
+                else {
+                    return Drawn::Glyph(Glyph::Cell(Cell::Empty, DrawSpeed::Fast));
+                };
+                let dots = glyphs
+                    .iter()
+                    .filter(|(glyph, _)| matches!(glyph, Glyph::Cell(cell, _) if *cell == kind))
+                    .fold(0, |dots, (_, dot)| dots | dot);
+                Drawn::Braille(shown, dots)
+            }
         }
     }
 
-    fn draw_cell(&self, cell: Cell, stdout: &mut io::Stdout) -> io::Result<()> {
-        match cell {
-            Cell::Empty => {
-                queue!(stdout, SetBackgroundColor(Color::Black), Print("  "))?;
+    /// The color `glyph` is known by in the theme, if it has colors
+    fn glyph_color(&self, glyph: Glyph) -> Option<Color> {
+        match glyph {
+            Glyph::Player(idx) => self.theme.player_color(idx),
+            Glyph::Ball => self.theme.ball_color(),
+            Glyph::Cell(cell, speed) => self.theme.cell_color(cell, speed),
+        }
+    }
+
+    fn draw_char(&self, drawn: Drawn, cell_width: u16, pen: &mut Pen, stdout: &mut impl Write) -> io::Result<()> {
+        match drawn {
+            Drawn::Glyph(glyph) => self.draw_glyph(glyph, cell_width, pen, stdout),
+            Drawn::HalfBlock(top, bottom) if self.theme.palette() != Palette::Mono => {
+                pen.set(self.glyph_color(bottom), self.glyph_color(top), stdout)?;
+                queue!(stdout, Print('▀'))
             }
-            Cell::Filled => {
-                queue!(stdout, SetBackgroundColor(Color::Blue), Print("  "))?;
+            // Without colors only shapes are left: a player, ball or trail
+            // takes the whole character, in that order
+            Drawn::HalfBlock(top, bottom) => {
+                let rank = |glyph: Glyph| match glyph {
+                    Glyph::Player(_) => 0,
+                    Glyph::Ball => 1,
+                    Glyph::Cell(Cell::Trail, _) => 2,
+                    Glyph::Cell(..) => 3,
+                };
+                let first = if rank(bottom) < rank(top) { bottom } else { top };
+                if rank(first) < 3 {
+                    return self.draw_glyph(first, cell_width, pen, stdout);
+                }
+                let filled = |glyph| matches!(glyph, Glyph::Cell(Cell::Filled, _));
+                let text = match (filled(top), filled(bottom)) {
+                    (false, false) => ' ',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (true, true) => '█',
+                };
+                queue!(stdout, Print(text))
             }
-            Cell::Trail => {
-                queue!(stdout, SetBackgroundColor(Color::Yellow), Print("  "))?;
+            Drawn::Braille(glyph, dots) => {
+                pen.set(self.theme.cell_color(Cell::Empty, DrawSpeed::Fast), self.glyph_color(glyph), stdout)?;
+                let text = char::from_u32(0x2800 + dots as u32).unwrap_or(' ');
+                queue!(stdout, Print(text))
             }
         }
+    }
+
+    fn draw_glyph(&self, glyph: Glyph, cell_width: u16, pen: &mut Pen, stdout: &mut impl Write) -> io::Result<()> {
+        let wide = cell_width == 2;
+        let style = match glyph {
+            Glyph::Player(idx) => self.theme.player(idx, wide),
+            Glyph::Ball => self.theme.ball(wide),
+            Glyph::Cell(cell, speed) => self.theme.cell(cell, speed, wide),
+        };
+        pen.set(style.background, style.foreground, stdout)?;
+        queue!(stdout, Print(style.text))
+    }
+
+    /// Shown instead of the board when even the smallest layout won't fit
+    fn draw_too_small(&self, game: &Game, stdout: &mut impl Write) -> io::Result<()> {
+        let (term_width, term_height) = self.terminal_size.unwrap_or((0, 0));
+        let (across, down) = self.density.cells_per_char();
+        let need_width = match self.board_fit {
+            BoardFit::Letterbox => (game.width + across - 1) / across * self.density.char_width(self.theme) as i32,
+            BoardFit::Scale => 1,
+        };
+        let need_height = match self.board_fit {
+            BoardFit::Letterbox => (game.height + down - 1) / down,
+            BoardFit::Scale => 1,
+        } + CliRenderer::info_height(game.players.len()) as i32;
+        let lines = [
+            "Terminal too small".to_string(),
+            format!("need {}x{}, have {}x{}", need_width, need_height, term_width, term_height),
+            "Game paused - enlarge the window".to_string(),
+        ];
+        let top = term_height.saturating_sub(lines.len() as u16) / 2;
+        for (row, line) in lines.iter().enumerate() {
+            let line = clip(line, term_width);
+            let left = term_width.saturating_sub(line.len() as u16) / 2;
+            queue!(stdout, cursor::MoveTo(left, top + row as u16))?;
+            Self::print_in(self.theme.highlight(), line, stdout)?;
+        }
         Ok(())
     }
 
-    fn draw_info(&self, game: &Game, stdout: &mut io::Stdout) -> io::Result<()> {
+    /// A box centered on the board: the title, then one line per item
+    fn draw_menu(&self, menu: &PauseMenu, game: &Game, layout: &Layout, stdout: &mut impl Write) -> io::Result<()> {
+        // Title, a gap and the notice take three rows; long pages scroll
+        let visible = menu.visible_items((layout.rows as usize).saturating_sub(3));
+        let labels: Vec<String> = menu.items()[visible.clone()]
+            .iter()
+            .map(|&item| PauseMenu::label(item, game, &self.bindings))
+            .collect();
+        let notice = menu.notice();
+        let inner = labels
+            .iter()
+            .map(|label| label.chars().count() + 4)
+            .chain([menu.title().len() + 4, notice.as_ref().map_or(0, |notice| notice.chars().count() + 2)])
+            .max()
+            .unwrap_or(0);
+        let board_width = (layout.cols * layout.cell_width as i32) as usize;
+        let left = layout.left as usize + board_width.saturating_sub(inner + 2) / 2;
+        let height = labels.len() + 2 + notice.is_some() as usize;
+        let top = layout.top as usize + (layout.rows as usize).saturating_sub(height) / 2;
+
+        let mut lines = vec![format!("{:^width$}", menu.title(), width = inner), String::new()];
+        for (idx, label) in visible.zip(&labels) {
+            let marker = if idx == menu.selected() { '>' } else { ' ' };
+            lines.push(format!(" {} {:<width$}", marker, label, width = inner - 3));
+        }
+        if let Some(notice) = notice {
+            lines.push(format!(" {:<width$}", notice, width = inner - 1));
+        }
+
+        if let Some(background) = self.theme.menu() {
+            queue!(stdout, SetBackgroundColor(background), SetForegroundColor(Color::Black))?;
+        }
+        for (row, line) in lines.iter().enumerate() {
+            queue!(
+                stdout,
+                cursor::MoveTo(left as u16, (top + row) as u16),
+                Print(format!(" {:<width$} ", line, width = inner))
+            )?;
+        }
+        queue!(stdout, ResetColor)
+    }
+
+    /// `text` in `color`, or as it comes in a theme without colors
+    fn print_in(color: Option<Color>, text: &str, stdout: &mut impl Write) -> io::Result<()> {
+        match color {
+            Some(color) => queue!(stdout, SetForegroundColor(color), Print(text), ResetColor),
+            None => queue!(stdout, Print(text)),
+        }
+    }
+
+    /// `[label]` buttons from `left` on `row`, as far as `width` allows;
+    /// notes each in `buttons` and returns the column after the last
+    fn draw_buttons(
+        labels: &[(&str, Input)],
+        (left, row, width): (u16, u16, u16),
+        buttons: &mut Vec<Button>,
+        stdout: &mut impl Write,
+    ) -> io::Result<u16> {
+        let mut column = left;
+        for &(label, input) in labels {
+            let text = format!("[{}]", label);
+            let end = column + text.len() as u16;
+            if end > left.saturating_add(width) {
+                break;
+            }
+            queue!(
+                stdout,
+                cursor::MoveTo(column, row),
+                SetAttribute(Attribute::Reverse),
+                Print(&text),
+                SetAttribute(Attribute::NoReverse),
+                Print(" ")
+            )?;
+            buttons.push(Button { row, columns: column..end, input });
+            column = end + 1;
+        }
+        Ok(column)
+    }
+
+    fn draw_info(
+        &self,
+        game: &Game,
+        layout: &Layout,
+        buttons: &mut Vec<Button>,
+        stdout: &mut impl Write,
+    ) -> io::Result<()> {
+        let left = layout.left;
+        let width = layout.info_width;
+        let mut row = layout.top + layout.rows as u16 + 1;
+        let player = &game.players[0];
+        let info = format!(
+            "Level: {}  Score: {}  Filled: {:.1}%  Target: {:.0}%  Draw: {}{}",
+            game.level,
+            game.score,
+            game.filled_percentage * 100.0,
+            game.target_percentage * 100.0,
+            player.draw_speed.label(),
+            if player.holding { "  HOLD" } else { "" }
+        );
         queue!(
             stdout,
-            cursor::MoveTo(0, (game.height + 1) as u16),
+            cursor::MoveTo(left, row),
             ResetColor,
-            Print(format!(
-                "Level: {}  Score: {}  Filled: {:.1}%  Target: {:.0}%",
-                game.level,
-                game.score,
-                game.filled_percentage * 100.0,
-                game.target_percentage * 100.0
-            ))
+            Print(clip(&info, width)),
+            terminal::Clear(ClearType::UntilNewLine)
         )?;
 
+        // One score per player when sharing the board
+        if game.players.len() > 1 {
+            row += 1;
+            queue!(stdout, cursor::MoveTo(left, row))?;
+            let mut room = width;
+            for (idx, player) in game.players.iter().enumerate() {
+                let score = format!(
+                    "P{}: {}{}  ",
+                    idx + 1,
+                    player.score,
+                    if player.alive { "" } else { " (OUT)" }
+                );
+                let shown = clip(&score, room);
+                room -= shown.len() as u16;
+                Self::print_in(self.theme.player_color(idx), shown, stdout)?;
+            }
+            queue!(stdout, terminal::Clear(ClearType::UntilNewLine))?;
+        }
+
+        row += 1;
+        let keys = &self.bindings;
+        let mut controls = format!(
+            "Controls: {} to move | {} hold | {} slow draw (2x points) | {} pause | {} to quit | {} to restart",
+            keys.describe_moves(0),
+            keys.describe(Input::Hold),
+            keys.describe(Input::ToggleSlowDraw),
+            keys.describe(Input::Pause),
+            keys.describe(Input::Quit),
+            keys.describe(Input::Restart)
+        );
+        if game.players.len() > 1 {
+            controls.push_str(&format!(" | P2: {}", keys.describe_moves(1)));
+        }
+        if game.movement == MovementMode::EightWay {
+            let diagonals = [Direction::UpLeft, Direction::UpRight, Direction::DownLeft, Direction::DownRight]
+                .map(|direction| keys.describe(Input::Direction(direction)));
+            controls.push_str(&format!(" | {} diagonals", diagonals.join("/")));
+        }
+        if self.mouse {
+            controls.push_str(" | Click to steer");
+        }
         queue!(
             stdout,
-            cursor::MoveTo(0, (game.height + 2) as u16),
-            Print("Controls: Arrow Keys to move | Q to quit | R to restart")
+            cursor::MoveTo(left, row),
+            Print(clip(&controls, width)),
+            terminal::Clear(ClearType::UntilNewLine)
         )?;
 
+        row += 1;
         match game.state {
             GameState::Won => {
-                queue!(
-                    stdout,
-                    cursor::MoveTo(0, (game.height + 3) as u16),
-                    SetForegroundColor(Color::Green),
-                    Print("YOU WIN! Press SPACE for next level or R to restart"),
-                    ResetColor
-                )?;
+                let headline = match game.target_mode {
+                    TargetMode::Competitive => match game.winner() {
+                        Some(idx) => format!("PLAYER {} WINS!", idx + 1),
+                        None => "DRAW!".to_string(),
+                    },
+                    TargetMode::Shared => "YOU WIN!".to_string(),
+                };
+                let message = format!(
+                    "{} Press {} for next level or {} to restart",
+                    headline,
+                    keys.describe(Input::NextLevel).to_uppercase(),
+                    keys.describe(Input::Restart)
+                );
+                let mut start = left;
+                if self.mouse {
+                    let labels = [("Next level", Input::NextLevel), ("Restart", Input::Restart)];
+                    start = Self::draw_buttons(&labels, (left, row, width), buttons, stdout)?;
+                }
+                queue!(stdout, cursor::MoveTo(start, row))?;
+                Self::print_in(self.theme.won(), clip(&message, width.saturating_sub(start - left)), stdout)?;
+                queue!(stdout, terminal::Clear(ClearType::UntilNewLine))?;
             }
             GameState::Lost => {
-                queue!(
-                    stdout,
-                    cursor::MoveTo(0, (game.height + 3) as u16),
-                    SetForegroundColor(Color::Red),
-                    Print("GAME OVER! Press R to restart"),
-                    ResetColor
-                )?;
+                let mut start = left;
+                if self.mouse {
+                    start = Self::draw_buttons(&[("Restart", Input::Restart)], (left, row, width), buttons, stdout)?;
+                }
+                queue!(stdout, cursor::MoveTo(start, row))?;
+                let message = format!("GAME OVER! Press {} to restart", keys.describe(Input::Restart));
+                let message = clip(&message, width.saturating_sub(start - left));
+                Self::print_in(self.theme.lost(), message, stdout)?;
+                queue!(stdout, terminal::Clear(ClearType::UntilNewLine))?;
+            }
+            GameState::Paused => {
+                queue!(stdout, cursor::MoveTo(left, row))?;
+                let message = format!(
+                    "PAUSED - {}/{} and {} to choose, {} to resume",
+                    keys.describe(Input::Direction(Direction::Up)),
+                    keys.describe(Input::Direction(Direction::Down)),
+                    keys.describe(Input::Confirm),
+                    keys.describe(Input::Pause).replace('/', " or ")
+                );
+                Self::print_in(self.theme.highlight(), clip(&message, width), stdout)?;
+                queue!(stdout, terminal::Clear(ClearType::UntilNewLine))?;
+            }
+            GameState::Playing => {
+                queue!(stdout, cursor::MoveTo(left, row))?;
+                if let Some(status) = &self.status {
+                    Self::print_in(self.theme.highlight(), clip(status, width), stdout)?;
+                }
+                queue!(stdout, terminal::Clear(ClearType::UntilNewLine))?;
             }
-            GameState::Playing => {}
         }
 
         Ok(())
     }
 }
 
-impl Renderer for CliRenderer {
+impl<W: Write> CliRenderer<W> {
+    /// The board cell under terminal `column`, `row` in the last frame; the
+    /// middle of the block when a character shows several cells
+    fn board_cell_at(&self, column: u16, row: u16) -> Option<Position> {
+        let Some(Some(layout)) = self.last_layout else {
+            return None;
+        };
+        if column < layout.left || row < layout.top {
+            return None;
+        }
+        let col = ((column - layout.left) / layout.cell_width) as i32;
+        let row = (row - layout.top) as i32;
+        if col >= layout.cols || row >= layout.rows {
+            return None;
+        }
+        let (across, down) = layout.per_char;
+        Some(Position::new(
+            (col * across * layout.block) + across * layout.block / 2,
+            (row * down * layout.block) + down * layout.block / 2,
+        ))
+    }
+
+    fn mouse_input(&self, mouse: MouseEvent) -> Option<Input> {
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => self
+                .buttons
+                .iter()
+                .find(|button| button.row == mouse.row && button.columns.contains(&mouse.column))
+                .map(|button| button.input)
+                .or_else(|| self.board_cell_at(mouse.column, mouse.row).map(Input::MoveToward)),
+            // The wheel only scrolls the pause menu; it never steers
+            MouseEventKind::ScrollUp if self.menu.is_some() => Some(Input::Direction(Direction::Up)),
+            MouseEventKind::ScrollDown if self.menu.is_some() => Some(Input::Direction(Direction::Down)),
+            _ => None,
+        }
+    }
+}
+
+impl<W: Write> Renderer for CliRenderer<W> {
     fn init(&mut self) -> io::Result<()> {
-        terminal::enable_raw_mode()?;
-        let mut stdout = io::stdout();
+        if self.raw_mode {
+            terminal::enable_raw_mode()?;
+            self.terminal_size = Some(terminal::size()?);
+        }
+        self.active = true;
+        self.invalidate();
         execute!(
-            stdout,
+            self.out,
             terminal::EnterAlternateScreen,
             terminal::Clear(ClearType::All),
-            cursor::Hide
+            cursor::Hide,
+            // Reports focus changes so the game can pause when the terminal loses focus
+            event::EnableFocusChange
         )?;
+        if self.mouse {
+            execute!(self.out, event::EnableMouseCapture)?;
+        }
         Ok(())
     }
 
     fn render(&mut self, game: &Game) -> io::Result<()> {
         // Frame rate limiting: skip rendering if not enough time has passed
-        if self.last_render.elapsed() < self.target_frame_time {
+        if self.last_render.is_some_and(|last| last.elapsed() < self.target_frame_time) {
             return Ok(());
         }
 
-        self.last_render = Instant::now();
-
-        let mut stdout = io::stdout();
+        self.last_render = Some(Instant::now());
 
-        queue!(stdout, cursor::MoveTo(0, 0))?;
-
-        // Pre-compute ball positions for O(1) lookup (avoids O(n) search per cell)
-        let ball_positions: HashSet<(i32, i32)> = game.balls
-            .iter()
-            .map(|ball| (ball.position.x, ball.position.y))
-            .collect();
-
-        // Draw board
-        for y in 0..game.height {
-            for x in 0..game.width {
-                let cell = game.cell_at(x, y);
-
-                // Check if this is the player position
-                if game.player.position.x == x && game.player.position.y == y {
-                    queue!(
-                        stdout,
-                        SetBackgroundColor(Color::Green),
-                        SetForegroundColor(Color::Black),
-                        Print("@@")
-                    )?;
-                    continue;
-                }
-
-                // Check if this is a ball position (O(1) lookup instead of O(n) search)
-                if ball_positions.contains(&(x, y)) {
-                    queue!(
-                        stdout,
-                        SetBackgroundColor(Color::Black),
-                        SetForegroundColor(Color::Red),
-                        Print("()"),
-                        ResetColor
-                    )?;
-                    continue;
-                }
-
-                self.draw_cell(cell, &mut stdout)?;
-            }
-            queue!(stdout, ResetColor, Print("\r\n"))?;
-        }
-
-        // Draw info
-        self.draw_info(game, &mut stdout)?;
-
-        stdout.flush()?;
-        Ok(())
+        // One write per frame rather than one per escape code
+        let mut frame = std::mem::take(&mut self.frame);
+        frame.clear();
+        self.compose_frame(game, &mut frame)?;
+        self.out.write_all(&frame)?;
+        self.frame = frame;
+        self.out.flush()
     }
 
     fn cleanup(&mut self) -> io::Result<()> {
-        let mut stdout = io::stdout();
+        if self.mouse {
+            execute!(self.out, event::DisableMouseCapture)?;
+        }
         execute!(
-            stdout,
+            self.out,
+            event::DisableFocusChange,
             cursor::Show,
             terminal::LeaveAlternateScreen,
             ResetColor
         )?;
-        terminal::disable_raw_mode()?;
+        if self.raw_mode {
+            terminal::disable_raw_mode()?;
+        }
+        self.active = false;
         Ok(())
     }
 
     fn poll_input(&mut self) -> io::Result<Option<Input>> {
-        if event::poll(Duration::from_millis(50))? {
-            if let Event::Key(KeyEvent { code, .. }) = event::read()? {
-                match code {
-                    KeyCode::Char('q') | KeyCode::Char('Q') => {
-                        return Ok(Some(Input::Quit));
-                    }
-                    KeyCode::Char('r') | KeyCode::Char('R') => {
-                        return Ok(Some(Input::Restart));
-                    }
-                    KeyCode::Char(' ') => {
-                        return Ok(Some(Input::NextLevel));
-                    }
-                    KeyCode::Up => return Ok(Some(Input::Direction(Direction::Up))),
-                    KeyCode::Down => return Ok(Some(Input::Direction(Direction::Down))),
-                    KeyCode::Left => return Ok(Some(Input::Direction(Direction::Left))),
-                    KeyCode::Right => return Ok(Some(Input::Direction(Direction::Right))),
-                    _ => {}
+        let poll_interval = Duration::from_millis(50);
+        let ready = match event::poll(poll_interval) {
+            Ok(ready) => ready,
+            // No terminal to read keys from, which is fine without raw mode
+            Err(_) if !self.raw_mode => {
+                std::thread::sleep(poll_interval);
+                false
+            }
+            Err(e) => return Err(e),
+        };
+        if ready {
+            let event = event::read()?;
+            if event == Event::FocusLost {
+                return Ok(Some(Input::FocusLost));
+            }
+            if let Event::Resize(columns, rows) = event {
+                self.terminal_size = Some((columns, rows));
+                return Ok(Some(Input::Resize));
+            }
+            if let Event::Mouse(mouse) = event {
+                return Ok(self.mouse_input(mouse));
+            }
+            if let Event::Key(KeyEvent { code, .. }) = event {
+                let Some(key) = key_for(code) else {
+                    return Ok(None);
+                };
+                if self.menu.is_some_and(|menu| menu.waiting_for_key()) {
+                    return Ok(Some(Input::Key(key)));
                 }
+                return Ok(self.bindings.input_for(key));
             }
         }
         Ok(None)
     }
 }
 
-impl Drop for CliRenderer {
+impl<W: Write> Drop for CliRenderer<W> {
     fn drop(&mut self) {
-        let _ = self.cleanup();
+        if self.active {
+            let _ = self.cleanup();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_letterbox_centers_or_gives_up() {
+        let layout = Layout::fit(20, 10, 4, 2, (1, 1), Some((60, 20)), BoardFit::Letterbox).unwrap();
+        assert_eq!((layout.left, layout.top), (10, 3));
+        assert_eq!((layout.cell_width, layout.block, layout.cols, layout.rows), (2, 1, 20, 10));
+
+        assert!(Layout::fit(20, 10, 4, 2, (1, 1), Some((39, 20)), BoardFit::Letterbox).is_none());
+        assert!(Layout::fit(20, 10, 4, 2, (1, 1), Some((60, 13)), BoardFit::Letterbox).is_none());
+    }
+
+    #[test]
+    fn test_scale_narrows_then_merges_cells() {
+        let narrow = Layout::fit(40, 10, 4, 2, (1, 1), Some((50, 20)), BoardFit::Scale).unwrap();
+        assert_eq!((narrow.cell_width, narrow.block, narrow.left), (1, 1, 5));
+
+        let merged = Layout::fit(40, 20, 4, 2, (1, 1), Some((40, 16)), BoardFit::Scale).unwrap();
+        assert_eq!((merged.cell_width, merged.block, merged.cols, merged.rows), (2, 2, 20, 10));
+
+        // Room for the info lines is the one hard limit
+        assert!(Layout::fit(40, 20, 4, 2, (1, 1), Some((30, 4)), BoardFit::Scale).is_none());
+    }
+
+    #[test]
+    fn test_too_small_terminal_shows_a_message() {
+        let game = Game::with_seed(30, 20, 1, 3);
+        let mut renderer = CliRenderer::new();
+        renderer.set_terminal_size(Some((40, 12)));
+        assert!(!renderer.fits(&game));
+
+        let mut frame = Vec::new();
+        renderer.compose_frame(&game, &mut frame).unwrap();
+        let frame = String::from_utf8(frame).unwrap();
+        assert!(frame.contains("Terminal too small"));
+        assert!(frame.contains("need 60x24, have 40x12"));
+
+        renderer.set_board_fit(BoardFit::Scale);
+        assert!(renderer.fits(&game));
+        let mut frame = Vec::new();
+        renderer.compose_frame(&game, &mut frame).unwrap();
+        assert!(!String::from_utf8(frame).unwrap().contains("too small"));
+    }
+
+    #[test]
+    fn test_merged_blocks_keep_players_and_balls() {
+        let mut game = Game::with_seed(30, 20, 1, 3);
+        game.balls = vec![crate::entity::Ball::new(7, 7, 1, 1)];
+        let positions = game.balls.iter().map(|ball| (ball.position.x, ball.position.y)).collect();
+
+        // The player starts at (0, 10), on the border
+        assert!(matches!(CliRenderer::glyph(&game, &positions, 0, 9, 3), Glyph::Player(0)));
+        assert!(matches!(CliRenderer::glyph(&game, &positions, 6, 6, 3), Glyph::Ball));
+        assert!(matches!(CliRenderer::glyph(&game, &positions, 0, 0, 3), Glyph::Cell(Cell::Filled, _)));
+        assert!(matches!(CliRenderer::glyph(&game, &positions, 12, 12, 3), Glyph::Cell(Cell::Empty, _)));
+    }
+
+    fn click(column: u16, row: u16) -> MouseEvent {
+        MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column,
+            row,
+            modifiers: event::KeyModifiers::NONE,
+        }
+    }
+
+    #[test]
+    fn test_clicks_name_board_cells_and_buttons() {
+        let mut game = Game::with_seed(20, 10, 1, 3);
+        let mut renderer = CliRenderer::with_writer(Vec::new());
+        renderer.set_mouse(true);
+        renderer.set_terminal_size(Some((60, 20)));
+        renderer.compose_frame(&game, &mut Vec::new()).unwrap();
+
+        // The board is letterboxed at (10, 3), two columns per cell
+        assert_eq!(renderer.mouse_input(click(10, 3)), Some(Input::MoveToward(Position::new(0, 0))));
+        assert_eq!(renderer.mouse_input(click(15, 7)), Some(Input::MoveToward(Position::new(2, 4))));
+        assert_eq!(renderer.mouse_input(click(9, 3)), None);
+        assert_eq!(renderer.mouse_input(click(50, 7)), None);
+
+        game.state = GameState::Won;
+        renderer.compose_frame(&game, &mut Vec::new()).unwrap();
+        // The message line, under the blank line, score and controls
+        let row = 3 + 10 + 3;
+        assert_eq!(renderer.mouse_input(click(10, row)), Some(Input::NextLevel));
+        assert_eq!(renderer.mouse_input(click(23, row)), Some(Input::Restart));
+        assert_eq!(renderer.mouse_input(click(40, row)), None);
+    }
+
+    #[test]
+    fn test_wheel_only_scrolls_the_menu() {
+        let mut renderer = CliRenderer::with_writer(Vec::new());
+        let scroll = |kind| MouseEvent { kind, ..click(0, 0) };
+        assert_eq!(renderer.mouse_input(scroll(MouseEventKind::ScrollDown)), None);
+        renderer.set_menu(Some(PauseMenu::new()));
+        assert_eq!(
+            renderer.mouse_input(scroll(MouseEventKind::ScrollDown)),
+            Some(Input::Direction(Direction::Down))
+        );
+    }
+
+    #[test]
+    fn test_renders_into_any_writer() {
+        let game = Game::with_seed(30, 20, 1, 3);
+        let mut renderer = CliRenderer::with_writer(Vec::new());
+        renderer.init().unwrap();
+        renderer.render(&game).unwrap();
+        renderer.cleanup().unwrap();
+
+        let written = String::from_utf8_lossy(renderer.writer()).into_owned();
+        assert!(written.starts_with("\x1b[?1049h"), "no alternate screen: {:?}", &written[..20]);
+        assert!(written.contains("Level: 1"));
+        assert!(written.ends_with("\x1b[?1049l\x1b[0m"));
+
+        // Frames closer together than the frame rate are dropped
+        renderer.writer_mut().clear();
+        renderer.render(&game).unwrap();
+        assert!(renderer.writer().is_empty());
+    }
+
+    #[test]
+    fn test_help_lines_name_the_bound_keys() {
+        let mut game = Game::with_seed(30, 20, 2, 3);
+        game.state = GameState::Lost;
+        let mut renderer = CliRenderer::with_writer(Vec::new());
+        renderer.set_key_bindings(KeyBindings::preset("wasd").unwrap());
+        renderer.key_bindings_mut().set_keys(Input::Restart, &[Key::Char('n')]).unwrap();
+        let mut frame = Vec::new();
+        renderer.compose_frame(&game, &mut frame).unwrap();
+        let written = String::from_utf8_lossy(&frame);
+        assert!(written.contains("Controls: Arrow Keys or W/A/S/D to move"), "{:?}", written);
+        assert!(written.contains("N to restart | P2: I/J/K/L"));
+        assert!(written.contains("GAME OVER! Press N to restart"));
+        assert_eq!(key_for(KeyCode::Char('N')), Some(Key::Char('n')));
+        assert_eq!(key_for(KeyCode::F(1)), None);
+    }
+
+    #[test]
+    fn test_mono_theme_writes_no_colors() {
+        let mut game = Game::with_seed(30, 20, 2, 3);
+        game.state = GameState::Lost;
+        let mut renderer = CliRenderer::with_writer(Vec::new());
+        renderer.set_theme(Theme::by_name("mono").unwrap());
+        let mut frame = Vec::new();
+        renderer.compose_frame(&game, &mut frame).unwrap();
+        let frame = String::from_utf8(frame).unwrap();
+        assert!(!frame.contains("[38;5;") && !frame.contains("[48;5;"), "colors in {:?}", frame);
+        assert!(frame.contains("##") && frame.contains("@1") && frame.contains("@2"));
+    }
+
+    #[test]
+    fn test_unicode_theme_packs_cells_into_one_column() {
+        let game = Game::with_seed(40, 10, 1, 3);
+        let mut renderer = CliRenderer::new();
+        renderer.set_terminal_size(Some((50, 20)));
+        assert!(!renderer.fits(&game));
+        renderer.set_theme(Theme::by_name("unicode").unwrap());
+        assert!(renderer.fits(&game));
+    }
+
+    #[test]
+    fn test_dense_modes_fit_bigger_boards() {
+        let game = Game::with_seed(80, 48, 1, 3);
+        let mut renderer = CliRenderer::new();
+        renderer.set_terminal_size(Some((80, 24)));
+        assert!(!renderer.fits(&game));
+        renderer.set_density(Density::HalfBlock);
+        assert!(!renderer.fits(&game));
+        renderer.set_density(Density::Braille);
+        let layout = renderer.layout(&game).unwrap();
+        assert_eq!((layout.cols, layout.rows, layout.cell_width), (40, 12, 1));
+    }
+
+    #[test]
+    fn test_braille_shows_the_trail_over_filled_cells() {
+        let mut game = Game::with_seed(30, 20, 1, 3);
+        game.balls.clear();
+        // The player starts at (0, 10) on the border, one cell thick
+        game.set_direction(crate::entity::Direction::Right);
+        for _ in 0..5 {
+            game.update();
+        }
+        assert_eq!(game.cell_at(1, 10), Cell::Trail);
+
+        let mut renderer = CliRenderer::new();
+        renderer.set_density(Density::Braille);
+        let layout = renderer.layout(&game).unwrap();
+        let positions = HashSet::new();
+        let trail = Glyph::Cell(Cell::Trail, DrawSpeed::Fast);
+        // Cells 0-1 across and 8-11 down: the trail hides the border's dots
+        assert_eq!(renderer.drawn(&game, &positions, &layout, 0, 2), Drawn::Braille(trail, 0x20));
+        assert_eq!(renderer.drawn(&game, &positions, &layout, 1, 2), Drawn::Braille(trail, 0x24));
+        // The top-left corner: the top row and left column of border
+        assert_eq!(
+            renderer.drawn(&game, &positions, &layout, 0, 0),
+            Drawn::Braille(Glyph::Cell(Cell::Filled, DrawSpeed::Fast), 0x4f)
+        );
+        // The player at (5, 10) covers the rest of their character
+        assert_eq!(renderer.drawn(&game, &positions, &layout, 2, 2), Drawn::Glyph(Glyph::Player(0)));
+    }
+
+    #[test]
+    fn test_half_blocks_without_color_keep_players_and_balls() {
+        let mut game = Game::with_seed(30, 20, 1, 3);
+        game.balls = vec![crate::entity::Ball::new(7, 7, 1, 1)];
+        let mut renderer = CliRenderer::with_writer(Vec::new());
+        renderer.set_theme(Theme::by_name("mono").unwrap());
+        renderer.set_density(Density::HalfBlock);
+        renderer.init().unwrap();
+        renderer.render(&game).unwrap();
+        let frame = String::from_utf8(renderer.writer().clone()).unwrap();
+        assert!(frame.contains('1') && frame.contains('o') && frame.contains('█'));
+    }
+
+    /// Bytes `renderer` writes for the next frame of `game`
+    fn frame_bytes<W: Write>(renderer: &mut CliRenderer<W>, game: &Game) -> usize {
+        let mut frame = Vec::new();
+        renderer.compose_frame(game, &mut frame).unwrap();
+        frame.len()
+    }
+
+    #[test]
+    fn test_only_changed_cells_are_written() {
+        let mut game = Game::with_seed(60, 30, 1, 5);
+        let mut renderer = CliRenderer::new();
+        let full = frame_bytes(&mut renderer, &game);
+        assert_eq!(frame_bytes(&mut renderer, &game), 0);
+
+        // A typical tick moves the player and a few balls
+        game.set_direction(crate::entity::Direction::Right);
+        game.update();
+        let tick = frame_bytes(&mut renderer, &game);
+        assert!(tick > 0);
+        assert!(tick * 10 < full, "tick wrote {} bytes, full frame {}", tick, full);
+
+        renderer.invalidate();
+        assert!(frame_bytes(&mut renderer, &game) >= full);
+    }
+
+    #[test]
+    fn test_closing_the_menu_repaints_under_it() {
+        let mut game = Game::with_seed(30, 20, 1, 3);
+        let mut renderer = CliRenderer::new();
+        let full = frame_bytes(&mut renderer, &game);
+
+        game.pause();
+        renderer.set_menu(Some(PauseMenu::new()));
+        assert!(frame_bytes(&mut renderer, &game) > 0);
+        assert_eq!(frame_bytes(&mut renderer, &game), 0);
+
+        game.resume();
+        renderer.set_menu(None);
+        assert!(frame_bytes(&mut renderer, &game) * 2 > full);
+    }
+
+    #[test]
+    fn test_shorter_info_lines_clear_what_was_there() {
+        let mut game = Game::with_seed(30, 20, 2, 3);
+        game.players[0].score = 12345;
+        let mut renderer = CliRenderer::new();
+        renderer.set_theme(Theme::by_name("mono").unwrap());
+        frame_bytes(&mut renderer, &game);
+
+        game.players[0].score = 7;
+        game.state = GameState::Lost;
+        let mut frame = Vec::new();
+        renderer.compose_frame(&game, &mut frame).unwrap();
+        let frame = String::from_utf8(frame).unwrap();
+        assert!(frame.contains("P1: 7  P2: 0  \x1b[K"), "{:?}", frame);
+        assert!(frame.contains("to restart\x1b[K"), "{:?}", frame);
     }
 }
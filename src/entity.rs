@@ -1,4 +1,6 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
@@ -10,12 +12,8 @@ impl Position {
     }
 
     pub fn moved(&self, direction: Direction) -> Self {
-        match direction {
-            Direction::Up => Position::new(self.x, self.y - 1),
-            Direction::Down => Position::new(self.x, self.y + 1),
-            Direction::Left => Position::new(self.x - 1, self.y),
-            Direction::Right => Position::new(self.x + 1, self.y),
-        }
+        let (dx, dy) = direction.delta();
+        Position::new(self.x + dx, self.y + dy)
     }
 }
 
@@ -25,6 +23,11 @@ pub enum Direction {
     Down,
     Left,
     Right,
+    // Diagonals, only accepted in `MovementMode::EightWay`
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
 }
 
 impl Direction {
@@ -34,6 +37,82 @@ impl Direction {
             Direction::Down => Direction::Up,
             Direction::Left => Direction::Right,
             Direction::Right => Direction::Left,
+            Direction::UpLeft => Direction::DownRight,
+            Direction::UpRight => Direction::DownLeft,
+            Direction::DownLeft => Direction::UpRight,
+            Direction::DownRight => Direction::UpLeft,
+        }
+    }
+
+    /// One-cell step as (dx, dy)
+    pub fn delta(&self) -> (i32, i32) {
+        match self {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+            Direction::UpLeft => (-1, -1),
+            Direction::UpRight => (1, -1),
+            Direction::DownLeft => (-1, 1),
+            Direction::DownRight => (1, 1),
+        }
+    }
+
+    pub fn is_diagonal(&self) -> bool {
+        let (dx, dy) = self.delta();
+        dx != 0 && dy != 0
+    }
+}
+
+/// Which directions the player may move in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MovementMode {
+    /// Classic orthogonal movement
+    #[default]
+    FourWay,
+    /// Orthogonal plus diagonal movement
+    EightWay,
+}
+
+impl MovementMode {
+    pub fn allows(&self, direction: Direction) -> bool {
+        match self {
+            MovementMode::FourWay => !direction.is_diagonal(),
+            MovementMode::EightWay => true,
+        }
+    }
+}
+
+/// How fast the player draws into empty space.
+///
+/// Slow draw advances only every other tick but captures are worth more (as in Qix).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DrawSpeed {
+    #[default]
+    Fast,
+    Slow,
+}
+
+impl DrawSpeed {
+    pub fn toggled(&self) -> DrawSpeed {
+        match self {
+            DrawSpeed::Fast => DrawSpeed::Slow,
+            DrawSpeed::Slow => DrawSpeed::Fast,
+        }
+    }
+
+    /// How info lines and menus name the speed
+    pub fn label(&self) -> &'static str {
+        match self {
+            DrawSpeed::Fast => "FAST",
+            DrawSpeed::Slow => "SLOW",
+        }
+    }
+
+    pub fn score_multiplier(&self) -> u32 {
+        match self {
+            DrawSpeed::Fast => 1,
+            DrawSpeed::Slow => 2,
         }
     }
 }
@@ -43,7 +122,22 @@ pub struct Player {
     pub position: Position,
     pub direction: Direction,
     pub trail: Vec<Position>,
+    /// Speed each `trail` cell was drawn at (same length as `trail`)
+    pub trail_speeds: Vec<DrawSpeed>,
+    /// Index of each `trail` cell, for constant-time hit tests on long trails
+    trail_steps: HashMap<Position, usize>,
     pub is_drawing: bool,
+    pub draw_speed: DrawSpeed,
+    slow_step_paused: bool,
+    /// Turns entered faster than the tick rate, applied one per move
+    pub turn_queue: VecDeque<Direction>,
+    /// A turn has been applied that the player hasn't moved on yet
+    pub turned_this_tick: bool,
+    /// Waiting in place on filled territory until the next turn
+    pub holding: bool,
+    pub score: u32,
+    /// False once knocked out in competitive play
+    pub alive: bool,
 }
 
 impl Player {
@@ -52,25 +146,83 @@ impl Player {
             position: Position::new(x, y),
             direction: Direction::Right,
             trail: Vec::new(),
+            trail_speeds: Vec::new(),
+            trail_steps: HashMap::new(),
             is_drawing: false,
+            draw_speed: DrawSpeed::Fast,
+            slow_step_paused: false,
+            turn_queue: VecDeque::new(),
+            turned_this_tick: false,
+            holding: false,
+            score: 0,
+            alive: true,
         }
     }
 
     pub fn start_trail(&mut self) {
         self.is_drawing = true;
         self.trail.clear();
-        self.trail.push(self.position);
+        self.trail_speeds.clear();
+        self.trail_steps.clear();
+        self.push_trail();
     }
 
     pub fn add_to_trail(&mut self) {
         if self.is_drawing {
-            self.trail.push(self.position);
+            self.push_trail();
         }
     }
 
+    fn push_trail(&mut self) {
+        self.trail_steps.insert(self.position, self.trail.len());
+        self.trail.push(self.position);
+        self.trail_speeds.push(self.draw_speed);
+    }
+
     pub fn clear_trail(&mut self) {
         self.trail.clear();
+        self.trail_speeds.clear();
+        self.trail_steps.clear();
         self.is_drawing = false;
+        self.slow_step_paused = false;
+    }
+
+    /// Whether the next slow-draw tick moves (false) or stays put (true)
+    pub fn slow_step_paused(&self) -> bool {
+        self.slow_step_paused
+    }
+
+    /// Advance the slow-draw cadence by one tick.
+    /// Returns true on the ticks where a slow-drawing player stays put.
+    pub fn skips_slow_step(&mut self) -> bool {
+        self.slow_step_paused = !self.slow_step_paused;
+        !self.slow_step_paused
+    }
+
+    /// True if a diagonal step from `from` to `to` slips between two consecutive
+    /// trail cells, i.e. crosses a diagonal segment of the trail without landing on it
+    pub fn trail_crosses(&self, from: Position, to: Position) -> bool {
+        if from.x == to.x || from.y == to.y {
+            return false;
+        }
+
+        let corner_a = self.trail_steps.get(&Position::new(to.x, from.y));
+        let corner_b = self.trail_steps.get(&Position::new(from.x, to.y));
+        match (corner_a, corner_b) {
+            (Some(&a), Some(&b)) => a.abs_diff(b) == 1,
+            _ => false,
+        }
+    }
+
+    /// True if `pos` is a cell of the current trail (including the filled
+    /// cell it started from)
+    pub fn trail_contains(&self, pos: Position) -> bool {
+        self.trail_steps.contains_key(&pos)
+    }
+
+    /// True if every cell of the current trail was drawn in slow mode
+    pub fn trail_is_slow(&self) -> bool {
+        !self.trail_speeds.is_empty() && self.trail_speeds.iter().all(|&speed| speed == DrawSpeed::Slow)
     }
 }
 
@@ -79,7 +231,7 @@ pub trait Enemy {
     fn update(&mut self, width: i32, height: i32, is_filled: &dyn Fn(i32, i32) -> bool);
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Ball {
     pub position: Position,
     pub velocity: (i32, i32),
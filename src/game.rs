@@ -1,5 +1,16 @@
-use crate::entity::{Ball, Direction, Player};
-use rand::Rng;
+use crate::board::{Board, CachedRegion, RegionScratch};
+use crate::capture::{CaptureRule, Region, XonixRule};
+use crate::entity::{Ball, Direction, DrawSpeed, MovementMode, Player, Position};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashSet;
+use std::fmt;
+
+/// Maximum number of buffered turns per player
+const TURN_QUEUE_CAPACITY: usize = 3;
+
+/// Most players that can share one board (one per edge)
+pub const MAX_PLAYERS: usize = 4;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Cell {
@@ -11,120 +22,376 @@ pub enum Cell {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GameState {
     Playing,
+    /// Frozen mid-game until resumed
+    Paused,
     Won,
     Lost,
 }
 
+/// How several players share the fill target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TargetMode {
+    /// Co-op: everyone fills toward one target and any crash ends the game
+    #[default]
+    Shared,
+    /// Players race for territory: a crashed player is knocked out, and the
+    /// highest score when the target is reached wins
+    Competitive,
+}
+
+/// How `Game` finds the empty regions left by a completed trail. Both give
+/// identical games; they differ only in speed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FillAlgorithm {
+    /// Scanline fill of just the regions the trail ran through, keeping the
+    /// rest from the previous capture
+    #[default]
+    Incremental,
+    /// Cell-by-cell flood fill of the whole board after every trail
+    FullScan,
+}
+
+/// `Game::spawn_balls` ran out of empty cells far enough from the players
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpawnShortfall {
+    pub requested: usize,
+    pub placed: usize,
+}
+
+impl fmt::Display for SpawnShortfall {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "only room for {} of {} balls", self.placed, self.requested)
+    }
+}
+
+impl std::error::Error for SpawnShortfall {}
+
 pub struct Game {
     pub width: i32,
     pub height: i32,
-    pub board: Vec<Vec<Cell>>,
-    pub player: Player,
+    /// Cells plus the draw speed each filled cell was captured at (borders count as fast)
+    pub board: Board,
+    pub players: Vec<Player>,
     pub balls: Vec<Ball>,
     pub state: GameState,
+    /// Combined score of all players
     pub score: u32,
     pub level: u32,
+    /// Ticks simulated since the game started, across levels
+    pub tick: u64,
     pub filled_percentage: f32,
     pub target_percentage: f32,
+    pub capture_rule: Box<dyn CaptureRule>,
+    pub movement: MovementMode,
+    pub target_mode: TargetMode,
+    pub fill_algorithm: FillAlgorithm,
+    /// Set when the board had no room for all of this level's balls; the
+    /// ones that were placed still play
+    pub ball_shortfall: Option<SpawnShortfall>,
+    /// Drives ball spawns; seeded so peers given the same seed stay in lockstep
+    rng: StdRng,
+    scratch: RegionScratch,
 }
 
 impl Game {
     pub fn new(width: i32, height: i32) -> Self {
-        let mut board = vec![vec![Cell::Empty; width as usize]; height as usize];
+        Self::with_players(width, height, 1)
+    }
+
+    /// Create a game with `player_count` players (clamped to 1..=MAX_PLAYERS),
+    /// each starting from the middle of a different edge.
+    pub fn with_players(width: i32, height: i32, player_count: usize) -> Self {
+        Self::with_seed(width, height, player_count, rand::random())
+    }
+
+    /// Create a game whose ball spawns are fully determined by `seed`.
+    /// Two games built with the same arguments and fed the same inputs tick identically.
+    pub fn with_seed(width: i32, height: i32, player_count: usize, seed: u64) -> Self {
+        let mut board = Board::new(width, height);
 
         // Fill borders
         for x in 0..width {
-            board[0][x as usize] = Cell::Filled;
-            board[(height - 1) as usize][x as usize] = Cell::Filled;
+            board.set(x, 0, Cell::Filled);
+            board.set(x, height - 1, Cell::Filled);
         }
         for y in 0..height {
-            board[y as usize][0] = Cell::Filled;
-            board[y as usize][(width - 1) as usize] = Cell::Filled;
+            board.set(0, y, Cell::Filled);
+            board.set(width - 1, y, Cell::Filled);
         }
 
-        let player = Player::new(0, height / 2);
+        let players = (0..player_count.clamp(1, MAX_PLAYERS))
+            .map(|idx| Self::starting_player(idx, width, height))
+            .collect();
 
         let mut game = Self {
             width,
             height,
             board,
-            player,
+            players,
             balls: Vec::new(),
             state: GameState::Playing,
             score: 0,
             level: 1,
+            tick: 0,
             filled_percentage: 0.0,
             target_percentage: 0.75,
+            capture_rule: Box::new(XonixRule),
+            movement: MovementMode::FourWay,
+            target_mode: TargetMode::Shared,
+            fill_algorithm: FillAlgorithm::Incremental,
+            ball_shortfall: None,
+            rng: StdRng::seed_from_u64(seed),
+            scratch: RegionScratch::default(),
         };
 
         let board_area = width * height;
         let num_balls = ((board_area as f32 / 267.0).round() as usize).max(1);
-        game.spawn_balls(num_balls);
+        // Boards too small for every ball start with fewer
+        game.ball_shortfall = game.spawn_balls(num_balls).err();
         game.update_filled_percentage();
 
         game
     }
 
+    /// Player `idx` at its starting spot: left, right, top, then bottom edge
+    fn starting_player(idx: usize, width: i32, height: i32) -> Player {
+        let (x, y, direction) = match idx {
+            0 => (0, height / 2, Direction::Right),
+            1 => (width - 1, height / 2, Direction::Left),
+            2 => (width / 2, 0, Direction::Down),
+            _ => (width / 2, height - 1, Direction::Up),
+        };
+        let mut player = Player::new(x, y);
+        player.direction = direction;
+        player
+    }
+
+    /// Choose how completed trails capture territory. Survives `reset` and `next_level`.
+    pub fn set_capture_rule(&mut self, rule: Box<dyn CaptureRule>) {
+        self.capture_rule = rule;
+    }
+
+    /// Choose shared or competitive play. Survives `reset` and `next_level`.
+    pub fn set_target_mode(&mut self, target_mode: TargetMode) {
+        self.target_mode = target_mode;
+    }
+
+    /// Choose how captured regions are found. Survives `reset` and `next_level`.
+    pub fn set_fill_algorithm(&mut self, fill_algorithm: FillAlgorithm) {
+        self.fill_algorithm = fill_algorithm;
+    }
+
     pub fn cell_at(&self, x: i32, y: i32) -> Cell {
         if x < 0 || y < 0 || x >= self.width || y >= self.height {
             return Cell::Filled;
         }
-        self.board[y as usize][x as usize]
+        self.board.get(x, y)
     }
 
     pub fn is_filled(&self, x: i32, y: i32) -> bool {
         self.cell_at(x, y) == Cell::Filled
     }
 
+    /// Draw speed the cell at (x, y) was captured with; only meaningful for filled cells
+    pub fn capture_speed_at(&self, x: i32, y: i32) -> DrawSpeed {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return DrawSpeed::Fast;
+        }
+        self.board.speed(x, y)
+    }
+
+    /// Index of the player whose current trail covers `pos`
+    pub fn trail_owner(&self, pos: Position) -> Option<usize> {
+        self.players
+            .iter()
+            .position(|player| player.is_drawing && player.trail_contains(pos))
+    }
+
+    /// In competitive play, the player with the strictly highest score once the game is won
+    pub fn winner(&self) -> Option<usize> {
+        if self.target_mode != TargetMode::Competitive || self.state != GameState::Won {
+            return None;
+        }
+        let best = self.players.iter().map(|player| player.score).max()?;
+        let mut leaders = self
+            .players
+            .iter()
+            .enumerate()
+            .filter(|(_, player)| player.score == best);
+        match (leaders.next(), leaders.next()) {
+            (Some((idx, _)), None) => Some(idx),
+            _ => None,
+        }
+    }
+
+    /// Cheap fingerprint of everything that affects future ticks (FNV-1a),
+    /// including where the ball spawn RNG is. Lockstep peers compare it every
+    /// tick to catch desyncs early.
+    pub fn state_hash(&self) -> u64 {
+        const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+        let mut hash = FNV_OFFSET;
+        let mut mix = |value: i64| {
+            for byte in value.to_le_bytes() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(FNV_PRIME);
+            }
+        };
+
+        for cell in self.board.cells() {
+            mix(cell as i64);
+        }
+        for player in &self.players {
+            mix(player.position.x as i64);
+            mix(player.position.y as i64);
+            mix(player.direction as i64);
+            for &speed in &player.trail_speeds {
+                mix(speed as i64);
+            }
+            mix(player.trail.len() as i64);
+            mix(player.score as i64);
+            mix(player.alive as i64);
+            mix(player.is_drawing as i64);
+            mix(player.holding as i64);
+            mix(player.draw_speed as i64);
+            mix(player.slow_step_paused() as i64);
+            mix(player.turned_this_tick as i64);
+            mix(player.turn_queue.len() as i64);
+            for &turn in &player.turn_queue {
+                mix(turn as i64);
+            }
+        }
+        for ball in &self.balls {
+            mix(ball.position.x as i64);
+            mix(ball.position.y as i64);
+            mix(ball.velocity.0 as i64);
+            mix(ball.velocity.1 as i64);
+        }
+        mix(self.state as i64);
+        mix(self.level as i64);
+        mix(self.tick as i64);
+        mix(self.target_percentage.to_bits() as i64);
+        // The next number it would draw stands in for its whole state
+        mix(self.rng.clone().gen::<u64>() as i64);
+        hash
+    }
+
+    /// Freeze a game in progress; `update` does nothing until `resume`
+    pub fn pause(&mut self) {
+        if self.state == GameState::Playing {
+            self.state = GameState::Paused;
+        }
+    }
+
+    pub fn resume(&mut self) {
+        if self.state == GameState::Paused {
+            self.state = GameState::Playing;
+        }
+    }
+
+    pub fn toggle_pause(&mut self) {
+        match self.state {
+            GameState::Playing => self.pause(),
+            GameState::Paused => self.resume(),
+            GameState::Won | GameState::Lost => {}
+        }
+    }
+
+    /// Switch the first player between fast and slow draw
+    pub fn toggle_draw_speed(&mut self) {
+        self.toggle_player_draw_speed(0);
+    }
+
+    pub fn toggle_player_draw_speed(&mut self, idx: usize) {
+        if let Some(player) = self.players.get_mut(idx) {
+            player.draw_speed = player.draw_speed.toggled();
+        }
+    }
+
+    /// Allow or forbid diagonal movement. Survives `reset` and `next_level`.
+    pub fn set_movement_mode(&mut self, movement: MovementMode) {
+        self.movement = movement;
+        for (idx, player) in self.players.iter_mut().enumerate() {
+            if !movement.allows(player.direction) {
+                player.direction = Self::starting_player(idx, self.width, self.height).direction;
+            }
+        }
+    }
+
+    /// Turn the first player; see `set_player_direction`
     pub fn set_direction(&mut self, direction: Direction) {
+        self.set_player_direction(0, direction);
+    }
+
+    /// Turn player `idx`. The first turn before a tick applies immediately; quicker
+    /// follow-up turns are buffered and applied one per move, in order.
+    pub fn set_player_direction(&mut self, idx: usize, direction: Direction) {
+        if !self.movement.allows(direction) || idx >= self.players.len() {
+            return;
+        }
+
+        let player = &mut self.players[idx];
+
+        // Any turn releases a hold
+        player.holding = false;
+
+        if !player.turned_this_tick && player.turn_queue.is_empty() {
+            Self::apply_turn(player, direction);
+        } else if player.turn_queue.len() < TURN_QUEUE_CAPACITY
+            && player.turn_queue.back() != Some(&direction)
+        {
+            player.turn_queue.push_back(direction);
+        }
+    }
+
+    /// Stop the first player in place, or start moving again; see `toggle_player_hold`
+    pub fn toggle_hold(&mut self) {
+        self.toggle_player_hold(0);
+    }
+
+    /// Stop in place, or start moving again. Holding is only possible on
+    /// filled territory; it's ignored while drawing a trail.
+    pub fn toggle_player_hold(&mut self, idx: usize) {
+        if let Some(player) = self.players.get_mut(idx) {
+            if player.holding {
+                player.holding = false;
+            } else if !player.is_drawing {
+                player.holding = true;
+            }
+        }
+    }
+
+    /// Apply a turn if it's legal right now. Returns true if the heading changed.
+    fn apply_turn(player: &mut Player, direction: Direction) -> bool {
         // Prevent reversing direction only while drawing a trail
         // When on safe filled territory, allow free movement including reversing
-        if self.player.is_drawing && direction == self.player.direction.opposite() {
-            return; // Can't reverse while drawing
+        if player.is_drawing && direction == player.direction.opposite() {
+            return false; // Can't reverse while drawing
         }
-        self.player.direction = direction;
+        if direction == player.direction {
+            return false;
+        }
+        player.direction = direction;
+        player.turned_this_tick = true;
+        true
     }
 
     pub fn update(&mut self) {
         if self.state != GameState::Playing {
             return;
         }
+        self.tick += 1;
 
-        // Move player
-        let next_pos = self.player.position.moved(self.player.direction);
-
-        // Check if position is valid and handle movement
-        if next_pos.x >= 0 && next_pos.y >= 0 && next_pos.x < self.width && next_pos.y < self.height {
-            let next_cell = self.cell_at(next_pos.x, next_pos.y);
-
-            match next_cell {
-                Cell::Filled => {
-                    // Moving on filled area
-                    if self.player.is_drawing {
-                        // Completed a path, fill the enclosed area
-                        self.complete_trail();
-                    }
-                    self.player.position = next_pos;
-                }
-                Cell::Empty => {
-                    // Drawing in empty space
-                    if !self.player.is_drawing {
-                        self.player.start_trail();
-                    }
-                    self.player.position = next_pos;
-                    self.player.add_to_trail();
-
-                    // Mark trail on board
-                    self.board[next_pos.y as usize][next_pos.x as usize] = Cell::Trail;
-                }
-                Cell::Trail => {
-                    // Hit own trail - lose life
-                    self.state = GameState::Lost;
+        // Move players in a fixed order so ties resolve the same way every time
+        for idx in 0..self.players.len() {
+            if self.players[idx].alive {
+                self.update_player(idx);
+                if self.state != GameState::Playing {
                     return;
                 }
             }
         }
-        // If out of bounds, player just doesn't move but game continues
 
         // Update balls
         for i in 0..self.balls.len() {
@@ -152,17 +419,29 @@ impl Game {
             self.balls[i].velocity.0 = vel_x;
             self.balls[i].velocity.1 = vel_y;
 
-            // Check collision with player
-            if self.balls[i].position == self.player.position {
-                self.state = GameState::Lost;
-                return;
-            }
+            // Trail cells are marked on the board, so most balls are cleared
+            // without looking at any trail: only a ball landing on a trail
+            // cell, or sweeping diagonally between two, can have hit one
+            let from = Position::new(pos_x, pos_y);
+            let ball_pos = self.balls[i].position;
+            let near_trail = self.cell_at(ball_pos.x, ball_pos.y) == Cell::Trail
+                || (self.cell_at(ball_pos.x, pos_y) != Cell::Empty && self.cell_at(pos_x, ball_pos.y) != Cell::Empty);
+            for p in 0..self.players.len() {
+                let player = &self.players[p];
+                if !player.alive {
+                    continue;
+                }
+
+                // Check collision with player, then with trail
+                // (diagonal trails can also be crossed between two of their cells)
+                let hit = ball_pos == player.position
+                    || (near_trail
+                        && player.is_drawing
+                        && (player.trail_contains(ball_pos) || player.trail_crosses(from, ball_pos)));
 
-            // Check collision with trail
-            if self.player.is_drawing {
-                for trail_pos in &self.player.trail {
-                    if self.balls[i].position == *trail_pos {
-                        self.state = GameState::Lost;
+                if hit {
+                    self.crash_player(p);
+                    if self.state != GameState::Playing {
                         return;
                     }
                 }
@@ -175,261 +454,353 @@ impl Game {
         }
     }
 
-    fn complete_trail(&mut self) {
-        if self.player.trail.is_empty() {
-            return;
-        }
-
-        // Mark trail as filled
-        for pos in &self.player.trail {
-            self.board[pos.y as usize][pos.x as usize] = Cell::Filled;
+    fn update_player(&mut self, idx: usize) {
+        // Take the next buffered turn once the previous one has been moved on.
+        // Turns that became illegal (reversals while drawing) are dropped.
+        let player = &mut self.players[idx];
+        if !player.turned_this_tick {
+            while let Some(direction) = player.turn_queue.pop_front() {
+                if Self::apply_turn(player, direction) {
+                    break;
+                }
+            }
         }
 
-        // Fill enclosed areas using flood fill
-        self.fill_enclosed_areas();
+        // Move player
+        let position = self.players[idx].position;
+        let next_pos = position.moved(self.players[idx].direction);
 
-        self.player.clear_trail();
-        self.update_filled_percentage();
+        // A holding player waits on safe territory; balls keep moving
+        let holding = self.players[idx].holding && self.cell_at(position.x, position.y) == Cell::Filled;
 
-        // Award points
-        self.score += (self.filled_percentage * 100.0) as u32;
-    }
+        // Slow draw: the player only advances every other tick while in empty
+        // space. The cadence only runs while the player is trying to move.
+        let slow_step_paused = !holding
+            && self.players[idx].draw_speed == DrawSpeed::Slow
+            && (self.players[idx].is_drawing || self.cell_at(next_pos.x, next_pos.y) == Cell::Empty)
+            && self.players[idx].skips_slow_step();
 
-    fn fill_enclosed_areas(&mut self) {
-        // SIMPLER APPROACH: The LARGEST empty region after completing a trail is the
-        // "outside" playable area. All smaller regions are enclosed and should be filled.
-        // This is the classic territory-capture behavior (inspired by Xonix).
+        // Check if position is valid and handle movement
+        if !slow_step_paused
+            && !holding
+            && next_pos.x >= 0
+            && next_pos.y >= 0
+            && next_pos.x < self.width
+            && next_pos.y < self.height
+        {
+            // A diagonal step between two consecutive trail cells crosses the trail
+            if self.players[idx].is_drawing && self.players[idx].trail_crosses(position, next_pos) {
+                self.crash_player(idx);
+                return;
+            }
+            for other in 0..self.players.len() {
+                if other != idx && self.players[other].trail_crosses(position, next_pos) {
+                    self.crash_player(other);
+                    if self.state != GameState::Playing {
+                        return;
+                    }
+                }
+            }
 
-        // Find ALL empty regions first
-        let mut visited = vec![vec![false; self.width as usize]; self.height as usize];
-        let mut all_regions: Vec<Vec<(i32, i32)>> = Vec::new();
+            let mut next_cell = self.cell_at(next_pos.x, next_pos.y);
 
-        for y in 1..(self.height - 1) {
-            for x in 1..(self.width - 1) {
-                let ux = x as usize;
-                let uy = y as usize;
-
-                // Find all separate empty regions
-                if !visited[uy][ux] && self.board[uy][ux] == Cell::Empty {
-                    // Start a new region flood fill for this enclosed area
-                    let mut region = Vec::new();
-                    let mut region_stack = vec![(x, y)];
-
-                    while let Some((rx, ry)) = region_stack.pop() {
-                        if rx < 1 || ry < 1 || rx >= self.width - 1 || ry >= self.height - 1 {
-                            continue;
-                        }
-
-                        let rux = rx as usize;
-                        let ruy = ry as usize;
-
-                        if visited[ruy][rux] || self.board[ruy][rux] == Cell::Filled {
-                            continue;
-                        }
-
-                        visited[ruy][rux] = true;
-                        region.push((rx, ry));
-
-                        region_stack.push((rx + 1, ry));
-                        region_stack.push((rx - 1, ry));
-                        region_stack.push((rx, ry + 1));
-                        region_stack.push((rx, ry - 1));
+            // Running into another player's trail cuts it
+            if next_cell == Cell::Trail {
+                if let Some(owner) = self.trail_owner(next_pos).filter(|&owner| owner != idx) {
+                    self.crash_player(owner);
+                    if self.state != GameState::Playing {
+                        return;
                     }
+                    next_cell = self.cell_at(next_pos.x, next_pos.y);
+                }
+            }
 
-                    if !region.is_empty() {
-                        all_regions.push(region);
+            match next_cell {
+                Cell::Filled => {
+                    // Moving on filled area
+                    if self.players[idx].is_drawing {
+                        // Completed a path, fill the enclosed area
+                        self.complete_trail(idx);
+                    }
+                    self.players[idx].position = next_pos;
+                }
+                Cell::Empty => {
+                    // Drawing in empty space
+                    let player = &mut self.players[idx];
+                    if !player.is_drawing {
+                        player.start_trail();
                     }
+                    player.position = next_pos;
+                    player.add_to_trail();
+
+                    // Mark trail on board
+                    self.board.set(next_pos.x, next_pos.y, Cell::Trail);
+                }
+                Cell::Trail => {
+                    // Hit own trail - lose life
+                    self.crash_player(idx);
+                    return;
                 }
             }
         }
+        // If out of bounds, player just doesn't move but game continues
 
-        // If there's only one region or no regions, nothing to fill
-        if all_regions.len() <= 1 {
-            return;
+        if !slow_step_paused && !holding {
+            self.players[idx].turned_this_tick = false;
         }
+    }
 
-        // Find the region containing the player - this is the "outside" playable area
-        // (The player just completed a trail and returned to safe territory)
-        let player_region_idx = all_regions
-            .iter()
-            .enumerate()
-            .find(|(_, region)| {
-                region.iter().any(|&(x, y)| x == self.player.position.x && y == self.player.position.y)
-            })
-            .map(|(idx, _)| idx);
-
-        // If player isn't in any empty region (on filled cell), fall back to largest region
-        let outside_idx = player_region_idx.unwrap_or_else(|| {
-            all_regions
-                .iter()
-                .enumerate()
-                .max_by_key(|(_, region)| region.len())
-                .map(|(idx, _)| idx)
-                .unwrap()
-        });
-
-        // All OTHER regions (not the outside) should be filled
-        let mut enclosed_regions = Vec::new();
-        for (idx, region) in all_regions.into_iter().enumerate() {
-            if idx != outside_idx {
-                enclosed_regions.push(region);
+    /// Player `idx` hit a ball, a trail, or had their trail cut.
+    /// Shared play ends the game; competitive play knocks that player out.
+    fn crash_player(&mut self, idx: usize) {
+        match self.target_mode {
+            TargetMode::Shared => {
+                self.state = GameState::Lost;
             }
-        }
-
-        // Categorize enclosed regions by whether they contain balls
-        let mut regions_with_balls: Vec<(usize, usize)> = Vec::new(); // (region_index, ball_count)
-        let mut regions_without_balls: Vec<usize> = Vec::new();
-
-        for (idx, region) in enclosed_regions.iter().enumerate() {
-            let mut ball_count = 0;
-
-            for &(rx, ry) in region {
-                for ball in &self.balls {
-                    if ball.position.x == rx && ball.position.y == ry {
-                        ball_count += 1;
+            TargetMode::Competitive => {
+                let player = &mut self.players[idx];
+                player.alive = false;
+                player.holding = false;
+                for pos in &player.trail {
+                    if self.board.get(pos.x, pos.y) == Cell::Trail {
+                        self.board.set(pos.x, pos.y, Cell::Empty);
                     }
                 }
-            }
+                player.clear_trail();
 
-            if ball_count > 0 {
-                regions_with_balls.push((idx, ball_count));
-            } else {
-                regions_without_balls.push(idx);
+                if self.players.iter().all(|player| !player.alive) {
+                    self.state = GameState::Lost;
+                }
             }
         }
+    }
+
+    fn complete_trail(&mut self, idx: usize) {
+        if self.players[idx].trail.is_empty() {
+            return;
+        }
+
+        let speed = if self.players[idx].trail_is_slow() {
+            DrawSpeed::Slow
+        } else {
+            DrawSpeed::Fast
+        };
 
-        // Determine which regions to fill
-        // CLASSIC BEHAVIOR: Only fill regions WITHOUT balls
-        // Regions with balls are NEVER automatically filled - this is the core mechanic
-        // that makes the game strategic (need multiple cuts to corner a ball)
-        let regions_to_fill: Vec<usize> = regions_without_balls;
+        // If the regions are up to date, only the ones the trail runs through
+        // need searching again
+        let incremental = self.scratch.is_current(&self.board);
 
-        // Fill the selected regions
-        for region_idx in regions_to_fill {
-            for &(x, y) in &enclosed_regions[region_idx] {
-                self.board[y as usize][x as usize] = Cell::Filled;
-            }
+        // Mark trail as filled
+        let trail: Vec<(i32, i32)> = self.players[idx].trail.iter().map(|pos| (pos.x, pos.y)).collect();
+        for &(x, y) in &trail {
+            self.board.set(x, y, Cell::Filled);
+            self.board.set_speed(x, y, speed);
+        }
+
+        // Fill enclosed areas using flood fill
+        for (x, y) in self.capture_regions(idx, incremental.then_some(&trail)) {
+            self.board.set_speed(x, y, speed);
         }
+
+        self.players[idx].clear_trail();
+        self.update_filled_percentage();
+
+        // Award points (slow-drawn captures are worth more)
+        let points = (self.filled_percentage * 100.0) as u32 * speed.score_multiplier();
+        self.players[idx].score += points;
+        self.score += points;
     }
 
-    fn update_filled_percentage(&mut self) {
-        let mut filled_count = 0;
-        let total_cells = (self.width - 2) * (self.height - 2); // Exclude borders
+    /// Fill the regions chosen by the capture rule after player `idx` completed
+    /// a trail, returning the captured cells
+    #[cfg(test)]
+    pub(crate) fn fill_enclosed_areas(&mut self, idx: usize) -> Vec<(i32, i32)> {
+        self.capture_regions(idx, None)
+    }
 
-        for y in 1..(self.height - 1) {
-            for x in 1..(self.width - 1) {
-                if self.board[y as usize][x as usize] == Cell::Filled {
-                    filled_count += 1;
+    /// `fill_enclosed_areas`, where `trail` lists the only cells that became
+    /// filled since the last search, if that's known
+    fn capture_regions(&mut self, idx: usize, trail: Option<&[(i32, i32)]>) -> Vec<(i32, i32)> {
+        self.search_regions(trail);
+
+        // Lend the cached cells to the capture rule rather than copy them
+        let cached = std::mem::take(&mut self.scratch.regions);
+        let keys: Vec<(u32, (i32, i32))> = cached.iter().map(|region| (region.label, region.first)).collect();
+        let regions: Vec<Region> = cached
+            .into_iter()
+            .map(|region| self.describe_region(region.cells, region.label, idx))
+            .collect();
+
+        // If there's only one region or no regions, nothing to fill.
+        // Otherwise the capture rule decides which side(s) of the cut become
+        // territory, but never buries another player who is still drawing.
+        let mut captured = if regions.len() <= 1 {
+            Vec::new()
+        } else {
+            self.capture_rule.regions_to_fill(&regions)
+        };
+        captured.retain(|&region_idx| !regions[region_idx].contains_other_player);
+
+        // Balls caught inside captured territory are out of play
+        if captured.iter().any(|&region_idx| regions[region_idx].has_balls()) {
+            let captured_labels: Vec<u32> = captured.iter().map(|&region_idx| keys[region_idx].0).collect();
+            let labels = &self.scratch.labels;
+            let width = self.width;
+            self.balls.retain(|ball| {
+                let label = labels[(ball.position.y * width + ball.position.x) as usize];
+                !captured_labels.contains(&label)
+            });
+        }
+
+        let mut captured_cells = Vec::new();
+        for (region_idx, (region, (label, first))) in regions.into_iter().zip(keys).enumerate() {
+            if captured.contains(&region_idx) {
+                for &(x, y) in &region.cells {
+                    self.board.set(x, y, Cell::Filled);
+                    self.scratch.labels[(y * self.width + x) as usize] = 0;
                 }
+                captured_cells.extend(region.cells);
+            } else {
+                self.scratch.regions.push(CachedRegion {
+                    label,
+                    first,
+                    cells: region.cells,
+                });
             }
         }
+        self.scratch.valid_at = Some(self.board.filled_changes());
 
-        self.filled_percentage = filled_count as f32 / total_cells as f32;
+        captured_cells
     }
 
-    fn spawn_balls(&mut self, count: usize) {
-        let mut rng = rand::thread_rng();
+    /// Find every separate empty region of the playable area, in row-major
+    /// order of each region's first cell, from the point of view of player `idx`.
+    ///
+    /// Regions are 4-connected in every movement mode. Eight-way trails are
+    /// 8-connected walls, and only a 4-connected fill is sealed by them: two
+    /// empty cells touching at a corner of a diagonal trail step stay apart.
+    #[cfg(test)]
+    pub(crate) fn find_empty_regions(&mut self, idx: usize) -> Vec<Region> {
+        self.search_regions(None);
+        self.scratch
+            .regions
+            .iter()
+            .map(|region| self.describe_region(region.cells.clone(), region.label, idx))
+            .collect()
+    }
 
-        // Pre-compute player data (done once per spawn_balls call)
-        let player_pos = self.player.position;
-        let player_dir = self.player.direction;
+    /// Bring `scratch` up to date with the board
+    fn search_regions(&mut self, trail: Option<&[(i32, i32)]>) {
+        match (self.fill_algorithm, trail) {
+            (FillAlgorithm::FullScan, _) => self.scratch.flood_all(&self.board),
+            (FillAlgorithm::Incremental, Some(trail)) => self.scratch.rescan_around(&self.board, trail),
+            (FillAlgorithm::Incremental, None) => self.scratch.scan_all(&self.board),
+        }
+    }
+
+    /// Count what the region labelled `label` holds, from the point of view of player `idx`
+    fn describe_region(&self, cells: Vec<(i32, i32)>, label: u32, idx: usize) -> Region {
+        let contains = |pos: Position| {
+            self.board.in_bounds(pos.x, pos.y) && self.scratch.labels[(pos.y * self.width + pos.x) as usize] == label
+        };
+
+        Region {
+            contains_player: self.players.get(idx).is_some_and(|player| contains(player.position)),
+            contains_other_player: self.players.iter().enumerate().any(|(other, player)| {
+                other != idx && player.alive && player.is_drawing && contains(player.position)
+            }),
+            ball_count: self.balls.iter().filter(|ball| contains(ball.position)).count(),
+            cells,
+        }
+    }
+
+    fn update_filled_percentage(&mut self) {
+        let total_cells = (self.width - 2) * (self.height - 2); // Exclude borders
+        self.filled_percentage = self.board.interior_filled() as f32 / total_cells as f32;
+    }
 
+    /// Add `count` balls on random empty cells away from the players and other balls,
+    /// each moving so it won't run straight into a player's first stretch.
+    /// Errors with how many were placed if the board ran out of such cells.
+    pub fn spawn_balls(&mut self, count: usize) -> Result<usize, SpawnShortfall> {
         const MIN_SAFE_DISTANCE: i32 = 5;
-        const DANGER_ZONE_WIDTH: i32 = 10;
-        const DANGER_ZONE_HEIGHT: i32 = 10; // Match width to catch diagonal trajectories
-        const MAX_ATTEMPTS: usize = 1000; // Prevent infinite loops
-
-        for ball_idx in 0..count {
-            let mut attempts = 0;
-
-            loop {
-                attempts += 1;
-                if attempts > MAX_ATTEMPTS {
-                    eprintln!(
-                        "Warning: Could not find safe position for ball {} after {} attempts. \
-                         Skipping this ball.",
-                        ball_idx, MAX_ATTEMPTS
-                    );
-                    break; // Skip this ball rather than hang
-                }
 
-                let x = rng.gen_range(2..self.width - 2);
-                let y = rng.gen_range(2..self.height - 2);
+        let starts: Vec<(Position, Direction)> = self
+            .players
+            .iter()
+            .map(|player| (player.position, player.direction))
+            .collect();
+
+        let taken: HashSet<Position> = self.balls.iter().map(|ball| ball.position).collect();
 
-                // Check 1: Position must be empty (cheap, fail fast)
-                if self.is_filled(x, y) {
+        // Every cell a ball could go on
+        let mut candidates: Vec<(i32, i32)> = Vec::new();
+        for y in 2..self.height - 2 {
+            for x in 2..self.width - 2 {
+                if self.board.get(x, y) != Cell::Empty || taken.contains(&Position::new(x, y)) {
                     continue;
                 }
-
-                // Check 2: Minimum manhattan distance (cheap: 2 abs, 1 add, 1 compare)
-                let dx = (x - player_pos.x).abs();
-                let dy = (y - player_pos.y).abs();
-                let manhattan_dist = dx + dy;
-
-                if manhattan_dist < MIN_SAFE_DISTANCE {
-                    continue; // Too close to player
+                let too_close = starts.iter().any(|&(player_pos, _)| {
+                    (x - player_pos.x).abs() + (y - player_pos.y).abs() < MIN_SAFE_DISTANCE
+                });
+                if !too_close && !Self::safe_velocities(&starts, x, y).is_empty() {
+                    candidates.push((x, y));
                 }
+            }
+        }
 
-                // Check 3: Danger zone detection (medium cost)
-                let in_danger_zone = match player_dir {
-                    Direction::Right => {
-                        x <= player_pos.x + DANGER_ZONE_WIDTH
-                            && dy <= DANGER_ZONE_HEIGHT
-                    }
-                    Direction::Left => {
-                        x >= player_pos.x - DANGER_ZONE_WIDTH
-                            && dy <= DANGER_ZONE_HEIGHT
-                    }
-                    Direction::Down => {
-                        y <= player_pos.y + DANGER_ZONE_WIDTH
-                            && dx <= DANGER_ZONE_HEIGHT
-                    }
-                    Direction::Up => {
-                        y >= player_pos.y - DANGER_ZONE_WIDTH
-                            && dx <= DANGER_ZONE_HEIGHT
-                    }
-                };
+        for placed in 0..count {
+            if candidates.is_empty() {
+                return Err(SpawnShortfall { requested: count, placed });
+            }
+            let (x, y) = candidates.swap_remove(self.rng.gen_range(0..candidates.len()));
+            let velocities = Self::safe_velocities(&starts, x, y);
+            let (vx, vy) = velocities[self.rng.gen_range(0..velocities.len())];
+            self.balls.push(Ball::new(x, y, vx, vy));
+        }
+        Ok(count)
+    }
 
-                // Smart velocity selection: choose safe velocity for position
-                // This is KEY for efficiency - we don't reject positions, we fix velocities
-                let (vx, vy) = if in_danger_zone {
-                    // In danger zone: choose velocity moving AWAY from player
-                    match player_dir {
-                        Direction::Right => {
-                            // Player moving right from left edge
-                            // Ball should move right (away from start) or perpendicular
-                            let vx = 1; // Always move right (away from player start)
-                            let vy = if rng.gen_bool(0.5) { 1 } else { -1 }; // Random vertical
-                            (vx, vy)
-                        }
-                        Direction::Left => {
-                            let vx = -1; // Move left (away from player start on right)
-                            let vy = if rng.gen_bool(0.5) { 1 } else { -1 };
-                            (vx, vy)
-                        }
-                        Direction::Down => {
-                            let vx = if rng.gen_bool(0.5) { 1 } else { -1 };
-                            let vy = 1; // Move down (away from player start at top)
-                            (vx, vy)
-                        }
-                        Direction::Up => {
-                            let vx = if rng.gen_bool(0.5) { 1 } else { -1 };
-                            let vy = -1; // Move up (away from player start at bottom)
-                            (vx, vy)
-                        }
-                    }
-                } else {
-                    // Outside danger zone: any velocity is safe
-                    (
-                        if rng.gen_bool(0.5) { 1 } else { -1 },
-                        if rng.gen_bool(0.5) { 1 } else { -1 },
-                    )
-                };
+    /// Diagonal velocities for a ball at (x, y) that don't head back toward
+    /// any player whose danger zone it is in
+    fn safe_velocities(starts: &[(Position, Direction)], x: i32, y: i32) -> Vec<(i32, i32)> {
+        [(1, 1), (1, -1), (-1, 1), (-1, -1)]
+            .into_iter()
+            .filter(|&(vx, vy)| {
+                !starts.iter().any(|&(player_pos, player_dir)| {
+                    Self::in_danger_zone(x, y, player_pos, player_dir) && Self::moving_toward(player_dir, vx, vy)
+                })
+            })
+            .collect()
+    }
+
+    /// Is (x, y) in the stretch a player starting at `player_pos` heading
+    /// `player_dir` will cross first? Diagonal headings use their horizontal component.
+    fn in_danger_zone(x: i32, y: i32, player_pos: Position, player_dir: Direction) -> bool {
+        const DANGER_ZONE_WIDTH: i32 = 10;
+        const DANGER_ZONE_HEIGHT: i32 = 10; // Match width to catch diagonal trajectories
 
-                // All checks passed, velocity is safe for this position
-                self.balls.push(Ball::new(x, y, vx, vy));
-                break;
+        let dx = (x - player_pos.x).abs();
+        let dy = (y - player_pos.y).abs();
+        match player_dir {
+            Direction::Right | Direction::UpRight | Direction::DownRight => {
+                x <= player_pos.x + DANGER_ZONE_WIDTH && dy <= DANGER_ZONE_HEIGHT
             }
+            Direction::Left | Direction::UpLeft | Direction::DownLeft => {
+                x >= player_pos.x - DANGER_ZONE_WIDTH && dy <= DANGER_ZONE_HEIGHT
+            }
+            Direction::Down => y <= player_pos.y + DANGER_ZONE_WIDTH && dx <= DANGER_ZONE_HEIGHT,
+            Direction::Up => y >= player_pos.y - DANGER_ZONE_WIDTH && dx <= DANGER_ZONE_HEIGHT,
+        }
+    }
+
+    /// Would a ball with velocity (vx, vy) head back toward a player starting out in `player_dir`?
+    fn moving_toward(player_dir: Direction, vx: i32, vy: i32) -> bool {
+        match player_dir {
+            Direction::Right | Direction::UpRight | Direction::DownRight => vx < 0,
+            Direction::Left | Direction::UpLeft | Direction::DownLeft => vx > 0,
+            Direction::Down => vy < 0,
+            Direction::Up => vy > 0,
         }
     }
 
@@ -440,29 +811,49 @@ impl Game {
         // Clear board except borders
         for y in 1..(self.height - 1) {
             for x in 1..(self.width - 1) {
-                self.board[y as usize][x as usize] = Cell::Empty;
+                self.board.set(x, y, Cell::Empty);
+                self.board.set_speed(x, y, DrawSpeed::Fast);
             }
         }
 
-        // Reset player
-        self.player = Player::new(0, self.height / 2);
+        // Reset players, keeping their scores and chosen draw speeds
+        for idx in 0..self.players.len() {
+            let mut player = Self::starting_player(idx, self.width, self.height);
+            player.score = self.players[idx].score;
+            player.draw_speed = self.players[idx].draw_speed;
+            self.players[idx] = player;
+        }
 
-        // Spawn more balls
+        // Spawn more balls, as many as fit
         self.balls.clear();
-        self.spawn_balls(2 + self.level as usize);
+        self.ball_shortfall = self.spawn_balls(2 + self.level as usize).err();
 
         self.update_filled_percentage();
     }
 
     pub fn reset(&mut self) {
-        *self = Self::new(self.width, self.height);
+        // Reseed from our own stream so a reset is as deterministic as the first game
+        let seed = self.rng.gen();
+        let fresh = Self::with_seed(self.width, self.height, self.players.len(), seed);
+        // Keep the settings the game was started with
+        let Self {
+            capture_rule,
+            movement,
+            target_mode,
+            fill_algorithm,
+            ..
+        } = std::mem::replace(self, fresh);
+        self.capture_rule = capture_rule;
+        self.movement = movement;
+        self.target_mode = target_mode;
+        self.fill_algorithm = fill_algorithm;
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::entity::{Position, Direction};
+    use crate::entity::{Position, Direction, MovementMode};
     use proptest::prelude::*;
 
     // Strategy for generating valid directions
@@ -515,6 +906,44 @@ mod tests {
     }
 
     proptest! {
+        /// Incremental scanline capture and the full-board flood fill always
+        /// leave identical games, whatever the rule and however many players
+        #[test]
+        fn prop_fill_algorithms_agree(
+            seed in any::<u64>(),
+            player_count in 1usize..=2,
+            rule in prop_oneof![Just("xonix"), Just("qix"), Just("smaller")],
+            competitive in any::<bool>(),
+            moves in prop::collection::vec((0usize..2, direction_strategy()), 1..300),
+        ) {
+            let build = |fill_algorithm| {
+                let mut game = Game::with_seed(20, 15, player_count, seed);
+                game.set_capture_rule(crate::capture::capture_rule_by_name(rule).unwrap());
+                if competitive {
+                    game.set_target_mode(TargetMode::Competitive);
+                }
+                game.set_fill_algorithm(fill_algorithm);
+                game
+            };
+            let mut incremental = build(FillAlgorithm::Incremental);
+            let mut full = build(FillAlgorithm::FullScan);
+
+            for (player, direction) in moves {
+                if incremental.state != GameState::Playing {
+                    break;
+                }
+                for game in [&mut incremental, &mut full] {
+                    game.set_player_direction(player, direction);
+                    game.update();
+                }
+
+                prop_assert_eq!(&incremental.board, &full.board);
+                prop_assert_eq!(&incremental.balls, &full.balls);
+                prop_assert_eq!(incremental.state, full.state);
+                prop_assert_eq!(incremental.score, full.score);
+            }
+        }
+
         /// Test that filled percentage never exceeds 100%
         #[test]
         fn prop_filled_percentage_never_exceeds_100(
@@ -558,26 +987,26 @@ mod tests {
             // Create random existing filled territory (horizontal lines)
             for &row in &existing_fill_rows {
                 for x in 1..19 {
-                    game.board[row][x] = Cell::Filled;
+                    game.board.set(x, row as i32, Cell::Filled);
                 }
             }
             game.update_filled_percentage();
             let initial_fill = game.filled_percentage;
 
             // Position player on filled territory or border
-            game.player.position.x = start_x;
-            game.player.position.y = if existing_fill_rows.contains(&(start_y as usize)) {
+            game.players[0].position.x = start_x;
+            game.players[0].position.y = if existing_fill_rows.contains(&(start_y as usize)) {
                 start_y
             } else {
                 0 // Default to border
             };
-            game.player.is_drawing = false;
+            game.players[0].is_drawing = false;
 
             // Track bounding box of the trail
-            let mut min_x = game.player.position.x;
-            let mut max_x = game.player.position.x;
-            let mut min_y = game.player.position.y;
-            let mut max_y = game.player.position.y;
+            let mut min_x = game.players[0].position.x;
+            let mut max_x = game.players[0].position.x;
+            let mut min_y = game.players[0].position.y;
+            let mut max_y = game.players[0].position.y;
 
             // Execute trail moves
             let mut completed_trail = false;
@@ -590,15 +1019,15 @@ mod tests {
                 game.update();
 
                 // Track bounding box while drawing
-                if game.player.is_drawing {
-                    min_x = min_x.min(game.player.position.x);
-                    max_x = max_x.max(game.player.position.x);
-                    min_y = min_y.min(game.player.position.y);
-                    max_y = max_y.max(game.player.position.y);
+                if game.players[0].is_drawing {
+                    min_x = min_x.min(game.players[0].position.x);
+                    max_x = max_x.max(game.players[0].position.x);
+                    min_y = min_y.min(game.players[0].position.y);
+                    max_y = max_y.max(game.players[0].position.y);
                 }
 
                 // If we just completed a trail, mark it
-                if i > 0 && !game.player.is_drawing && completed_trail == false {
+                if i > 0 && !game.players[0].is_drawing && !completed_trail {
                     completed_trail = true;
                     break;
                 }
@@ -648,9 +1077,9 @@ mod tests {
             let initial_fill = game.filled_percentage;
 
             // Start from border
-            game.player.position.x = 1;
-            game.player.position.y = 0;
-            game.player.direction = Direction::Down;
+            game.players[0].position.x = 1;
+            game.players[0].position.y = 0;
+            game.players[0].direction = Direction::Down;
 
             let mut trail_length = 0;
             let mut completed = false;
@@ -661,16 +1090,16 @@ mod tests {
                     break;
                 }
 
-                let was_drawing = game.player.is_drawing;
+                let was_drawing = game.players[0].is_drawing;
                 game.set_direction(direction);
                 game.update();
 
-                if game.player.is_drawing {
+                if game.players[0].is_drawing {
                     trail_length += 1;
                 }
 
                 // Check if we just completed
-                if was_drawing && !game.player.is_drawing {
+                if was_drawing && !game.players[0].is_drawing {
                     completed = true;
                 }
             }
@@ -733,8 +1162,8 @@ mod tests {
                 let cells_filled = ((filled_after - filled_before) * total_cells as f32) as i32;
 
                 // Physical impossibility: can't fill more cells than we drew
-                if game.player.trail.len() > 0 {
-                    let trail_len = game.player.trail.len() as i32;
+                if !game.players[0].trail.is_empty() {
+                    let trail_len = game.players[0].trail.len() as i32;
                     // Trail length + maximum enclosed area should be reasonable
                     prop_assert!(
                         cells_filled <= total_cells,
@@ -828,9 +1257,9 @@ mod tests {
             let initial_filled = game.filled_percentage;
 
             // Create a small trail along the top border
-            game.player.position.x = 1;
-            game.player.position.y = 1;
-            game.player.direction = Direction::Down;
+            game.players[0].position.x = 1;
+            game.players[0].position.y = 1;
+            game.players[0].direction = Direction::Down;
 
             // Move down into empty space
             game.update();
@@ -889,14 +1318,14 @@ mod tests {
                     break;
                 }
 
-                let was_drawing = game.player.is_drawing;
+                let was_drawing = game.players[0].is_drawing;
                 let before_fill = game.filled_percentage;
 
                 game.set_direction(direction);
                 game.update();
 
                 // If we were drawing and now we're not, we completed a trail
-                if was_drawing && !game.player.is_drawing && game.state == GameState::Playing {
+                if was_drawing && !game.players[0].is_drawing && game.state == GameState::Playing {
                     prop_assert!(
                         game.filled_percentage >= before_fill,
                         "Filled percentage decreased after completing trail: {:.1}% -> {:.1}%",
@@ -967,20 +1396,20 @@ mod tests {
                     break;
                 }
 
-                let was_drawing = game.player.is_drawing;
+                let was_drawing = game.players[0].is_drawing;
 
                 game.set_direction(direction);
                 game.update();
 
                 // If we just completed a trail
-                if was_drawing && !game.player.is_drawing && game.state == GameState::Playing {
-                    let player_cell = game.cell_at(game.player.position.x, game.player.position.y);
+                if was_drawing && !game.players[0].is_drawing && game.state == GameState::Playing {
+                    let player_cell = game.cell_at(game.players[0].position.x, game.players[0].position.y);
                     prop_assert_eq!(
                         player_cell,
                         Cell::Filled,
                         "Player at ({}, {}) is not on filled territory after completing trail",
-                        game.player.position.x,
-                        game.player.position.y
+                        game.players[0].position.x,
+                        game.players[0].position.y
                     );
                 }
             }
@@ -1032,8 +1461,8 @@ mod tests {
 
             let game = Game::new(width, height);
 
-            let player_pos = game.player.position;
-            let player_dir = game.player.direction;
+            let player_pos = game.players[0].position;
+            let player_dir = game.players[0].direction;
 
             for (i, ball) in game.balls.iter().enumerate() {
                 // Property 1: Minimum safe distance
@@ -1055,11 +1484,11 @@ mod tests {
                 // Property 2: No collision course in danger zone
                 // Danger zone is in the direction the player is facing (initially Right)
                 let in_danger_zone = match player_dir {
-                    Direction::Right => {
+                    Direction::Right | Direction::UpRight | Direction::DownRight => {
                         ball.position.x <= player_pos.x + DANGER_ZONE_WIDTH
                             && (ball.position.y - player_pos.y).abs() <= DANGER_ZONE_HEIGHT
                     }
-                    Direction::Left => {
+                    Direction::Left | Direction::UpLeft | Direction::DownLeft => {
                         ball.position.x >= player_pos.x - DANGER_ZONE_WIDTH
                             && (ball.position.y - player_pos.y).abs() <= DANGER_ZONE_HEIGHT
                     }
@@ -1076,8 +1505,8 @@ mod tests {
                 if in_danger_zone {
                     // Check if ball is moving toward player
                     let moving_toward_player = match player_dir {
-                        Direction::Right => ball.velocity.0 < 0, // Ball moving left toward start
-                        Direction::Left => ball.velocity.0 > 0,  // Ball moving right
+                        Direction::Right | Direction::UpRight | Direction::DownRight => ball.velocity.0 < 0, // Ball moving left toward start
+                        Direction::Left | Direction::UpLeft | Direction::DownLeft => ball.velocity.0 > 0,  // Ball moving right
                         Direction::Down => ball.velocity.1 < 0,  // Ball moving up
                         Direction::Up => ball.velocity.1 > 0,    // Ball moving down
                     };
@@ -1121,6 +1550,61 @@ mod tests {
     }
 
     // Regular unit tests for specific scenarios
+    #[test]
+    fn test_spawn_finds_the_last_free_cells() {
+        let mut game = Game::with_seed(60, 40, 1, 4);
+        game.balls.clear();
+        for y in 1..39 {
+            for x in 1..59 {
+                game.board.set(x, y, Cell::Filled);
+            }
+        }
+        let free = [(50, 5), (30, 30), (45, 20)];
+        for &(x, y) in &free {
+            game.board.set(x, y, Cell::Empty);
+        }
+
+        assert_eq!(game.spawn_balls(3), Ok(3));
+        let mut spots: Vec<(i32, i32)> = game.balls.iter().map(|ball| (ball.position.x, ball.position.y)).collect();
+        spots.sort();
+        assert_eq!(spots, [(30, 30), (45, 20), (50, 5)]);
+    }
+
+    #[test]
+    fn test_spawn_reports_how_many_fit() {
+        let mut game = Game::with_seed(60, 40, 1, 4);
+        game.balls.clear();
+        for y in 1..39 {
+            for x in 1..59 {
+                if (x, y) != (40, 10) && (x, y) != (40, 30) {
+                    game.board.set(x, y, Cell::Filled);
+                }
+            }
+        }
+        // Next to the player's start, so not a safe spot either
+        game.board.set(2, 20, Cell::Empty);
+
+        assert_eq!(game.spawn_balls(5), Err(SpawnShortfall { requested: 5, placed: 2 }));
+        assert_eq!(game.balls.len(), 2);
+        assert_eq!(game.spawn_balls(1), Err(SpawnShortfall { requested: 1, placed: 0 }));
+    }
+
+    #[test]
+    fn test_games_record_when_balls_dont_fit() {
+        assert_eq!(Game::with_seed(60, 40, 1, 4).ball_shortfall, None);
+
+        // Every inner cell is too close to the player
+        let game = Game::with_seed(5, 5, 1, 4);
+        assert_eq!(game.ball_shortfall, Some(SpawnShortfall { requested: 1, placed: 0 }));
+        assert!(game.balls.is_empty());
+    }
+
+    #[test]
+    fn test_tiny_board_starts_without_balls() {
+        let game = Game::with_seed(4, 4, 1, 1);
+        assert!(game.balls.is_empty());
+    }
+
     #[test]
     fn test_adjacent_trail_to_border_doesnt_fill_entire_board() {
         let mut game = Game::new(10, 10);
@@ -1129,14 +1613,14 @@ mod tests {
         let initial_fill = game.filled_percentage;
 
         // Start at border (0, 5)
-        game.player.position.x = 1;
-        game.player.position.y = 1;
-        game.player.direction = Direction::Right;
+        game.players[0].position.x = 1;
+        game.players[0].position.y = 1;
+        game.players[0].direction = Direction::Right;
 
         // Move down (into empty space)
         game.set_direction(Direction::Down);
         game.update();
-        assert!(game.player.is_drawing);
+        assert!(game.players[0].is_drawing);
 
         // Move right 3 steps
         game.set_direction(Direction::Right);
@@ -1162,24 +1646,552 @@ mod tests {
         assert_eq!(game.state, GameState::Playing);
     }
 
+    #[test]
+    fn test_paused_game_is_frozen() {
+        let mut game = Game::with_seed(30, 20, 1, 2);
+        game.update();
+        game.toggle_pause();
+        assert_eq!(game.state, GameState::Paused);
+
+        let before = (game.players[0].position, game.balls.clone(), game.tick);
+        for _ in 0..10 {
+            game.update();
+        }
+        assert_eq!((game.players[0].position, game.balls.clone(), game.tick), before);
+
+        game.toggle_pause();
+        game.update();
+        assert_eq!(game.state, GameState::Playing);
+        assert_eq!(game.tick, 2);
+    }
+
+    #[test]
+    fn test_only_running_games_pause() {
+        let mut game = Game::with_seed(30, 20, 1, 2);
+        game.state = GameState::Lost;
+        game.toggle_pause();
+        assert_eq!(game.state, GameState::Lost);
+        game.resume();
+        assert_eq!(game.state, GameState::Lost);
+    }
+
     #[test]
     fn test_cannot_reverse_while_drawing() {
         let mut game = Game::new(10, 10);
-        game.player.direction = Direction::Right;
-        game.player.is_drawing = true;
+        game.players[0].direction = Direction::Right;
+        game.players[0].is_drawing = true;
 
         game.set_direction(Direction::Left);
-        assert_eq!(game.player.direction, Direction::Right);
+        assert_eq!(game.players[0].direction, Direction::Right);
     }
 
     #[test]
     fn test_can_reverse_on_safe_territory() {
         let mut game = Game::new(10, 10);
-        game.player.direction = Direction::Right;
-        game.player.is_drawing = false;
+        game.players[0].direction = Direction::Right;
+        game.players[0].is_drawing = false;
 
         game.set_direction(Direction::Left);
-        assert_eq!(game.player.direction, Direction::Left);
+        assert_eq!(game.players[0].direction, Direction::Left);
+    }
+
+    /// Draw a U-shaped trail off the top border at x = 5, enclosing a 2x2 pocket
+    fn draw_small_pocket(game: &mut Game) {
+        game.players[0].position.x = 5;
+        game.players[0].position.y = 0;
+
+        let path = [
+            (Direction::Down, 3),
+            (Direction::Right, 3),
+            (Direction::Up, 3),
+        ];
+        for (direction, steps) in path {
+            game.set_direction(direction);
+            for _ in 0..steps {
+                // Keep ticking until the player actually advances (slow draw skips ticks)
+                let before = game.players[0].position;
+                while game.state == GameState::Playing && game.players[0].position == before {
+                    game.update();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_slow_draw_moves_every_other_tick() {
+        let mut game = Game::new(20, 20);
+        game.balls.clear();
+        game.toggle_draw_speed();
+
+        game.players[0].position.x = 5;
+        game.players[0].position.y = 0;
+        game.set_direction(Direction::Down);
+
+        let mut positions = Vec::new();
+        for _ in 0..6 {
+            game.update();
+            positions.push(game.players[0].position.y);
+        }
+
+        assert_eq!(positions, vec![1, 1, 2, 2, 3, 3]);
+        assert!(game.players[0].trail_speeds.iter().all(|&s| s == DrawSpeed::Slow));
+    }
+
+    #[test]
+    fn test_slow_capture_is_tagged_and_scores_double() {
+        let mut fast = Game::new(20, 20);
+        fast.balls.clear();
+        draw_small_pocket(&mut fast);
+
+        let mut slow = Game::new(20, 20);
+        slow.balls.clear();
+        slow.toggle_draw_speed();
+        draw_small_pocket(&mut slow);
+
+        assert!(!fast.players[0].is_drawing && !slow.players[0].is_drawing);
+        assert_eq!(fast.filled_percentage, slow.filled_percentage);
+        assert!(fast.score > 0);
+        assert_eq!(slow.score, fast.score * 2);
+
+        assert_eq!(fast.capture_speed_at(5, 1), DrawSpeed::Fast);
+        assert_eq!(slow.capture_speed_at(5, 1), DrawSpeed::Slow);
+        assert_eq!(slow.capture_speed_at(6, 2), DrawSpeed::Slow);
+    }
+
+    #[test]
+    fn test_mixed_speed_trail_captures_as_fast() {
+        let mut game = Game::new(20, 20);
+        game.balls.clear();
+        game.toggle_draw_speed();
+
+        game.players[0].position.x = 5;
+        game.players[0].position.y = 0;
+        game.set_direction(Direction::Down);
+        game.update();
+        game.toggle_draw_speed();
+        game.set_direction(Direction::Right);
+        game.update();
+        game.update();
+        game.set_direction(Direction::Up);
+        game.update();
+
+        assert!(!game.players[0].is_drawing);
+        assert_eq!(game.capture_speed_at(6, 1), DrawSpeed::Fast);
+    }
+
+    #[test]
+    fn test_four_way_mode_rejects_diagonals() {
+        let mut game = Game::new(10, 10);
+        game.set_direction(Direction::DownRight);
+        assert_eq!(game.players[0].direction, Direction::Right);
+    }
+
+    #[test]
+    fn test_eight_way_cannot_reverse_diagonal_while_drawing() {
+        let mut game = Game::new(10, 10);
+        game.set_movement_mode(MovementMode::EightWay);
+        game.players[0].direction = Direction::UpRight;
+        game.players[0].is_drawing = true;
+
+        game.set_direction(Direction::DownLeft);
+        assert_eq!(game.players[0].direction, Direction::UpRight);
+
+        game.set_direction(Direction::DownRight);
+        assert_eq!(game.players[0].direction, Direction::DownRight);
+    }
+
+    #[test]
+    fn test_diagonal_trail_seals_corner_region() {
+        let mut game = Game::new(20, 20);
+        game.balls.clear();
+        game.set_movement_mode(MovementMode::EightWay);
+
+        // Cut off the top-left corner with a diagonal from (5, 0) to (0, 5)
+        game.players[0].position = Position::new(5, 0);
+        game.set_direction(Direction::DownLeft);
+        for _ in 0..5 {
+            game.update();
+        }
+
+        assert!(!game.players[0].is_drawing);
+        assert_eq!(game.cell_at(3, 2), Cell::Filled, "trail cell");
+        assert_eq!(game.cell_at(2, 2), Cell::Filled, "corner pocket should be captured");
+        assert_eq!(game.cell_at(1, 1), Cell::Filled, "corner pocket should be captured");
+        assert_eq!(game.cell_at(3, 3), Cell::Empty, "far side of the diagonal stays open");
+    }
+
+    #[test]
+    fn test_crossing_own_diagonal_trail_loses_game() {
+        let mut game = Game::new(20, 20);
+        game.balls.clear();
+        game.set_movement_mode(MovementMode::EightWay);
+
+        game.players[0].position = Position::new(5, 0);
+        game.set_direction(Direction::DownRight);
+        game.update(); // (6, 1)
+        game.update(); // (7, 2)
+        game.set_direction(Direction::Down);
+        game.update(); // (7, 3)
+        game.set_direction(Direction::Left);
+        game.update(); // (6, 3)
+        game.set_direction(Direction::UpLeft);
+        game.update(); // (5, 2)
+        game.set_direction(Direction::Right);
+        game.update(); // (6, 2)
+        assert_eq!(game.state, GameState::Playing);
+
+        // (6, 2) -> (7, 1) slips between trail cells (6, 1) and (7, 2)
+        game.set_direction(Direction::UpRight);
+        game.update();
+        assert_eq!(game.state, GameState::Lost);
+    }
+
+    #[test]
+    fn test_ball_crossing_diagonal_trail_loses_game() {
+        let mut game = Game::new(20, 20);
+        game.balls.clear();
+        game.set_movement_mode(MovementMode::EightWay);
+
+        game.players[0].position = Position::new(5, 0);
+        game.set_direction(Direction::DownRight);
+        game.update(); // (6, 1)
+        game.update(); // (7, 2)
+        game.set_direction(Direction::Down);
+
+        // Ball at (7, 1) heading to (6, 2) passes between (6, 1) and (7, 2)
+        game.balls.push(Ball::new(7, 1, -1, 1));
+        game.update();
+        assert_eq!(game.state, GameState::Lost);
+    }
+
+    #[test]
+    fn test_ball_hitting_far_end_of_long_trail_loses_game() {
+        let mut game = Game::new(60, 20);
+        game.balls.clear();
+        for _ in 0..40 {
+            game.update();
+        }
+
+        // A ball passing close by, but not touching, is harmless
+        game.balls.push(Ball::new(3, 8, 1, -1));
+        game.update();
+        assert_eq!(game.state, GameState::Playing);
+
+        // One landing on the first trail cell drawn is not
+        game.balls.push(Ball::new(2, 11, -1, -1));
+        game.update();
+        assert_eq!(game.state, GameState::Lost);
+    }
+
+    #[test]
+    fn test_quick_turns_are_applied_one_per_tick() {
+        let mut game = Game::new(20, 20);
+        game.balls.clear();
+        game.players[0].position = Position::new(5, 0);
+        game.players[0].direction = Direction::Down;
+        game.update(); // (5, 1), drawing
+
+        // "Right then up" entered within a single tick
+        game.set_direction(Direction::Right);
+        game.set_direction(Direction::Up);
+        assert_eq!(game.players[0].direction, Direction::Right);
+
+        game.update();
+        assert_eq!(game.players[0].position, Position::new(6, 1));
+        game.update();
+        assert_eq!(game.players[0].position, Position::new(6, 0));
+        assert!(!game.players[0].is_drawing);
+    }
+
+    #[test]
+    fn test_queued_reversal_is_dropped_but_later_turns_apply() {
+        let mut game = Game::new(20, 20);
+        game.balls.clear();
+        game.players[0].position = Position::new(5, 0);
+        game.players[0].direction = Direction::Down;
+        game.update(); // (5, 1), drawing
+        game.update(); // (5, 2)
+
+        game.set_direction(Direction::Right);
+        game.set_direction(Direction::Left); // Reversal of Right once it applies
+        game.set_direction(Direction::Down);
+
+        game.update();
+        assert_eq!(game.players[0].position, Position::new(6, 2));
+        game.update();
+        assert_eq!(game.players[0].direction, Direction::Down);
+        assert_eq!(game.players[0].position, Position::new(6, 3));
+        assert_eq!(game.state, GameState::Playing);
+    }
+
+    #[test]
+    fn test_turn_queue_is_bounded() {
+        let mut game = Game::new(20, 20);
+        game.set_direction(Direction::Down);
+        for direction in [Direction::Left, Direction::Up, Direction::Right, Direction::Down, Direction::Left] {
+            game.set_direction(direction);
+        }
+        assert_eq!(game.players[0].turn_queue.len(), TURN_QUEUE_CAPACITY);
+    }
+
+    #[test]
+    fn test_slow_draw_does_not_skip_queued_turns() {
+        let mut game = Game::new(20, 20);
+        game.balls.clear();
+        game.toggle_draw_speed();
+        game.players[0].position = Position::new(5, 0);
+        game.players[0].direction = Direction::Down;
+        game.update(); // (5, 1), drawing
+
+        game.set_direction(Direction::Right);
+        game.set_direction(Direction::Up);
+        let mut visited = Vec::new();
+        for _ in 0..4 {
+            game.update();
+            if visited.last() != Some(&game.players[0].position) {
+                visited.push(game.players[0].position);
+            }
+        }
+        assert_eq!(visited, vec![Position::new(5, 1), Position::new(6, 1), Position::new(6, 0)]);
+    }
+
+    #[test]
+    fn test_hold_stops_player_on_filled_territory() {
+        let mut game = Game::new(20, 20);
+        game.balls.clear();
+        game.balls.push(Ball::new(10, 10, 1, 1));
+        let start = game.players[0].position;
+
+        game.toggle_hold();
+        game.update();
+        game.update();
+
+        assert_eq!(game.players[0].position, start);
+        assert_eq!(game.balls[0].position, Position::new(12, 12), "balls keep moving");
+
+        // Turning releases the hold
+        game.set_direction(Direction::Down);
+        assert!(!game.players[0].holding);
+        game.update();
+        assert_eq!(game.players[0].position, Position::new(start.x, start.y + 1));
+    }
+
+    #[test]
+    fn test_hold_is_ignored_while_drawing() {
+        let mut game = Game::new(20, 20);
+        game.balls.clear();
+        game.players[0].position = Position::new(5, 0);
+        game.set_direction(Direction::Down);
+        game.update(); // (5, 1), drawing
+
+        game.toggle_hold();
+        assert!(!game.players[0].holding);
+        game.update();
+        assert_eq!(game.players[0].position, Position::new(5, 2));
+    }
+
+    /// Two players on a 20x20 board: P1 drawing down column 10, P2 drawing
+    /// down column 7 and then turning right into P1's trail on the third tick
+    fn cut_across_trail(target_mode: TargetMode) -> Game {
+        let mut game = Game::with_players(20, 20, 2);
+        game.set_target_mode(target_mode);
+        game.balls.clear();
+        game.players[0].position = Position::new(7, 0);
+        game.players[0].direction = Direction::Down;
+        game.players[1].position = Position::new(10, 0);
+        game.players[1].direction = Direction::Down;
+
+        game.update();
+        game.update(); // P1 at (7, 2), P2 at (10, 2)
+        game.set_direction(Direction::Right);
+        game.update();
+        game.update(); // P1 at (9, 2), P2 at (10, 4)
+        game.update(); // P1 steps onto (10, 2)
+        game
+    }
+
+    #[test]
+    fn test_players_start_on_opposite_edges() {
+        let game = Game::with_players(20, 20, 2);
+        assert_eq!(game.players.len(), 2);
+        assert_eq!(game.players[0].position, Position::new(0, 10));
+        assert_eq!(game.players[0].direction, Direction::Right);
+        assert_eq!(game.players[1].position, Position::new(19, 10));
+        assert_eq!(game.players[1].direction, Direction::Left);
+
+        assert_eq!(Game::with_players(20, 20, 9).players.len(), MAX_PLAYERS);
+    }
+
+    #[test]
+    fn test_cutting_trail_in_shared_mode_loses_game() {
+        let game = cut_across_trail(TargetMode::Shared);
+        assert_eq!(game.state, GameState::Lost);
+    }
+
+    #[test]
+    fn test_cutting_trail_in_competitive_mode_knocks_out_owner() {
+        let game = cut_across_trail(TargetMode::Competitive);
+        assert_eq!(game.state, GameState::Playing);
+        assert!(game.players[0].alive);
+        assert!(!game.players[1].alive);
+
+        // The cut trail is wiped and the cutter carries on drawing through it
+        assert_eq!(game.cell_at(10, 1), Cell::Empty);
+        assert_eq!(game.cell_at(10, 3), Cell::Empty);
+        assert_eq!(game.cell_at(10, 2), Cell::Trail);
+        assert_eq!(game.players[0].position, Position::new(10, 2));
+        assert_eq!(game.trail_owner(Position::new(10, 2)), Some(0));
+    }
+
+    #[test]
+    fn test_competitive_game_lost_when_every_player_is_out() {
+        let mut game = Game::with_players(20, 20, 2);
+        game.set_target_mode(TargetMode::Competitive);
+        game.players[0].position = Position::new(5, 0);
+        game.players[0].direction = Direction::Down;
+        game.players[1].position = Position::new(15, 0);
+        game.players[1].direction = Direction::Down;
+
+        // Each ball lands on a player's first trail cell
+        game.balls = vec![Ball::new(6, 2, -1, -1), Ball::new(16, 2, -1, -1)];
+        game.update();
+
+        assert!(game.players.iter().all(|player| !player.alive));
+        assert_eq!(game.state, GameState::Lost);
+        assert_eq!(game.cell_at(5, 1), Cell::Empty);
+    }
+
+    #[test]
+    fn test_capture_never_buries_another_drawing_player() {
+        let mut game = Game::with_players(30, 30, 2);
+        game.balls.clear();
+        for y in 1..29 {
+            game.board.set(10, y, Cell::Filled);
+        }
+
+        // P1 is on the left; the right side would normally be captured
+        game.players[0].position = Position::new(5, 10);
+        game.players[1].position = Position::new(20, 10);
+        game.players[1].start_trail();
+        game.board.set(20, 10, Cell::Trail);
+
+        assert!(game.fill_enclosed_areas(0).is_empty());
+        assert_eq!(game.cell_at(25, 10), Cell::Empty);
+    }
+
+    #[test]
+    fn test_competitive_winner_has_highest_score() {
+        let mut game = Game::with_players(20, 20, 2);
+        game.players[0].score = 40;
+        game.players[1].score = 55;
+        game.state = GameState::Won;
+        assert_eq!(game.winner(), None); // Shared games have no individual winner
+
+        game.set_target_mode(TargetMode::Competitive);
+        assert_eq!(game.winner(), Some(1));
+
+        game.players[0].score = 55;
+        assert_eq!(game.winner(), None); // Tie
+    }
+
+    #[test]
+    fn test_next_level_keeps_player_scores() {
+        let mut game = Game::with_players(20, 20, 2);
+        game.players[0].score = 12;
+        game.players[1].score = 34;
+        game.players[1].alive = false;
+        game.next_level();
+
+        assert_eq!(game.players[0].score, 12);
+        assert_eq!(game.players[1].score, 34);
+        assert!(game.players[1].alive);
+        assert_eq!(game.players[1].position, Position::new(19, 10));
+    }
+
+    #[test]
+    fn test_capture_on_large_board() {
+        let mut game = Game::with_seed(1000, 1000, 1, 1);
+        game.balls.clear();
+
+        // Three cells in, then straight up to the top border
+        for _ in 0..3 {
+            game.update();
+        }
+        game.set_direction(Direction::Up);
+        while game.players[0].is_drawing {
+            game.update();
+        }
+
+        // The 2x499 pocket behind the trail is captured along with the trail itself
+        assert_eq!(game.state, GameState::Playing);
+        assert_eq!(game.cell_at(1, 1), Cell::Filled);
+        assert_eq!(game.cell_at(4, 1), Cell::Empty);
+        assert_eq!(game.board.interior_filled(), 2 * 499 + 3 + 499);
+        assert_eq!(game.filled_percentage, 1500.0 / (998.0 * 998.0));
+    }
+
+    #[test]
+    fn test_same_seed_same_game() {
+        let mut a = Game::with_seed(30, 20, 2, 7);
+        let mut b = Game::with_seed(30, 20, 2, 7);
+        assert_eq!(a.state_hash(), b.state_hash());
+
+        for direction in [Direction::Down, Direction::Right, Direction::Up] {
+            a.set_direction(direction);
+            b.set_direction(direction);
+            for _ in 0..3 {
+                a.update();
+                b.update();
+            }
+            assert_eq!(a.state_hash(), b.state_hash());
+        }
+
+        // Resets draw from the game's own stream, so they stay in step too
+        a.reset();
+        b.reset();
+        assert_eq!(a.state_hash(), b.state_hash());
+
+        b.set_direction(Direction::Down);
+        b.update();
+        a.update();
+        assert_ne!(a.state_hash(), b.state_hash());
+    }
+
+    #[test]
+    fn test_holding_doesnt_shift_the_slow_draw_cadence() {
+        let start = |held_ticks| {
+            let mut game = Game::with_seed(30, 20, 1, 7);
+            game.balls.clear();
+            game.toggle_draw_speed();
+            game.toggle_hold();
+            for _ in 0..held_ticks {
+                game.update();
+            }
+            game.toggle_hold();
+            game.update();
+            game.players[0].position
+        };
+        assert_eq!(start(0), Position::new(1, 10));
+        assert_eq!(start(3), start(0));
+        assert_eq!(start(4), start(0));
+    }
+
+    #[test]
+    fn test_state_hash_sees_state_that_isnt_on_the_board() {
+        let base = Game::with_seed(30, 20, 1, 7);
+        let changes: [fn(&mut Game); 5] = [
+            |game| game.toggle_draw_speed(),
+            |game| game.players[0].holding = true,
+            |game| game.players[0].turn_queue.push_back(Direction::Down),
+            |game| game.target_percentage = 0.8,
+            // Same balls, but the next spawns would differ
+            |game| game.rng = StdRng::seed_from_u64(8),
+        ];
+        for change in changes {
+            let mut game = Game::with_seed(30, 20, 1, 7);
+            change(&mut game);
+            assert_ne!(game.state_hash(), base.state_hash());
+        }
     }
 
     #[test]
@@ -1188,8 +2200,8 @@ mod tests {
         game.balls.clear();
 
         // Start on border and move into empty space
-        game.player.position.x = 1;
-        game.player.position.y = 0; // On top border
+        game.players[0].position.x = 1;
+        game.players[0].position.y = 0; // On top border
         game.set_direction(Direction::Down);
         game.update(); // Now at (1,1), drawing started
 
@@ -1222,16 +2234,16 @@ mod tests {
         // First, create some filled territory in the middle
         // Mark a horizontal line as filled (simulating previous gameplay)
         for x in 1..19 {
-            game.board[5][x as usize] = Cell::Filled;
+            game.board.set(x, 5, Cell::Filled);
         }
         game.update_filled_percentage();
         let filled_after_setup = game.filled_percentage;
 
         // Position player on the filled line
-        game.player.position.x = 10;
-        game.player.position.y = 5;
-        game.player.is_drawing = false;
-        game.player.direction = Direction::Down;
+        game.players[0].position.x = 10;
+        game.players[0].position.y = 5;
+        game.players[0].is_drawing = false;
+        game.players[0].direction = Direction::Down;
 
         // Move down into empty space (starting a trail)
         game.update(); // (10, 6) - drawing started
@@ -1275,9 +2287,9 @@ mod tests {
         game.balls.clear();
         let initial_fill = game.filled_percentage;
 
-        game.player.position.x = 5;
-        game.player.position.y = 0;
-        game.player.direction = Direction::Down;
+        game.players[0].position.x = 5;
+        game.players[0].position.y = 0;
+        game.players[0].direction = Direction::Down;
 
         game.update();
         game.set_direction(Direction::Right);
@@ -1296,7 +2308,7 @@ mod tests {
         game.update();
 
         assert!(game.filled_percentage > initial_fill);
-        assert_eq!(game.board[2][5], Cell::Filled);
+        assert_eq!(game.board.get(5, 2), Cell::Filled);
     }
 
     #[test]
@@ -1305,11 +2317,11 @@ mod tests {
         game.balls.clear();
 
         for y in 1..19 {
-            game.board[y][10] = Cell::Filled;
+            game.board.set(10, y, Cell::Filled);
         }
 
         game.balls.push(Ball::new(5, 10, 1, 1));
-        game.fill_enclosed_areas();
+        game.fill_enclosed_areas(0);
 
         assert_eq!(game.cell_at(5, 10), Cell::Empty);
         assert_eq!(game.cell_at(15, 10), Cell::Filled);
@@ -1321,13 +2333,13 @@ mod tests {
         game.balls.clear();
 
         for y in 1..19 {
-            game.board[y][5] = Cell::Filled;
-            game.board[y][15] = Cell::Filled;
+            game.board.set(5, y, Cell::Filled);
+            game.board.set(15, y, Cell::Filled);
         }
 
         game.balls.push(Ball::new(3, 10, 1, 1));
         game.balls.push(Ball::new(17, 10, 1, 1));
-        game.fill_enclosed_areas();
+        game.fill_enclosed_areas(0);
 
         assert_eq!(game.cell_at(3, 10), Cell::Empty);
         assert_eq!(game.cell_at(17, 10), Cell::Empty);
@@ -1349,12 +2361,12 @@ mod tests {
 
         // Vertical divider at x=10 (creates left section)
         for y in 1..29 {
-            game.board[y][10] = Cell::Filled;
+            game.board.set(10, y, Cell::Filled);
         }
 
         // Vertical divider at x=15 (creates small center section where player is)
         for y in 1..29 {
-            game.board[y][15] = Cell::Filled;
+            game.board.set(15, y, Cell::Filled);
         }
 
         // Now we have 3 regions:
@@ -1363,8 +2375,8 @@ mod tests {
         // 3. Right (x=16-28): size ~364 cells - LARGEST!
 
         // Place player in the center (small) region
-        game.player.position.x = 12;
-        game.player.position.y = 10;
+        game.players[0].position.x = 12;
+        game.players[0].position.y = 10;
 
         // Place a ball in the left region only
         game.balls.push(Ball::new(5, 10, 1, 1));
@@ -1373,7 +2385,7 @@ mod tests {
         // the right region is the outside and not fill it.
         // With new logic (player's region = outside), it correctly identifies
         // center as outside and fills the large right region.
-        game.fill_enclosed_areas();
+        game.fill_enclosed_areas(0);
 
         // CRITICAL: The large RIGHT region (no ball) should be filled
         assert_eq!(
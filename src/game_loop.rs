@@ -0,0 +1,265 @@
+//! Fixed-timestep driver shared by every front end.
+//!
+//! The game always advances in whole ticks of `TICK_INTERVAL`. A `GameLoop`
+//! reads a `Clock` once per frame and says how many ticks are owed, so a slow
+//! frame is made up with extra ticks instead of slowing the game down. After
+//! a long stall (a suspended laptop, a hidden browser tab) only a few ticks
+//! are caught up and the rest of the time is dropped, so the game never
+//! spends its frames running ticks it can't keep up with.
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Game logic runs at 10 Hz
+pub const TICK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Most ticks caught up in one go before leftover time is dropped
+pub const MAX_CATCH_UP_TICKS: u32 = 5;
+
+/// A source of time for the loop to measure against
+pub trait Clock {
+    /// Time since some fixed start; must never go backwards
+    fn now(&self) -> Duration;
+}
+
+/// Wall-clock time from `std::time::Instant` (not available on wasm)
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock {
+    start: std::time::Instant,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self {
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// A clock that only moves when told to. Clones share the same time, so a
+/// test can keep one and hand the other to a `GameLoop`.
+#[derive(Debug, Clone, Default)]
+pub struct FakeClock {
+    now: Rc<Cell<Duration>>,
+}
+
+impl FakeClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.now.set(self.now.get() + by);
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
+
+pub struct GameLoop<C: Clock> {
+    clock: C,
+    tick_interval: Duration,
+    max_catch_up: u32,
+    /// Clock reading as of the last catch-up
+    last: Duration,
+    /// Time owed to the game that hasn't been ticked away yet
+    owed: Duration,
+    paused: bool,
+}
+
+impl<C: Clock> GameLoop<C> {
+    /// A loop ticking every `TICK_INTERVAL`, starting from the clock's current time
+    pub fn new(clock: C) -> Self {
+        let last = clock.now();
+        Self {
+            clock,
+            tick_interval: TICK_INTERVAL,
+            max_catch_up: MAX_CATCH_UP_TICKS,
+            last,
+            owed: Duration::ZERO,
+            paused: false,
+        }
+    }
+
+    pub fn set_tick_interval(&mut self, tick_interval: Duration) {
+        self.tick_interval = tick_interval;
+    }
+
+    /// Most ticks owed at once (at least 1); older time is dropped
+    pub fn set_max_catch_up(&mut self, ticks: u32) {
+        self.max_catch_up = ticks.max(1);
+    }
+
+    pub fn tick_interval(&self) -> Duration {
+        self.tick_interval
+    }
+
+    /// Read the clock and say whether a tick is owed. Doesn't take it; call
+    /// `take_tick` once it has actually run.
+    pub fn tick_due(&mut self) -> bool {
+        self.catch_up();
+        !self.paused && self.owed >= self.tick_interval
+    }
+
+    /// Add the time since the last reading to what's owed, unless paused
+    fn catch_up(&mut self) {
+        let now = self.clock.now();
+        if !self.paused {
+            let cap = self.tick_interval * self.max_catch_up;
+            self.owed = (self.owed + now.saturating_sub(self.last)).min(cap);
+        }
+        self.last = now;
+    }
+
+    /// Count one owed tick as run
+    pub fn take_tick(&mut self) {
+        self.owed = self.owed.saturating_sub(self.tick_interval);
+    }
+
+    /// `tick_due` and `take_tick` together, for callers that always run the
+    /// tick: `while game_loop.next_tick() { game.update(); }`
+    pub fn next_tick(&mut self) -> bool {
+        let due = self.tick_due();
+        if due {
+            self.take_tick();
+        }
+        due
+    }
+
+    /// How far into the next tick we are, from 0 to 1, for interpolating
+    /// positions between ticks. Reflects the clock as of the last `tick_due`.
+    pub fn progress(&self) -> f64 {
+        (self.owed.as_secs_f64() / self.tick_interval.as_secs_f64()).min(1.0)
+    }
+
+    /// Stop owing ticks. Time spent paused is never caught up.
+    pub fn pause(&mut self) {
+        self.catch_up();
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.catch_up();
+        self.paused = false;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        if self.paused {
+            self.resume();
+        } else {
+            self.pause();
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+
+    /// A loop on a fake clock, plus a handle to move the clock
+    fn fake_loop() -> (GameLoop<FakeClock>, FakeClock) {
+        let clock = FakeClock::new();
+        (GameLoop::new(clock.clone()), clock)
+    }
+
+    fn ticks_owed(game_loop: &mut GameLoop<FakeClock>) -> u32 {
+        let mut ticks = 0;
+        while game_loop.next_tick() {
+            ticks += 1;
+        }
+        ticks
+    }
+
+    #[test]
+    fn test_ticks_at_the_fixed_rate() {
+        let (mut game_loop, clock) = fake_loop();
+        assert_eq!(ticks_owed(&mut game_loop), 0);
+
+        clock.advance(Duration::from_millis(60));
+        assert_eq!(ticks_owed(&mut game_loop), 0);
+        assert!((game_loop.progress() - 0.6).abs() < 1e-9);
+
+        // The leftover 60ms carries over instead of being dropped
+        clock.advance(Duration::from_millis(60));
+        assert_eq!(ticks_owed(&mut game_loop), 1);
+        clock.advance(Duration::from_millis(80));
+        assert_eq!(ticks_owed(&mut game_loop), 1);
+    }
+
+    #[test]
+    fn test_slow_frames_catch_up() {
+        let (mut game_loop, clock) = fake_loop();
+        clock.advance(Duration::from_millis(350));
+        assert_eq!(ticks_owed(&mut game_loop), 3);
+        clock.advance(Duration::from_millis(50));
+        assert_eq!(ticks_owed(&mut game_loop), 1);
+    }
+
+    #[test]
+    fn test_long_stalls_are_capped() {
+        let (mut game_loop, clock) = fake_loop();
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(ticks_owed(&mut game_loop), MAX_CATCH_UP_TICKS);
+
+        game_loop.set_max_catch_up(2);
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(ticks_owed(&mut game_loop), 2);
+    }
+
+    #[test]
+    fn test_paused_time_is_not_caught_up() {
+        let (mut game_loop, clock) = fake_loop();
+        clock.advance(Duration::from_millis(50));
+        game_loop.pause();
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(ticks_owed(&mut game_loop), 0);
+
+        // The 50ms from before the pause still counts
+        game_loop.toggle_pause();
+        assert!(!game_loop.is_paused());
+        clock.advance(Duration::from_millis(50));
+        assert_eq!(ticks_owed(&mut game_loop), 1);
+    }
+
+    #[test]
+    fn test_tick_left_owed_until_taken() {
+        let (mut game_loop, clock) = fake_loop();
+        clock.advance(Duration::from_millis(100));
+        // Still owed while the caller can't run it (e.g. waiting on a peer)
+        assert!(game_loop.tick_due());
+        assert!(game_loop.tick_due());
+        game_loop.take_tick();
+        assert!(!game_loop.tick_due());
+    }
+
+    #[test]
+    fn test_game_ticks_follow_the_clock() {
+        let (mut game_loop, clock) = fake_loop();
+        let mut game = Game::with_seed(30, 20, 1, 1);
+        for _ in 0..25 {
+            clock.advance(Duration::from_millis(40));
+            while game_loop.next_tick() {
+                game.update();
+            }
+        }
+        assert_eq!(game.tick, 10);
+    }
+}
@@ -0,0 +1,471 @@
+//! Which keys do what.
+//!
+//! Keys have the same names on every front end ("Up", "Space", "q"), so the
+//! CLI's config file and the browser's localStorage share one text format,
+//! one action per line:
+//!
+//! ```text
+//! # Start from a preset (default, wasd or vim), then change single actions
+//! preset = vim
+//! hold = x Enter
+//! ```
+//!
+//! A line replaces all of its action's keys. A key can only do one thing,
+//! so text that binds one key twice is rejected with every conflict listed.
+
+use crate::entity::Direction;
+use crate::renderer::Input;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A key, with letters always lowercase
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Key {
+    Char(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Enter,
+    Esc,
+    Tab,
+    Backspace,
+}
+
+const NAMED_KEYS: [(&str, Key); 13] = [
+    ("Up", Key::Up),
+    ("Down", Key::Down),
+    ("Left", Key::Left),
+    ("Right", Key::Right),
+    ("Home", Key::Home),
+    ("End", Key::End),
+    ("PageUp", Key::PageUp),
+    ("PageDown", Key::PageDown),
+    ("Enter", Key::Enter),
+    ("Esc", Key::Esc),
+    ("Tab", Key::Tab),
+    ("Backspace", Key::Backspace),
+    ("Space", Key::Char(' ')),
+];
+
+impl Key {
+    /// A letter or symbol key; letters are folded to lowercase
+    pub fn char(c: char) -> Self {
+        Key::Char(c.to_ascii_lowercase())
+    }
+
+    /// A key by its name in the config format, ignoring case
+    pub fn parse(name: &str) -> Option<Self> {
+        if let Some(&(_, key)) = NAMED_KEYS.iter().find(|(known, _)| known.eq_ignore_ascii_case(name)) {
+            return Some(key);
+        }
+        let mut chars = name.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if !c.is_whitespace() && c != '#' && c != '=' => Some(Self::char(c)),
+            _ => None,
+        }
+    }
+
+    /// A key from a browser `KeyboardEvent.key`
+    pub fn from_web(key: &str) -> Option<Self> {
+        match key {
+            "ArrowUp" => Some(Key::Up),
+            "ArrowDown" => Some(Key::Down),
+            "ArrowLeft" => Some(Key::Left),
+            "ArrowRight" => Some(Key::Right),
+            "Escape" => Some(Key::Esc),
+            " " => Some(Key::Char(' ')),
+            _ => Self::parse(key),
+        }
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match NAMED_KEYS.iter().find(|(_, key)| key == self) {
+            Some((name, _)) => f.write_str(name),
+            None => match self {
+                Key::Char(c) => write!(f, "{}", c),
+                _ => unreachable!("every other key is named"),
+            },
+        }
+    }
+}
+
+/// Everything a key can be bound to, with its name in the config format
+pub const ACTIONS: [(&str, Input); 19] = [
+    ("up", Input::Direction(Direction::Up)),
+    ("down", Input::Direction(Direction::Down)),
+    ("left", Input::Direction(Direction::Left)),
+    ("right", Input::Direction(Direction::Right)),
+    ("up-left", Input::Direction(Direction::UpLeft)),
+    ("up-right", Input::Direction(Direction::UpRight)),
+    ("down-left", Input::Direction(Direction::DownLeft)),
+    ("down-right", Input::Direction(Direction::DownRight)),
+    ("p2-up", Input::PlayerDirection(1, Direction::Up)),
+    ("p2-down", Input::PlayerDirection(1, Direction::Down)),
+    ("p2-left", Input::PlayerDirection(1, Direction::Left)),
+    ("p2-right", Input::PlayerDirection(1, Direction::Right)),
+    ("hold", Input::Hold),
+    ("slow-draw", Input::ToggleSlowDraw),
+    ("pause", Input::Pause),
+    ("confirm", Input::Confirm),
+    ("restart", Input::Restart),
+    ("next-level", Input::NextLevel),
+    ("quit", Input::Quit),
+];
+
+/// Names accepted by `KeyBindings::preset`
+pub const PRESETS: [&str; 3] = ["default", "wasd", "vim"];
+
+/// Player 1 on the arrows and player 2 on WASD
+const DEFAULT_KEYS: [(&str, &[Key]); 19] = [
+    ("up", &[Key::Up]),
+    ("down", &[Key::Down]),
+    ("left", &[Key::Left]),
+    ("right", &[Key::Right]),
+    // Numpad 7/9/1/3 without num lock
+    ("up-left", &[Key::Home]),
+    ("up-right", &[Key::PageUp]),
+    ("down-left", &[Key::End]),
+    ("down-right", &[Key::PageDown]),
+    ("p2-up", &[Key::Char('w')]),
+    ("p2-down", &[Key::Char('s')]),
+    ("p2-left", &[Key::Char('a')]),
+    ("p2-right", &[Key::Char('d')]),
+    ("hold", &[Key::Char('x')]),
+    ("slow-draw", &[Key::Char('z')]),
+    ("pause", &[Key::Char('p'), Key::Esc]),
+    ("confirm", &[Key::Enter]),
+    ("restart", &[Key::Char('r')]),
+    ("next-level", &[Key::Char(' ')]),
+    ("quit", &[Key::Char('q')]),
+];
+
+/// Changes from the default: player 2 moves over to IJKL, freeing WASD for
+/// player 1 alongside the arrows
+const WASD_KEYS: [(&str, &[Key]); 8] = [
+    ("p2-up", &[Key::Char('i')]),
+    ("p2-down", &[Key::Char('k')]),
+    ("p2-left", &[Key::Char('j')]),
+    ("p2-right", &[Key::Char('l')]),
+    ("up", &[Key::Up, Key::Char('w')]),
+    ("down", &[Key::Down, Key::Char('s')]),
+    ("left", &[Key::Left, Key::Char('a')]),
+    ("right", &[Key::Right, Key::Char('d')]),
+];
+
+/// Changes from the default: player 1 on hjkl too, with yubn diagonals
+const VIM_KEYS: [(&str, &[Key]); 8] = [
+    ("up", &[Key::Up, Key::Char('k')]),
+    ("down", &[Key::Down, Key::Char('j')]),
+    ("left", &[Key::Left, Key::Char('h')]),
+    ("right", &[Key::Right, Key::Char('l')]),
+    ("up-left", &[Key::Home, Key::Char('y')]),
+    ("up-right", &[Key::PageUp, Key::Char('u')]),
+    ("down-left", &[Key::End, Key::Char('b')]),
+    ("down-right", &[Key::PageDown, Key::Char('n')]),
+];
+
+/// The config name of `input`, if it can be bound
+pub fn action_name(input: Input) -> Option<&'static str> {
+    ACTIONS.iter().find(|&&(_, action)| action == input).map(|&(name, _)| name)
+}
+
+fn action_by_name(name: &str) -> Option<Input> {
+    ACTIONS.iter().find(|&&(known, _)| known == name).map(|&(_, input)| input)
+}
+
+/// A key that's wanted for one action but already does another
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conflict {
+    pub key: Key,
+    pub bound_to: Input,
+    pub wanted_by: Input,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} is already bound to {}, so it can't also be {}",
+            self.key,
+            action_name(self.bound_to).unwrap_or("something else"),
+            action_name(self.wanted_by).unwrap_or("that"),
+        )
+    }
+}
+
+impl std::error::Error for Conflict {}
+
+/// Everything wrong with a bindings text, one problem per line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BindingsError {
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for BindingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.problems.join("\n"))
+    }
+}
+
+impl std::error::Error for BindingsError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyBindings {
+    keys: BTreeMap<Key, Input>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self::preset("default").expect("the default preset exists")
+    }
+}
+
+impl KeyBindings {
+    /// One of `PRESETS`
+    pub fn preset(name: &str) -> Option<Self> {
+        let changes: &[(&str, &[Key])] = match name {
+            "default" => &[],
+            "wasd" => &WASD_KEYS,
+            "vim" => &VIM_KEYS,
+            _ => return None,
+        };
+        let mut bindings = Self { keys: BTreeMap::new() };
+        for &(action, keys) in DEFAULT_KEYS.iter().chain(changes) {
+            let input = action_by_name(action).expect("presets only name actions");
+            bindings.set_keys(input, keys).expect("presets have no conflicts");
+        }
+        Some(bindings)
+    }
+
+    /// Bindings from the config format, starting from the default preset.
+    /// The lines apply together, so two actions can swap keys in any order.
+    pub fn parse(text: &str) -> Result<Self, BindingsError> {
+        let mut bindings = Self::default();
+        let mut problems: Vec<(usize, String)> = Vec::new();
+        // Line number and keys for each action the text sets
+        let mut changes: Vec<(usize, Input, Vec<Key>)> = Vec::new();
+        for (idx, line) in text.lines().enumerate() {
+            let line_no = idx + 1;
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let Some((name, value)) = line.split_once('=') else {
+                problems.push((line_no, format!("expected `action = keys`, got `{}`", line)));
+                continue;
+            };
+            let (name, value) = (name.trim(), value.trim());
+            if name == "preset" {
+                match Self::preset(value) {
+                    Some(preset) => bindings = preset,
+                    None => problems.push((line_no, format!("unknown preset `{}` (try {})", value, PRESETS.join(", ")))),
+                }
+                continue;
+            }
+            let Some(input) = action_by_name(name) else {
+                problems.push((line_no, format!("unknown action `{}`", name)));
+                continue;
+            };
+            let mut keys = Vec::new();
+            for key_name in value.split_whitespace() {
+                match Key::parse(key_name) {
+                    Some(key) => keys.push(key),
+                    None => problems.push((line_no, format!("unknown key `{}`", key_name))),
+                }
+            }
+            changes.retain(|&(_, changed, _)| changed != input);
+            changes.push((line_no, input, keys));
+        }
+
+        bindings.keys.retain(|_, bound| !changes.iter().any(|&(_, input, _)| input == *bound));
+        for (line_no, input, keys) in changes {
+            for key in keys {
+                match bindings.input_for(key) {
+                    Some(bound) if bound != input => {
+                        let conflict = Conflict {
+                            key,
+                            bound_to: bound,
+                            wanted_by: input,
+                        };
+                        problems.push((line_no, conflict.to_string()));
+                    }
+                    _ => {
+                        bindings.keys.insert(key, input);
+                    }
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(bindings)
+        } else {
+            problems.sort_by_key(|&(line_no, _)| line_no);
+            Err(BindingsError {
+                problems: problems.into_iter().map(|(line_no, message)| format!("line {}: {}", line_no, message)).collect(),
+            })
+        }
+    }
+
+    /// These bindings in the config format, every action spelled out
+    pub fn to_config(&self) -> String {
+        let mut text = String::new();
+        for (name, input) in ACTIONS {
+            let keys: Vec<String> = self.keys_for(input).iter().map(Key::to_string).collect();
+            text.push_str(&format!("{} = {}\n", name, keys.join(" ")).replace(" \n", "\n"));
+        }
+        text
+    }
+
+    pub fn input_for(&self, key: Key) -> Option<Input> {
+        self.keys.get(&key).copied()
+    }
+
+    pub fn keys_for(&self, input: Input) -> Vec<Key> {
+        self.keys
+            .iter()
+            .filter(|&(_, &bound)| bound == input)
+            .map(|(&key, _)| key)
+            .collect()
+    }
+
+    /// The keys for `input` as help text shows them, letters in capitals,
+    /// e.g. "P/Esc"
+    pub fn describe(&self, input: Input) -> String {
+        let names: Vec<String> = self.keys_for(input).into_iter().map(Self::describe_key).collect();
+        names.join("/")
+    }
+
+    /// The keys moving player `idx` up, left, down and right, e.g.
+    /// "Arrow Keys" or "Arrow Keys or K/H/J/L"
+    pub fn describe_moves(&self, idx: usize) -> String {
+        const WAYS: [(Direction, Key); 4] = [
+            (Direction::Up, Key::Up),
+            (Direction::Left, Key::Left),
+            (Direction::Down, Key::Down),
+            (Direction::Right, Key::Right),
+        ];
+        let input = |direction| match idx {
+            0 => Input::Direction(direction),
+            _ => Input::PlayerDirection(idx, direction),
+        };
+        let arrows = WAYS.iter().all(|&(direction, arrow)| self.input_for(arrow) == Some(input(direction)));
+        let others: Option<Vec<String>> = WAYS
+            .iter()
+            .map(|&(direction, arrow)| {
+                let key = self.keys_for(input(direction)).into_iter().find(|&key| key != arrow)?;
+                Some(Self::describe_key(key))
+            })
+            .collect();
+        match (arrows, others) {
+            (true, Some(others)) => format!("Arrow Keys or {}", others.join("/")),
+            (true, None) => "Arrow Keys".to_string(),
+            (false, Some(others)) => others.join("/"),
+            (false, None) => WAYS.iter().map(|&(direction, _)| self.describe(input(direction))).collect::<Vec<_>>().join(" "),
+        }
+    }
+
+    fn describe_key(key: Key) -> String {
+        match key {
+            Key::Char(c) if c != ' ' => c.to_ascii_uppercase().to_string(),
+            _ => key.to_string(),
+        }
+    }
+
+    /// Bind `keys` to `input` in place of whatever it had. Nothing changes
+    /// if one of them already does something else.
+    pub fn set_keys(&mut self, input: Input, keys: &[Key]) -> Result<(), Conflict> {
+        if let Some(&key) = keys.iter().find(|key| self.input_for(**key).is_some_and(|bound| bound != input)) {
+            return Err(Conflict {
+                key,
+                bound_to: self.keys[&key],
+                wanted_by: input,
+            });
+        }
+        self.keys.retain(|_, bound| *bound != input);
+        self.keys.extend(keys.iter().map(|&key| (key, input)));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets_keep_the_arrows() {
+        for name in PRESETS {
+            let bindings = KeyBindings::preset(name).unwrap();
+            assert_eq!(bindings.input_for(Key::Up), Some(Input::Direction(Direction::Up)), "{}", name);
+            assert_eq!(bindings.input_for(Key::Char('q')), Some(Input::Quit), "{}", name);
+            // Every action can be reached
+            for (action, input) in ACTIONS {
+                assert!(!bindings.keys_for(input).is_empty(), "{} has no {} key", name, action);
+            }
+        }
+        let vim = KeyBindings::preset("vim").unwrap();
+        assert_eq!(vim.input_for(Key::Char('h')), Some(Input::Direction(Direction::Left)));
+        let wasd = KeyBindings::preset("wasd").unwrap();
+        assert_eq!(wasd.input_for(Key::Char('w')), Some(Input::Direction(Direction::Up)));
+        assert_eq!(wasd.input_for(Key::Char('i')), Some(Input::PlayerDirection(1, Direction::Up)));
+        assert!(KeyBindings::preset("emacs").is_none());
+    }
+
+    #[test]
+    fn test_parse_applies_lines_over_a_preset() {
+        // Enter moves to hold before confirm lets go of it
+        let text = "# mine\npreset = vim\nhold = x Enter  # x or Enter\nconfirm = Tab\n\nquit = Q\n";
+        let bindings = KeyBindings::parse(text).unwrap();
+        assert_eq!(bindings.input_for(Key::Enter), Some(Input::Hold));
+        assert_eq!(bindings.input_for(Key::Tab), Some(Input::Confirm));
+        assert_eq!(bindings.keys_for(Input::Quit), vec![Key::Char('q')]);
+        assert_eq!(bindings.input_for(Key::Char('k')), Some(Input::Direction(Direction::Up)));
+
+        // What's written reads back the same
+        assert_eq!(KeyBindings::parse(&bindings.to_config()).unwrap(), bindings);
+    }
+
+    #[test]
+    fn test_conflicts_are_reported_not_applied() {
+        let mut bindings = KeyBindings::default();
+        let conflict = bindings.set_keys(Input::Hold, &[Key::Char('h'), Key::Char('q')]).unwrap_err();
+        assert_eq!(conflict.key, Key::Char('q'));
+        assert_eq!(conflict.bound_to, Input::Quit);
+        assert_eq!(bindings.keys_for(Input::Hold), vec![Key::Char('x')]);
+
+        let err = KeyBindings::parse("hold = q\nfly = f\nslow-draw = Shift\npreset = emacs").unwrap_err();
+        assert_eq!(err.problems.len(), 4);
+        assert_eq!(err.problems[0], "line 1: q is already bound to quit, so it can't also be hold");
+        assert!(err.problems[1].contains("unknown action `fly`"));
+    }
+
+    #[test]
+    fn test_help_text_follows_the_bindings() {
+        let bindings = KeyBindings::default();
+        assert_eq!(bindings.describe(Input::Pause), "P/Esc");
+        assert_eq!(bindings.describe(Input::NextLevel), "Space");
+        assert_eq!(bindings.describe_moves(0), "Arrow Keys");
+        assert_eq!(bindings.describe_moves(1), "W/A/S/D");
+        assert_eq!(KeyBindings::preset("vim").unwrap().describe_moves(0), "Arrow Keys or K/H/J/L");
+        assert_eq!(KeyBindings::preset("wasd").unwrap().describe_moves(1), "I/J/K/L");
+    }
+
+    #[test]
+    fn test_key_names() {
+        assert_eq!(Key::parse("space"), Some(Key::Char(' ')));
+        assert_eq!(Key::parse("PAGEUP"), Some(Key::PageUp));
+        assert_eq!(Key::parse("R"), Some(Key::Char('r')));
+        assert_eq!(Key::parse("#"), None);
+        assert_eq!(Key::from_web("ArrowLeft"), Some(Key::Left));
+        assert_eq!(Key::from_web("Escape"), Some(Key::Esc));
+        assert_eq!(Key::from_web("Shift"), None);
+        assert_eq!(Key::Char(' ').to_string(), "Space");
+        assert_eq!(Key::PageDown.to_string(), "PageDown");
+    }
+}
@@ -1,23 +1,57 @@
 pub mod game;
+pub mod game_loop;
+pub mod board;
 pub mod entity;
 pub mod renderer;
+pub mod menu;
+pub mod key_bindings;
+pub mod steering;
+pub mod buffer_renderer;
 pub mod ball_trail;
+pub mod capture;
+pub mod snapshot;
+pub mod protocol;
+pub mod lockstep;
 
 #[cfg(not(target_arch = "wasm32"))]
 pub mod cli_renderer;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod theme;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod websocket;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod server;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod spectate;
+
 #[cfg(target_arch = "wasm32")]
 pub mod web_renderer;
 
 #[cfg(target_arch = "wasm32")]
 pub mod web_main;
 
-pub use game::{Game, GameState, Cell};
-pub use entity::{Position, Direction, Player, Ball, Enemy};
+pub use game::{Game, GameState, Cell, FillAlgorithm, SpawnShortfall, TargetMode, MAX_PLAYERS};
+pub use entity::{Position, Direction, DrawSpeed, MovementMode, Player, Ball, Enemy};
+pub use game_loop::{Clock, FakeClock, GameLoop, TICK_INTERVAL};
 pub use renderer::{Renderer, Input};
+pub use menu::{MenuItem, PauseMenu};
+pub use key_bindings::{Key, KeyBindings};
+pub use steering::Steering;
+pub use buffer_renderer::BufferRenderer;
+pub use capture::{CaptureRule, Region, XonixRule, QixRule, SmallerSideRule};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use cli_renderer::{BoardFit, CliRenderer, Density};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use theme::{Glyphs, Palette, Theme};
 
 #[cfg(not(target_arch = "wasm32"))]
-pub use cli_renderer::CliRenderer;
+pub use game_loop::SystemClock;
 
 #[cfg(target_arch = "wasm32")]
 pub use web_renderer::WebRenderer;
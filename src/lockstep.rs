@@ -0,0 +1,515 @@
+//! Lockstep netplay sessions.
+//!
+//! The host owns seat 0 and relays: each tick it waits for every client's
+//! `Input`, broadcasts the combined `Step`, and everyone (host included)
+//! applies it and calls `Game::update`. Games are seeded identically, so the
+//! simulations only diverge on a bug; the state hash sent with each `Input`
+//! catches that on the next tick.
+//!
+//! Sessions never block. Callers pump them from their own loop, which keeps
+//! them usable over TCP and over in-process loopback transports in tests.
+
+use crate::game::Game;
+use crate::protocol::{Command, GameSetup, Message, Transport, PROTOCOL_VERSION};
+use std::io;
+
+/// Most history steps sent to one peer per pump. A rejoining peer catches up
+/// over several pumps, so a long game never floods its outgoing queue.
+const REPLAY_BATCH: usize = 256;
+
+enum Seat {
+    /// The host's own player
+    Local,
+    /// Nobody has joined yet
+    Open,
+    Connected(Box<dyn Transport>),
+    /// Lost its connection; held for a `Rejoin`
+    Disconnected,
+}
+
+pub struct HostSession {
+    pub game: Game,
+    setup: GameSetup,
+    seats: Vec<Seat>,
+    /// Each seat's commands for the current tick, once received
+    ready: Vec<Option<Vec<Command>>>,
+    /// Connections that haven't asked for a seat yet
+    lobby: Vec<Box<dyn Transport>>,
+    local_commands: Vec<Command>,
+    tick: u64,
+    /// Every step so far, replayed to rejoining clients
+    history: Vec<Vec<(usize, Command)>>,
+    /// How many steps of `history` each seat has been sent
+    sent: Vec<usize>,
+    /// `hashes[t]` is the state hash just before step `t`
+    hashes: Vec<u64>,
+    /// First tick a peer disagreed with the host; the session stops there
+    pub desync: Option<u64>,
+}
+
+impl HostSession {
+    pub fn new(setup: GameSetup) -> Self {
+        let game = setup.build();
+        let player_count = game.players.len();
+        let mut seats: Vec<Seat> = (0..player_count).map(|_| Seat::Open).collect();
+        seats[0] = Seat::Local;
+
+        Self {
+            hashes: vec![game.state_hash()],
+            game,
+            setup,
+            seats,
+            ready: vec![None; player_count],
+            lobby: Vec::new(),
+            local_commands: Vec::new(),
+            tick: 0,
+            history: Vec::new(),
+            sent: vec![0; player_count],
+            desync: None,
+        }
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Players whose seats have no live connection
+    pub fn waiting_for(&self) -> Vec<usize> {
+        self.seats
+            .iter()
+            .enumerate()
+            .filter(|(_, seat)| matches!(seat, Seat::Open | Seat::Disconnected))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// Hand over a new connection; it gets a seat once it sends `Join` or `Rejoin`
+    pub fn accept(&mut self, transport: Box<dyn Transport>) {
+        self.lobby.push(transport);
+    }
+
+    /// Queue a command from the host's own player for the next step
+    pub fn push_local(&mut self, command: Command) {
+        self.local_commands.push(command);
+    }
+
+    /// Read everything the peers have sent
+    pub fn pump(&mut self) {
+        self.pump_lobby();
+        self.send_steps();
+
+        for player in 0..self.seats.len() {
+            while let Seat::Connected(transport) = &mut self.seats[player] {
+                match transport.try_recv() {
+                    Ok(Some(Message::Input { tick, hash, commands })) if tick == self.tick => {
+                        if hash != self.hashes[tick as usize] && self.desync.is_none() {
+                            self.desync = Some(tick);
+                            self.broadcast(&Message::Desync { tick });
+                        }
+                        self.ready[player] = Some(commands);
+                    }
+                    // Stale inputs from a client replaying history
+                    Ok(Some(_)) => {}
+                    Ok(None) => break,
+                    Err(_) => {
+                        self.seats[player] = Seat::Disconnected;
+                        self.ready[player] = None;
+                    }
+                }
+            }
+        }
+    }
+
+    fn pump_lobby(&mut self) {
+        let mut waiting = Vec::new();
+
+        for mut transport in std::mem::take(&mut self.lobby) {
+            let seat = match transport.try_recv() {
+                Ok(Some(Message::Join { version })) if version == PROTOCOL_VERSION => self
+                    .seats
+                    .iter()
+                    .position(|seat| matches!(seat, Seat::Open))
+                    .ok_or("game is full"),
+                Ok(Some(Message::Rejoin { version, player })) if version == PROTOCOL_VERSION => {
+                    match self.seats.get(player) {
+                        Some(Seat::Disconnected) => Ok(player),
+                        _ => Err("seat is not free"),
+                    }
+                }
+                Ok(Some(Message::Join { .. } | Message::Rejoin { .. })) => Err("protocol version mismatch"),
+                Ok(Some(_)) => Err("expected JOIN"),
+                Ok(None) => {
+                    waiting.push(transport);
+                    continue;
+                }
+                Err(_) => continue,
+            };
+
+            match seat {
+                Ok(player) => {
+                    let welcome = Message::Welcome {
+                        player,
+                        setup: self.setup.clone(),
+                    };
+                    // The steps so far follow over the next pumps
+                    if transport.send(&welcome).is_ok() {
+                        self.seats[player] = Seat::Connected(transport);
+                        self.sent[player] = 0;
+                    }
+                }
+                Err(reason) => {
+                    let _ = transport.send(&Message::Refused {
+                        reason: reason.to_string(),
+                    });
+                }
+            }
+        }
+
+        self.lobby = waiting;
+    }
+
+    /// Send each peer the steps it hasn't had yet, at most `REPLAY_BATCH` at a time
+    fn send_steps(&mut self) {
+        for player in 0..self.seats.len() {
+            let Seat::Connected(transport) = &mut self.seats[player] else {
+                continue;
+            };
+            let start = self.sent[player];
+            let end = self.history.len().min(start + REPLAY_BATCH);
+            for tick in start..end {
+                let step = Message::Step {
+                    tick: tick as u64,
+                    commands: self.history[tick].clone(),
+                };
+                if transport.send(&step).is_err() {
+                    self.seats[player] = Seat::Disconnected;
+                    self.ready[player] = None;
+                    break;
+                }
+            }
+            self.sent[player] = end;
+        }
+    }
+
+    fn broadcast(&mut self, message: &Message) {
+        for seat in &mut self.seats {
+            if let Seat::Connected(transport) = seat {
+                if transport.send(message).is_err() {
+                    *seat = Seat::Disconnected;
+                }
+            }
+        }
+    }
+
+    /// Advance one tick if every seat has sent its input. Returns false while
+    /// waiting for players (including disconnected ones) or after a desync.
+    pub fn try_step(&mut self) -> bool {
+        if self.desync.is_some() || !self.waiting_for().is_empty() {
+            return false;
+        }
+        if (1..self.seats.len()).any(|player| self.ready[player].is_none()) {
+            return false;
+        }
+
+        let mut commands: Vec<(usize, Command)> = self
+            .local_commands
+            .drain(..)
+            .map(|command| (0, command))
+            .collect();
+        for player in 1..self.seats.len() {
+            let seat_commands = self.ready[player].take().unwrap_or_default();
+            commands.extend(seat_commands.into_iter().map(|command| (player, command)));
+        }
+
+        apply_step(&mut self.game, &commands);
+        self.history.push(commands);
+        self.tick += 1;
+        self.hashes.push(self.game.state_hash());
+        // Every seat has caught up to step here, so this sends just the new step
+        self.send_steps();
+        true
+    }
+}
+
+fn apply_step(game: &mut Game, commands: &[(usize, Command)]) {
+    for &(player, command) in commands {
+        command.apply(game, player);
+    }
+    game.update();
+}
+
+pub struct ClientSession {
+    /// None until the host's `Welcome` arrives
+    pub game: Option<Game>,
+    /// Our seat, known once welcomed
+    pub player: Option<usize>,
+    transport: Box<dyn Transport>,
+    tick: u64,
+    local_commands: Vec<Command>,
+    /// Tick at which the host reported a desync
+    pub desync: Option<u64>,
+}
+
+impl ClientSession {
+    /// Ask the host for a free seat
+    pub fn join(mut transport: Box<dyn Transport>) -> io::Result<Self> {
+        transport.send(&Message::Join {
+            version: PROTOCOL_VERSION,
+        })?;
+        Ok(Self::connecting(transport))
+    }
+
+    /// Reclaim `player`'s seat after a dropped connection; the game is rebuilt
+    /// from the host's history
+    pub fn rejoin(mut transport: Box<dyn Transport>, player: usize) -> io::Result<Self> {
+        transport.send(&Message::Rejoin {
+            version: PROTOCOL_VERSION,
+            player,
+        })?;
+        Ok(Self::connecting(transport))
+    }
+
+    fn connecting(transport: Box<dyn Transport>) -> Self {
+        Self {
+            game: None,
+            player: None,
+            transport,
+            tick: 0,
+            local_commands: Vec::new(),
+            desync: None,
+        }
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Queue a command from our player for the next input we send
+    pub fn push_local(&mut self, command: Command) {
+        self.local_commands.push(command);
+    }
+
+    /// Apply every step the host has sent, then send our input for the next tick.
+    /// Errors if the host refused us or the connection dropped.
+    pub fn pump(&mut self) -> io::Result<()> {
+        let mut needs_input = false;
+
+        while let Some(message) = self.transport.try_recv()? {
+            match message {
+                Message::Welcome { player, setup } => {
+                    self.game = Some(setup.build());
+                    self.player = Some(player);
+                    self.tick = 0;
+                    needs_input = true;
+                }
+                Message::Step { tick, commands } if tick == self.tick => {
+                    if let Some(game) = &mut self.game {
+                        apply_step(game, &commands);
+                        self.tick += 1;
+                        needs_input = true;
+                    }
+                }
+                Message::Desync { tick } => self.desync = Some(tick),
+                Message::Refused { reason } => {
+                    return Err(io::Error::new(io::ErrorKind::ConnectionRefused, reason));
+                }
+                _ => {}
+            }
+        }
+
+        if let (true, Some(game)) = (needs_input, &self.game) {
+            self.transport.send(&Message::Input {
+                tick: self.tick,
+                hash: game.state_hash(),
+                commands: std::mem::take(&mut self.local_commands),
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::{Direction, MovementMode};
+    use crate::game::TargetMode;
+    use crate::protocol::{loopback_pair, TcpTransport};
+
+    fn setup(player_count: usize) -> GameSetup {
+        GameSetup {
+            width: 30,
+            height: 20,
+            player_count,
+            seed: 1234,
+            capture_rule: "xonix".to_string(),
+            movement: MovementMode::FourWay,
+            target_mode: TargetMode::Competitive,
+        }
+    }
+
+    fn connect(host: &mut HostSession) -> ClientSession {
+        let (host_end, client_end) = loopback_pair();
+        host.accept(Box::new(host_end));
+        ClientSession::join(Box::new(client_end)).unwrap()
+    }
+
+    /// Pump everyone until the host has stepped `ticks` more times and every
+    /// client has caught up
+    fn run(host: &mut HostSession, clients: &mut [ClientSession], ticks: u64) {
+        let target = host.tick() + ticks;
+        for _ in 0..(ticks * 10 + 10) {
+            host.pump();
+            for client in clients.iter_mut() {
+                client.pump().unwrap();
+            }
+            if host.tick() < target {
+                host.try_step();
+            } else if clients.iter().all(|client| client.tick() == target) {
+                return;
+            }
+        }
+        panic!("session stalled at tick {}", host.tick());
+    }
+
+    fn client_hash(client: &ClientSession) -> u64 {
+        client.game.as_ref().unwrap().state_hash()
+    }
+
+    #[test]
+    fn test_peers_stay_in_lockstep() {
+        let mut host = HostSession::new(setup(3));
+        let mut clients = vec![connect(&mut host), connect(&mut host)];
+        run(&mut host, &mut clients, 0);
+        assert!(host.waiting_for().is_empty());
+        assert_eq!(clients[0].player, Some(1));
+        assert_eq!(clients[1].player, Some(2));
+
+        let turns = [Direction::Down, Direction::Right, Direction::Up, Direction::Left];
+        for round in 0..20 {
+            host.push_local(Command::Turn(turns[round % 4]));
+            clients[0].push_local(Command::Turn(turns[(round + 1) % 4]));
+            if round % 3 == 0 {
+                clients[1].push_local(Command::ToggleSlowDraw);
+            }
+            run(&mut host, &mut clients, 2);
+
+            for client in &clients {
+                assert_eq!(client_hash(client), host.game.state_hash());
+            }
+        }
+
+        // The commands really were applied on every peer
+        assert_eq!(host.game.players[2].draw_speed, clients[0].game.as_ref().unwrap().players[2].draw_speed);
+        assert!(host.desync.is_none());
+    }
+
+    #[test]
+    fn test_host_waits_for_every_seat() {
+        let mut host = HostSession::new(setup(2));
+        assert_eq!(host.waiting_for(), vec![1]);
+        assert!(!host.try_step());
+
+        let mut clients = vec![connect(&mut host)];
+        run(&mut host, &mut clients, 3);
+        assert_eq!(host.tick(), 3);
+    }
+
+    #[test]
+    fn test_desync_is_detected() {
+        let mut host = HostSession::new(setup(2));
+        let mut clients = vec![connect(&mut host)];
+        run(&mut host, &mut clients, 2);
+
+        clients[0].game.as_mut().unwrap().balls.clear();
+        for _ in 0..10 {
+            host.pump();
+            clients[0].pump().unwrap();
+            host.try_step();
+        }
+
+        assert_eq!(host.desync, Some(3));
+        assert_eq!(clients[0].desync, Some(3));
+        assert_eq!(host.tick(), 3);
+    }
+
+    #[test]
+    fn test_rejoin_replays_history() {
+        let mut host = HostSession::new(setup(2));
+        let mut clients = vec![connect(&mut host)];
+        host.push_local(Command::Turn(Direction::Down));
+        run(&mut host, &mut clients, 5);
+
+        // Connection drops: the host pauses and holds the seat
+        clients.clear();
+        host.pump();
+        assert_eq!(host.waiting_for(), vec![1]);
+        assert!(!host.try_step());
+
+        let (host_end, client_end) = loopback_pair();
+        host.accept(Box::new(host_end));
+        clients.push(ClientSession::rejoin(Box::new(client_end), 1).unwrap());
+        run(&mut host, &mut clients, 0);
+        assert_eq!(clients[0].tick(), 5);
+        assert_eq!(client_hash(&clients[0]), host.game.state_hash());
+
+        run(&mut host, &mut clients, 5);
+        assert_eq!(client_hash(&clients[0]), host.game.state_hash());
+    }
+
+    #[test]
+    fn test_rejoin_after_a_long_game_catches_up_over_tcp() {
+        let mut host = HostSession::new(setup(2));
+        let mut clients = vec![connect(&mut host)];
+        run(&mut host, &mut clients, 0);
+        clients.clear();
+        host.pump();
+
+        // Play on as if the seat were still there, with a busy step every tick
+        let busy: Vec<(usize, Command)> = [Direction::Up, Direction::Down]
+            .repeat(32)
+            .into_iter()
+            .map(|direction| (1, Command::Turn(direction)))
+            .collect();
+        for _ in 0..30_000 {
+            apply_step(&mut host.game, &busy);
+            host.history.push(busy.clone());
+            host.tick += 1;
+            host.hashes.push(host.game.state_hash());
+        }
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let client_end = TcpTransport::connect(&listener.local_addr().unwrap().to_string()).unwrap();
+        host.accept(Box::new(TcpTransport::new(listener.accept().unwrap().0).unwrap()));
+        let mut client = ClientSession::rejoin(Box::new(client_end), 1).unwrap();
+        for _ in 0..10_000 {
+            host.pump();
+            client.pump().unwrap();
+            if client.tick() == host.tick() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert_eq!(client.tick(), 30_000);
+        assert_eq!(client_hash(&client), host.game.state_hash());
+        assert!(host.waiting_for().is_empty());
+    }
+
+    #[test]
+    fn test_extra_and_mismatched_clients_are_refused() {
+        let mut host = HostSession::new(setup(2));
+        let mut clients = vec![connect(&mut host)];
+        run(&mut host, &mut clients, 0);
+
+        let mut extra = connect(&mut host);
+        host.pump();
+        assert_eq!(extra.pump().unwrap_err().kind(), io::ErrorKind::ConnectionRefused);
+
+        let (host_end, mut client_end) = loopback_pair();
+        host.accept(Box::new(host_end));
+        client_end.send(&Message::Join { version: PROTOCOL_VERSION + 1 }).unwrap();
+        let mut old = ClientSession::connecting(Box::new(client_end));
+        host.pump();
+        assert!(old.pump().is_err());
+    }
+}
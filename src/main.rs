@@ -1,53 +1,379 @@
 use crossterm::terminal;
 use std::io;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
-use axion::{CliRenderer, Game, GameState, Input, Renderer};
+use axion::capture::capture_rule_by_name;
+use axion::lockstep::{ClientSession, HostSession};
+use axion::protocol::{Command, GameSetup, TcpTransport, MAX_BOARD_SIDE, MIN_BOARD_SIZE};
+use axion::spectate::{Publisher, Spectator};
+use axion::key_bindings::PRESETS;
+use axion::{BoardFit, CaptureRule, CliRenderer, Density, Game, GameLoop, GameState, Input, KeyBindings, MovementMode, PauseMenu, Renderer, Steering, SystemClock, TargetMode, Theme, XonixRule, MAX_PLAYERS};
 
-// Game logic update rate (controls gameplay speed)
-const GAME_UPDATE_RATE: Duration = Duration::from_millis(100); // 10 updates/sec
 
-fn main() -> io::Result<()> {
-    // Get terminal size and calculate game dimensions
-    let (term_width, term_height) = terminal::size()?;
+const DEFAULT_PORT: u16 = 7345;
+const DEFAULT_SPECTATOR_PORT: u16 = 7347;
+
+// How long a client keeps trying to get back into a game after losing the host
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+const RECONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+const USAGE: &str = "Usage: axion-cli [host [--port N]] [--capture-rule xonix|qix|smaller] [--eight-way] \
+                     [--players 1-4] [--competitive] [--spectators [PORT]] [--fit letterbox|scale] [--no-raw] [--mouse]\n                 \
+                     [--theme default|colorblind|ascii|unicode|mono] [--density normal|half-block|braille]\n                 \
+                     [--keys default|wasd|vim|FILE]\n       axion-cli join <host:port> [--keys ...]\n       \
+                     axion-cli watch [host:port] [--keys ...]";
+
+enum Mode {
+    Local,
+    Host { port: u16 },
+    Join { addr: String },
+    Watch { addr: String },
+}
+
+/// Command-line options
+struct Options {
+    mode: Mode,
+    capture_rule: Box<dyn CaptureRule>,
+    movement: MovementMode,
+    players: usize,
+    target_mode: TargetMode,
+    /// Local port to publish the game on for `axion-cli watch`
+    spectator_port: Option<u16>,
+    /// How the board is drawn once the terminal is resized
+    board_fit: BoardFit,
+    /// Put the terminal in raw mode; off where there's no terminal (CI logs)
+    raw_mode: bool,
+    /// Colors and characters to draw with; detected from the terminal unless given
+    theme: Theme,
+    /// Board cells per character; denser boards are bigger
+    density: Density,
+    /// Click to steer and to press buttons
+    mouse: bool,
+    key_bindings: KeyBindings,
+    /// Where keys rebound in the pause menu are saved
+    key_bindings_path: Option<PathBuf>,
+}
+
+fn parse_args() -> io::Result<Options> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, format!("{}\n{}", msg, USAGE));
+
+    let mut options = Options {
+        mode: Mode::Local,
+        capture_rule: Box::new(XonixRule),
+        movement: MovementMode::FourWay,
+        players: 1,
+        target_mode: TargetMode::Shared,
+        spectator_port: None,
+        board_fit: BoardFit::Letterbox,
+        raw_mode: true,
+        theme: Theme::detect(),
+        density: Density::Normal,
+        mouse: false,
+        key_bindings: KeyBindings::default(),
+        key_bindings_path: key_bindings_path(),
+    };
+    let mut keys_given = false;
+
+    let mut args = std::env::args().skip(1).peekable();
+    match args.peek().map(String::as_str) {
+        Some("host") => {
+            args.next();
+            options.mode = Mode::Host { port: DEFAULT_PORT };
+            options.players = 2;
+        }
+        Some("join") => {
+            args.next();
+            let addr = args
+                .next()
+                .ok_or_else(|| invalid("join needs a host address".to_string()))?;
+            // The host decides everything else
+            if let Some(arg) = args.peek().filter(|arg| *arg != "--keys") {
+                return Err(invalid(format!("Unexpected argument after join: {}", arg)));
+            }
+            options.mode = Mode::Join { addr };
+        }
+        Some("watch") => {
+            args.next();
+            let addr = args
+                .next()
+                .unwrap_or_else(|| format!("127.0.0.1:{}", DEFAULT_SPECTATOR_PORT));
+            if let Some(arg) = args.peek().filter(|arg| *arg != "--keys") {
+                return Err(invalid(format!("Unexpected argument after watch: {}", arg)));
+            }
+            options.mode = Mode::Watch { addr };
+        }
+        _ => {}
+    }
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--capture-rule" => {
+                let name = args
+                    .next()
+                    .ok_or_else(|| invalid("--capture-rule needs a value".to_string()))?;
+                options.capture_rule = capture_rule_by_name(&name)
+                    .ok_or_else(|| invalid(format!("Unknown capture rule: {}", name)))?;
+            }
+            "--eight-way" => options.movement = MovementMode::EightWay,
+            "--players" => {
+                let count = args
+                    .next()
+                    .ok_or_else(|| invalid("--players needs a value".to_string()))?;
+                options.players = count
+                    .parse()
+                    .ok()
+                    .filter(|count| (1..=MAX_PLAYERS).contains(count))
+                    .ok_or_else(|| invalid(format!("Player count must be 1-{}: {}", MAX_PLAYERS, count)))?;
+            }
+            "--competitive" => options.target_mode = TargetMode::Competitive,
+            "--spectators" => {
+                // The port is optional
+                let port = match args.peek().filter(|arg| !arg.starts_with("--")) {
+                    Some(port) => {
+                        let port = port
+                            .parse()
+                            .map_err(|_| invalid(format!("Invalid spectator port: {}", port)))?;
+                        args.next();
+                        port
+                    }
+                    None => DEFAULT_SPECTATOR_PORT,
+                };
+                options.spectator_port = Some(port);
+            }
+            "--fit" => {
+                options.board_fit = match args.next().as_deref() {
+                    Some("letterbox") => BoardFit::Letterbox,
+                    Some("scale") => BoardFit::Scale,
+                    Some(fit) => return Err(invalid(format!("Unknown board fit: {}", fit))),
+                    None => return Err(invalid("--fit needs a value".to_string())),
+                };
+            }
+            "--no-raw" => options.raw_mode = false,
+            "--mouse" => options.mouse = true,
+            "--theme" => {
+                let name = args
+                    .next()
+                    .ok_or_else(|| invalid("--theme needs a value".to_string()))?;
+                options.theme = Theme::by_name(&name).ok_or_else(|| invalid(format!("Unknown theme: {}", name)))?;
+            }
+            "--density" => {
+                options.density = match args.next().as_deref() {
+                    Some("normal") => Density::Normal,
+                    Some("half-block") => Density::HalfBlock,
+                    Some("braille") => Density::Braille,
+                    Some(density) => return Err(invalid(format!("Unknown density: {}", density))),
+                    None => return Err(invalid("--density needs a value".to_string())),
+                };
+            }
+            // A preset, or a file to load and save rebound keys in
+            "--keys" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| invalid("--keys needs a preset or a file".to_string()))?;
+                options.key_bindings = match KeyBindings::preset(&value) {
+                    Some(bindings) => bindings,
+                    None => {
+                        let path = PathBuf::from(&value);
+                        if !path.is_file() {
+                            return Err(invalid(format!(
+                                "--keys takes {} or a file: {}",
+                                PRESETS.join(", "),
+                                value
+                            )));
+                        }
+                        let bindings = load_key_bindings(&path)?;
+                        options.key_bindings_path = Some(path);
+                        bindings
+                    }
+                };
+                keys_given = true;
+            }
+            _ if matches!(options.mode, Mode::Join { .. } | Mode::Watch { .. }) => {
+                return Err(invalid(format!("Unexpected argument: {}", arg)));
+            }
+            "--port" if matches!(options.mode, Mode::Host { .. }) => {
+                let port = args
+                    .next()
+                    .and_then(|port| port.parse().ok())
+                    .ok_or_else(|| invalid("--port needs a port number".to_string()))?;
+                options.mode = Mode::Host { port };
+            }
+            _ => return Err(invalid(format!("Unknown argument: {}", arg))),
+        }
+    }
+
+    if matches!(options.mode, Mode::Host { .. }) && options.players < 2 {
+        return Err(invalid("A hosted game needs 2-4 players".to_string()));
+    }
+    if !keys_given {
+        if let Some(path) = options.key_bindings_path.as_deref().filter(|path| path.exists()) {
+            options.key_bindings = load_key_bindings(path)?;
+        }
+    }
+
+    Ok(options)
+}
+
+/// `$XDG_CONFIG_HOME/axion/keys.conf`, falling back to `~/.config` and
+/// then `%APPDATA%`
+fn key_bindings_path() -> Option<PathBuf> {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(config.join("axion").join("keys.conf"))
+}
+
+fn load_key_bindings(path: &Path) -> io::Result<KeyBindings> {
+    let text = std::fs::read_to_string(path)?;
+    KeyBindings::parse(&text)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}:\n{}", path.display(), e)))
+}
+
+fn save_key_bindings(path: &Path, bindings: &KeyBindings) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let text = format!("# axion key bindings, rewritten when keys are changed in the pause menu\n{}", bindings.to_config());
+    std::fs::write(path, text)
+}
+
+/// Board size that fits the terminal for `players` players, drawn with
+/// `theme` at `density`
+fn board_size(players: usize, theme: Theme, density: Density) -> (i32, i32) {
+    // Get terminal size and calculate game dimensions, assuming 80x24
+    // when there's no terminal to ask
+    let (term_width, term_height) = terminal::size().unwrap_or((80, 24));
 
     // Account for:
-    // - Each cell is 2 chars wide, so width = term_width / 2
-    // - Reserve 4 lines at bottom for info display
-    // - Minimum size of 20x10 for playability
-    let game_width = ((term_width / 2) as i32).max(20);
-    let game_height = ((term_height - 4) as i32).max(10);
+    // - Each character is char_width columns wide and shows cells_per_char cells
+    // - Reserve lines at bottom for info display
+    // - Minimum size of 20x10 for playability, and no bigger than a peer accepts
+    let info_lines = CliRenderer::info_height(players) as i32;
+    let (across, down) = density.cells_per_char();
+    let game_width = ((term_width / density.char_width(theme)) as i32 * across).clamp(MIN_BOARD_SIZE.0, MAX_BOARD_SIDE);
+    let game_height = ((term_height as i32 - info_lines) * down).clamp(MIN_BOARD_SIZE.1, MAX_BOARD_SIDE);
+    (game_width, game_height)
+}
+
+/// Publisher for `axion-cli watch`, if spectators were asked for
+fn spectator_publisher(port: Option<u16>) -> io::Result<Option<Publisher>> {
+    // Local only: spectating isn't meant to cross machines
+    port.map(|port| Publisher::bind(&format!("127.0.0.1:{}", port))).transpose()
+}
+
+fn main() -> io::Result<()> {
+    let options = parse_args()?;
+
+    match options.mode {
+        Mode::Local => run_local(options),
+        Mode::Host { port } => run_host(options, port),
+        Mode::Join { ref addr } => run_join(addr, options.theme, options.key_bindings),
+        Mode::Watch { ref addr } => run_watch(addr, options.theme, options.key_bindings),
+    }
+}
 
-    let mut game = Game::new(game_width, game_height);
+fn run_local(options: Options) -> io::Result<()> {
+    let (game_width, game_height) = board_size(options.players, options.theme, options.density);
+
+    let mut game = Game::with_players(game_width, game_height, options.players);
+    game.set_target_mode(options.target_mode);
+    game.set_capture_rule(options.capture_rule);
+    game.set_movement_mode(options.movement);
+    let mut publisher = spectator_publisher(options.spectator_port)?;
     let mut renderer = CliRenderer::new();
+    renderer.set_board_fit(options.board_fit);
+    renderer.set_raw_mode(options.raw_mode);
+    renderer.set_theme(options.theme);
+    renderer.set_density(options.density);
+    renderer.set_mouse(options.mouse);
+    renderer.set_key_bindings(options.key_bindings.clone());
 
     renderer.init()?;
 
-    let mut last_game_update = Instant::now();
+    let mut game_loop = GameLoop::new(SystemClock::default());
+    let mut menu = PauseMenu::new();
+    let mut steering = Steering::new();
 
     loop {
-        // Poll for input
+        // Poll for input; the pause menu gets all of it while paused
         if let Some(input) = renderer.poll_input()? {
-            match input {
-                Input::Direction(direction) => {
-                    game.set_direction(direction);
-                }
-                Input::Quit => {
+            if game.state == GameState::Paused {
+                let before = renderer.key_bindings().clone();
+                if menu.handle(input, &mut game, renderer.key_bindings_mut()) {
                     break;
                 }
-                Input::Restart => {
-                    game.reset();
+                if let Some(path) = options.key_bindings_path.as_deref() {
+                    // Not being able to save only costs the change next time; keep playing
+                    if *renderer.key_bindings() != before {
+                        let saved = save_key_bindings(path, renderer.key_bindings());
+                        renderer.set_status(saved.err().map(|e| format!("Couldn't save key bindings: {}", e)));
+                    }
                 }
-                Input::NextLevel if game.state == GameState::Won => {
-                    game.next_level();
+            } else {
+                match input {
+                    // Steering keys take over from a click
+                    Input::Direction(direction) => {
+                        steering.set_target(None);
+                        game.set_direction(direction);
+                    }
+                    Input::MoveToward(target) => {
+                        steering.set_target(Some(target));
+                    }
+                    Input::PlayerDirection(idx, direction) => {
+                        // Keys for players who aren't in this game are ignored
+                        game.set_player_direction(idx, direction);
+                    }
+                    Input::Quit => {
+                        break;
+                    }
+                    Input::Restart => {
+                        steering.set_target(None);
+                        game.reset();
+                    }
+                    Input::ToggleSlowDraw => {
+                        game.toggle_draw_speed();
+                    }
+                    Input::Hold => {
+                        game.toggle_hold();
+                    }
+                    Input::NextLevel if game.state == GameState::Won => {
+                        steering.set_target(None);
+                        game.next_level();
+                    }
+                    // Losing focus only pauses; it never resumes
+                    Input::Pause | Input::FocusLost => {
+                        game.pause();
+                        menu = PauseMenu::new();
+                    }
+                    _ => {}
                 }
-                _ => {}
             }
         }
+        // Keep the game from running on unseen while the terminal is too small,
+        // whether it just shrank or the hidden menu tried to resume or restart
+        if game.state == GameState::Playing && !renderer.fits(&game) {
+            game.pause();
+            menu = PauseMenu::new();
+        }
+        renderer.set_menu((game.state == GameState::Paused).then_some(menu));
+
+        // Time spent paused is never caught up after resuming
+        if game.state == GameState::Paused {
+            game_loop.pause();
+        } else if game_loop.is_paused() {
+            game_loop.resume();
+        }
 
-        // Update game logic at fixed rate
-        if last_game_update.elapsed() >= GAME_UPDATE_RATE {
+        // Update game logic at fixed rate, catching up after a slow frame
+        while game_loop.next_tick() {
+            steering.steer(&mut game);
             game.update();
-            last_game_update = Instant::now();
+            if let Some(publisher) = &mut publisher {
+                publisher.publish(&game);
+            }
         }
 
         // Let renderer decide when to actually render
@@ -58,3 +384,190 @@ fn main() -> io::Result<()> {
     renderer.cleanup()?;
     Ok(())
 }
+
+/// Host a lockstep game on `port`; this terminal plays as P1
+fn run_host(options: Options, port: u16) -> io::Result<()> {
+    let (game_width, game_height) = board_size(options.players, options.theme, options.density);
+
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    listener.set_nonblocking(true)?;
+
+    let mut session = HostSession::new(GameSetup {
+        width: game_width,
+        height: game_height,
+        player_count: options.players,
+        seed: rand::random(),
+        capture_rule: options.capture_rule.name().to_string(),
+        movement: options.movement,
+        target_mode: options.target_mode,
+    });
+    let mut publisher = spectator_publisher(options.spectator_port)?;
+    let mut renderer = CliRenderer::new();
+    renderer.set_board_fit(options.board_fit);
+    renderer.set_raw_mode(options.raw_mode);
+    renderer.set_theme(options.theme);
+    renderer.set_density(options.density);
+    renderer.set_mouse(options.mouse);
+    renderer.set_key_bindings(options.key_bindings.clone());
+
+    renderer.init()?;
+
+    let mut game_loop = GameLoop::new(SystemClock::default());
+    // Why the last connection attempt failed, shown while waiting for players
+    let mut dropped: Option<String> = None;
+
+    loop {
+        // A failed connection only loses that player; the others play on
+        match listener.accept() {
+            Ok((stream, peer)) => match TcpTransport::new(stream) {
+                Ok(transport) => session.accept(Box::new(transport)),
+                Err(e) => dropped = Some(format!("dropped {}: {}", peer, e)),
+            },
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            Err(e) => dropped = Some(format!("accept failed: {}", e)),
+        }
+
+        // Poll for input
+        if let Some(input) = renderer.poll_input()? {
+            if input == Input::Quit {
+                break;
+            }
+            if let Some(command) = Command::from_input(input) {
+                session.push_local(command);
+            }
+        }
+
+        session.pump();
+
+        // Step at the fixed rate; a tick owed while waiting on a peer runs once their input is in
+        while game_loop.tick_due() && session.try_step() {
+            game_loop.take_tick();
+            if let Some(publisher) = &mut publisher {
+                publisher.publish(&session.game);
+            }
+        }
+
+        let waiting = session.waiting_for();
+        renderer.set_status(if let Some(tick) = session.desync {
+            Some(format!("DESYNC at tick {}! Press {} to quit", tick, renderer.key_bindings().describe(Input::Quit)))
+        } else if !waiting.is_empty() {
+            let players: Vec<String> = waiting.iter().map(|idx| format!("P{}", idx + 1)).collect();
+            let note = dropped.as_ref().map(|reason| format!(" ({})", reason)).unwrap_or_default();
+            Some(format!("Port {}: waiting for {}{}", port, players.join(", "), note))
+        } else {
+            None
+        });
+
+        renderer.render(&session.game)?;
+    }
+
+    renderer.cleanup()?;
+    Ok(())
+}
+
+/// Join a hosted game, reconnecting to the same seat if the connection drops
+fn run_join(addr: &str, theme: Theme, key_bindings: KeyBindings) -> io::Result<()> {
+    let mut session = ClientSession::join(Box::new(TcpTransport::connect(addr)?))?;
+    let mut seat: Option<usize> = None;
+    // Last game we were in, shown while reconnecting
+    let mut previous: Option<ClientSession> = None;
+    let mut lost_at: Option<Instant> = None;
+    let mut last_attempt = Instant::now();
+    let mut renderer = CliRenderer::new();
+    // The host picked the board size for its own terminal
+    renderer.set_board_fit(BoardFit::Scale);
+    renderer.set_theme(theme);
+    renderer.set_key_bindings(key_bindings);
+
+    renderer.init()?;
+
+    loop {
+        // Poll for input
+        if let Some(input) = renderer.poll_input()? {
+            if input == Input::Quit {
+                break;
+            }
+            if let Some(command) = Command::from_input(input) {
+                session.push_local(command);
+            }
+        }
+
+        if let Err(e) = session.pump() {
+            // Before we have a seat there's nothing to reconnect to
+            if seat.is_none() {
+                return Err(e);
+            }
+            lost_at.get_or_insert_with(Instant::now);
+
+            if last_attempt.elapsed() >= RECONNECT_INTERVAL {
+                last_attempt = Instant::now();
+                if let (Ok(transport), Some(player)) = (TcpTransport::connect(addr), seat) {
+                    let lost = std::mem::replace(&mut session, ClientSession::rejoin(Box::new(transport), player)?);
+                    if lost.game.is_some() {
+                        previous = Some(lost);
+                    }
+                }
+            }
+        }
+
+        // Once welcomed, the (re)built game replaces the one shown while reconnecting
+        if session.game.is_some() {
+            seat = session.player;
+            previous = None;
+            lost_at = None;
+        } else if lost_at.is_some_and(|since| since.elapsed() > RECONNECT_TIMEOUT) {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "Lost connection to host"));
+        }
+
+        renderer.set_status(if let Some(tick) = session.desync {
+            Some(format!("DESYNC at tick {}! Press {} to quit", tick, renderer.key_bindings().describe(Input::Quit)))
+        } else if lost_at.is_some() {
+            Some("Connection lost, reconnecting...".to_string())
+        } else if session.game.is_none() {
+            Some(format!("Joining {}...", addr))
+        } else {
+            None
+        });
+
+        match session.game.as_ref().or(previous.as_ref().and_then(|p| p.game.as_ref())) {
+            Some(game) => renderer.render(game)?,
+            None => std::thread::sleep(Duration::from_millis(10)),
+        }
+    }
+
+    renderer.cleanup()?;
+    Ok(())
+}
+
+/// Show a game published with `--spectators`; only quitting does anything
+fn run_watch(addr: &str, theme: Theme, key_bindings: KeyBindings) -> io::Result<()> {
+    let mut spectator = Spectator::connect(addr)?;
+    let mut renderer = CliRenderer::new();
+    // The player picked the board size for their own terminal
+    renderer.set_board_fit(BoardFit::Scale);
+    renderer.set_theme(theme);
+    renderer.set_key_bindings(key_bindings);
+
+    renderer.init()?;
+    let quit = renderer.key_bindings().describe(Input::Quit);
+    renderer.set_status(Some(format!("WATCHING (read-only) - {} to quit", quit)));
+
+    loop {
+        if renderer.poll_input()? == Some(Input::Quit) {
+            break;
+        }
+
+        if let Err(e) = spectator.pump() {
+            renderer.cleanup()?;
+            return Err(e);
+        }
+
+        match &spectator.game {
+            Some(game) => renderer.render(game)?,
+            None => std::thread::sleep(Duration::from_millis(10)),
+        }
+    }
+
+    renderer.cleanup()?;
+    Ok(())
+}
@@ -0,0 +1,359 @@
+//! The menu shown over a paused game.
+//!
+//! Front ends send every input to the menu while `Game::state` is `Paused`
+//! and draw `items` with the `selected` one highlighted. Everything but
+//! quitting is applied to the game directly, and rebinding to the
+//! `KeyBindings` passed in; front ends save those when they change.
+
+use crate::entity::MovementMode;
+use crate::game::Game;
+use crate::key_bindings::{action_name, Conflict, Key, KeyBindings, ACTIONS, PRESETS};
+use crate::renderer::Input;
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuItem {
+    Resume,
+    Restart,
+    Settings,
+    Quit,
+    DrawSpeed,
+    Movement,
+    Controls,
+    /// The action at this index of `key_bindings::ACTIONS`
+    Binding(usize),
+    /// Switch to the next key bindings preset
+    Preset,
+    Back,
+}
+
+const MAIN_ITEMS: [MenuItem; 4] = [MenuItem::Resume, MenuItem::Restart, MenuItem::Settings, MenuItem::Quit];
+const SETTINGS_ITEMS: [MenuItem; 4] = [MenuItem::DrawSpeed, MenuItem::Movement, MenuItem::Controls, MenuItem::Back];
+const CONTROLS_ITEMS: [MenuItem; ACTIONS.len() + 2] = {
+    let mut items = [MenuItem::Back; ACTIONS.len() + 2];
+    items[0] = MenuItem::Preset;
+    let mut idx = 0;
+    while idx < ACTIONS.len() {
+        items[idx + 1] = MenuItem::Binding(idx);
+        idx += 1;
+    }
+    items
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Page {
+    #[default]
+    Main,
+    Settings,
+    Controls,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PauseMenu {
+    page: Page,
+    selected: usize,
+    /// The highlighted binding takes the next key pressed
+    waiting: bool,
+    /// The last key pressed to bind, which did something else already
+    conflict: Option<Conflict>,
+}
+
+impl PauseMenu {
+    /// The main page with Resume highlighted
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self.page {
+            Page::Main => "PAUSED",
+            Page::Settings => "SETTINGS",
+            Page::Controls => "CONTROLS",
+        }
+    }
+
+    pub fn items(&self) -> &'static [MenuItem] {
+        match self.page {
+            Page::Main => &MAIN_ITEMS,
+            Page::Settings => &SETTINGS_ITEMS,
+            Page::Controls => &CONTROLS_ITEMS,
+        }
+    }
+
+    /// The part of `items` to show when only `rows` fit, scrolled to keep
+    /// the highlight in view
+    pub fn visible_items(&self, rows: usize) -> Range<usize> {
+        let count = self.items().len();
+        let rows = rows.clamp(1, count);
+        let first = self.selected.saturating_sub(rows / 2).min(count - rows);
+        first..first + rows
+    }
+
+    /// While true, front ends send key presses as `Input::Key` rather than
+    /// through the bindings, so any key can be picked
+    pub fn waiting_for_key(&self) -> bool {
+        self.waiting
+    }
+
+    /// A line to show under the items, if anything needs saying
+    pub fn notice(&self) -> Option<String> {
+        if self.waiting {
+            let (name, _) = ACTIONS[self.binding()?];
+            return Some(format!("Press a key for {} (Esc to cancel)", name));
+        }
+        self.conflict.map(|conflict| {
+            format!(
+                "{} already does {}",
+                conflict.key,
+                action_name(conflict.bound_to).unwrap_or("something else")
+            )
+        })
+    }
+
+    /// The index into `ACTIONS` of the highlighted binding, if it is one
+    fn binding(&self) -> Option<usize> {
+        match self.items().get(self.selected) {
+            Some(&MenuItem::Binding(idx)) => Some(idx),
+            _ => None,
+        }
+    }
+
+    /// Index into `items` of the highlighted item
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// What to show for `item`; settings include their current value
+    pub fn label(item: MenuItem, game: &Game, bindings: &KeyBindings) -> String {
+        match item {
+            MenuItem::Resume => "Resume".to_string(),
+            MenuItem::Restart => "Restart".to_string(),
+            MenuItem::Settings => "Settings".to_string(),
+            MenuItem::Quit => "Quit".to_string(),
+            MenuItem::DrawSpeed => format!("Draw speed: {}", game.players[0].draw_speed.label()),
+            MenuItem::Movement => format!(
+                "Movement: {}",
+                match game.movement {
+                    MovementMode::FourWay => "4-way",
+                    MovementMode::EightWay => "8-way",
+                }
+            ),
+            MenuItem::Controls => "Controls".to_string(),
+            MenuItem::Binding(idx) => {
+                let (name, input) = ACTIONS[idx];
+                let keys: Vec<String> = bindings.keys_for(input).iter().map(Key::to_string).collect();
+                format!("{:<11}{}", name, keys.join(" "))
+            }
+            MenuItem::Preset => format!(
+                "Preset: {}",
+                PRESETS
+                    .into_iter()
+                    .find(|&name| KeyBindings::preset(name).as_ref() == Some(bindings))
+                    .unwrap_or("custom")
+            ),
+            MenuItem::Back => "Back".to_string(),
+        }
+    }
+
+    /// Handle `input` for a paused `game`: up and down move the highlight,
+    /// Enter or a tap picks the item, and pause resumes. Picking a binding
+    /// makes the next `Input::Key` its only key, unless that key is taken.
+    /// Returns true when the player chose to quit.
+    pub fn handle(&mut self, input: Input, game: &mut Game, bindings: &mut KeyBindings) -> bool {
+        self.conflict = None;
+        if self.waiting {
+            if let Input::Key(key) = input {
+                self.waiting = false;
+                if let (Some(idx), false) = (self.binding(), key == Key::Esc) {
+                    self.conflict = bindings.set_keys(ACTIONS[idx].1, &[key]).err();
+                }
+            }
+            return false;
+        }
+        let count = self.items().len();
+        match input {
+            Input::Direction(direction) if direction.delta().1 < 0 => {
+                self.selected = (self.selected + count - 1) % count;
+            }
+            Input::Direction(direction) if direction.delta().1 > 0 => {
+                self.selected = (self.selected + 1) % count;
+            }
+            Input::Confirm | Input::Tap => return self.activate(game, bindings),
+            Input::Pause => self.activate_item(MenuItem::Resume, game),
+            Input::Restart => self.activate_item(MenuItem::Restart, game),
+            Input::Quit => return true,
+            _ => {}
+        }
+        false
+    }
+
+    fn activate(&mut self, game: &mut Game, bindings: &mut KeyBindings) -> bool {
+        match self.items()[self.selected] {
+            MenuItem::Quit => return true,
+            MenuItem::Binding(_) => self.waiting = true,
+            MenuItem::Preset => {
+                let current = PRESETS
+                    .iter()
+                    .position(|&name| KeyBindings::preset(name).as_ref() == Some(&*bindings));
+                let next = current.map_or(0, |idx| (idx + 1) % PRESETS.len());
+                *bindings = KeyBindings::preset(PRESETS[next]).unwrap_or_default();
+            }
+            item => self.activate_item(item, game),
+        }
+        false
+    }
+
+    fn activate_item(&mut self, item: MenuItem, game: &mut Game) {
+        match item {
+            MenuItem::Resume => {
+                game.resume();
+                *self = Self::new();
+            }
+            MenuItem::Restart => {
+                game.reset();
+                *self = Self::new();
+            }
+            MenuItem::Settings => self.open(Page::Settings, 0),
+            MenuItem::Controls => self.open(Page::Controls, 0),
+            MenuItem::DrawSpeed => game.toggle_draw_speed(),
+            MenuItem::Movement => game.set_movement_mode(match game.movement {
+                MovementMode::FourWay => MovementMode::EightWay,
+                MovementMode::EightWay => MovementMode::FourWay,
+            }),
+            MenuItem::Back => match self.page {
+                Page::Controls => self.open(Page::Settings, SETTINGS_ITEMS.iter().position(|&item| item == MenuItem::Controls).unwrap_or(0)),
+                _ => self.open(Page::Main, MAIN_ITEMS.iter().position(|&item| item == MenuItem::Settings).unwrap_or(0)),
+            },
+            MenuItem::Binding(_) | MenuItem::Preset | MenuItem::Quit => {}
+        }
+    }
+
+    fn open(&mut self, page: Page, selected: usize) {
+        self.page = page;
+        self.selected = selected;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::{Direction, DrawSpeed};
+    use crate::game::GameState;
+
+    fn paused_game() -> Game {
+        let mut game = Game::with_seed(30, 20, 1, 6);
+        game.pause();
+        game
+    }
+
+    #[test]
+    fn test_selection_wraps() {
+        let mut game = paused_game();
+        let mut menu = PauseMenu::new();
+        let mut bindings = KeyBindings::default();
+        menu.handle(Input::Direction(Direction::Up), &mut game, &mut bindings);
+        assert_eq!(menu.items()[menu.selected()], MenuItem::Quit);
+        menu.handle(Input::Direction(Direction::Down), &mut game, &mut bindings);
+        assert_eq!(menu.items()[menu.selected()], MenuItem::Resume);
+        // Sideways does nothing
+        menu.handle(Input::Direction(Direction::Left), &mut game, &mut bindings);
+        assert_eq!(menu.selected(), 0);
+    }
+
+    #[test]
+    fn test_resume_and_quit() {
+        let mut game = paused_game();
+        let mut menu = PauseMenu::new();
+        let mut bindings = KeyBindings::default();
+        assert!(!menu.handle(Input::Confirm, &mut game, &mut bindings));
+        assert_eq!(game.state, GameState::Playing);
+
+        game.pause();
+        menu.handle(Input::Direction(Direction::Up), &mut game, &mut bindings);
+        assert!(menu.handle(Input::Confirm, &mut game, &mut bindings));
+        assert!(menu.handle(Input::Quit, &mut game, &mut bindings));
+    }
+
+    #[test]
+    fn test_settings_change_the_game() {
+        let mut game = paused_game();
+        let mut menu = PauseMenu::new();
+        let mut bindings = KeyBindings::default();
+        menu.handle(Input::Direction(Direction::Down), &mut game, &mut bindings);
+        menu.handle(Input::Direction(Direction::Down), &mut game, &mut bindings);
+        menu.handle(Input::Confirm, &mut game, &mut bindings);
+        assert_eq!(menu.title(), "SETTINGS");
+
+        menu.handle(Input::Confirm, &mut game, &mut bindings);
+        assert_eq!(game.players[0].draw_speed, DrawSpeed::Slow);
+        menu.handle(Input::Direction(Direction::Down), &mut game, &mut bindings);
+        menu.handle(Input::Tap, &mut game, &mut bindings);
+        assert_eq!(game.movement, MovementMode::EightWay);
+        assert_eq!(PauseMenu::label(MenuItem::Movement, &game, &bindings), "Movement: 8-way");
+
+        // Back lands on Settings, and the game is still paused
+        menu.handle(Input::Direction(Direction::Down), &mut game, &mut bindings);
+        menu.handle(Input::Direction(Direction::Down), &mut game, &mut bindings);
+        menu.handle(Input::Confirm, &mut game, &mut bindings);
+        assert_eq!(menu.items()[menu.selected()], MenuItem::Settings);
+        assert_eq!(game.state, GameState::Paused);
+    }
+
+    #[test]
+    fn test_rebinding_a_key() {
+        let mut game = paused_game();
+        let mut menu = PauseMenu::new();
+        let mut bindings = KeyBindings::default();
+        // Settings, then Controls, then the first binding (up)
+        for input in [Input::Direction(Direction::Down), Input::Direction(Direction::Down), Input::Confirm] {
+            menu.handle(input, &mut game, &mut bindings);
+        }
+        for input in [Input::Direction(Direction::Down), Input::Direction(Direction::Down), Input::Confirm] {
+            menu.handle(input, &mut game, &mut bindings);
+        }
+        assert_eq!(menu.title(), "CONTROLS");
+        menu.handle(Input::Direction(Direction::Down), &mut game, &mut bindings);
+        menu.handle(Input::Confirm, &mut game, &mut bindings);
+        assert!(menu.waiting_for_key());
+        assert_eq!(menu.notice().unwrap(), "Press a key for up (Esc to cancel)");
+
+        // A key that's taken is refused
+        menu.handle(Input::Key(Key::Char('q')), &mut game, &mut bindings);
+        assert!(!menu.waiting_for_key());
+        assert_eq!(menu.notice().unwrap(), "q already does quit");
+        assert_eq!(bindings.keys_for(Input::Direction(Direction::Up)), vec![Key::Up]);
+
+        menu.handle(Input::Confirm, &mut game, &mut bindings);
+        menu.handle(Input::Key(Key::Char('i')), &mut game, &mut bindings);
+        assert_eq!(bindings.input_for(Key::Char('i')), Some(Input::Direction(Direction::Up)));
+        assert_eq!(bindings.input_for(Key::Up), None);
+        assert_eq!(menu.notice(), None);
+        assert_eq!(PauseMenu::label(MenuItem::Preset, &game, &bindings), "Preset: custom");
+
+        // Esc cancels, and the preset item starts over from the default
+        menu.handle(Input::Confirm, &mut game, &mut bindings);
+        menu.handle(Input::Key(Key::Esc), &mut game, &mut bindings);
+        assert_eq!(bindings.input_for(Key::Char('i')), Some(Input::Direction(Direction::Up)));
+        menu.handle(Input::Direction(Direction::Up), &mut game, &mut bindings);
+        menu.handle(Input::Confirm, &mut game, &mut bindings);
+        assert_eq!(bindings, KeyBindings::default());
+        menu.handle(Input::Confirm, &mut game, &mut bindings);
+        assert_eq!(PauseMenu::label(MenuItem::Preset, &game, &bindings), "Preset: wasd");
+
+        // Back returns to Settings on Controls
+        menu.handle(Input::Direction(Direction::Up), &mut game, &mut bindings);
+        menu.handle(Input::Confirm, &mut game, &mut bindings);
+        assert_eq!(menu.items()[menu.selected()], MenuItem::Controls);
+    }
+
+    #[test]
+    fn test_visible_items_follow_the_highlight() {
+        let mut menu = PauseMenu { page: Page::Controls, ..PauseMenu::new() };
+        assert_eq!(menu.visible_items(5), 0..5);
+        menu.selected = 10;
+        assert_eq!(menu.visible_items(5), 8..13);
+        menu.selected = CONTROLS_ITEMS.len() - 1;
+        assert_eq!(menu.visible_items(5), CONTROLS_ITEMS.len() - 5..CONTROLS_ITEMS.len());
+        assert_eq!(menu.visible_items(100), 0..CONTROLS_ITEMS.len());
+    }
+}
@@ -0,0 +1,1048 @@
+//! Wire protocol for networked games.
+//!
+//! Messages are single lines of space-separated ASCII tokens, so a session can
+//! be followed with `nc` and the same encoding works over TCP or WebSocket
+//! text frames.
+
+use crate::capture::capture_rule_by_name;
+use crate::entity::{Ball, Direction, DrawSpeed, MovementMode, Position};
+use crate::game::{Cell, Game, GameState, TargetMode, MAX_PLAYERS};
+use crate::renderer::Input;
+use crate::snapshot::{BoardDelta, BoardMismatch, BoardSnapshot, CellState, Run};
+use std::fmt;
+use std::io;
+use std::str::SplitWhitespace;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+
+/// Bumped whenever the message format or simulation rules change
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// A single player action, applied at the start of a tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Turn(Direction),
+    ToggleSlowDraw,
+    Hold,
+    Restart,
+    NextLevel,
+}
+
+impl Command {
+    /// The command a local input stands for; quitting and local-only inputs map to none
+    pub fn from_input(input: Input) -> Option<Self> {
+        match input {
+            Input::Direction(direction) => Some(Command::Turn(direction)),
+            Input::ToggleSlowDraw => Some(Command::ToggleSlowDraw),
+            Input::Hold => Some(Command::Hold),
+            Input::Restart => Some(Command::Restart),
+            Input::NextLevel => Some(Command::NextLevel),
+            _ => None,
+        }
+    }
+
+    /// Apply this command on behalf of `player`
+    pub fn apply(self, game: &mut Game, player: usize) {
+        match self {
+            Command::Turn(direction) => game.set_player_direction(player, direction),
+            Command::ToggleSlowDraw => game.toggle_player_draw_speed(player),
+            Command::Hold => game.toggle_player_hold(player),
+            Command::Restart => game.reset(),
+            Command::NextLevel => {
+                if game.state == GameState::Won {
+                    game.next_level();
+                }
+            }
+        }
+    }
+
+    fn token(self) -> &'static str {
+        match self {
+            Command::Turn(direction) => direction_token(direction),
+            Command::ToggleSlowDraw => "S",
+            Command::Hold => "H",
+            Command::Restart => "X",
+            Command::NextLevel => "N",
+        }
+    }
+
+    fn from_token(token: &str) -> Option<Self> {
+        Some(match token {
+            "S" => Command::ToggleSlowDraw,
+            "H" => Command::Hold,
+            "X" => Command::Restart,
+            "N" => Command::NextLevel,
+            _ => Command::Turn(direction_from_token(token)?),
+        })
+    }
+}
+
+fn direction_token(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Up => "U",
+        Direction::Down => "D",
+        Direction::Left => "L",
+        Direction::Right => "R",
+        Direction::UpLeft => "UL",
+        Direction::UpRight => "UR",
+        Direction::DownLeft => "DL",
+        Direction::DownRight => "DR",
+    }
+}
+
+fn direction_from_token(token: &str) -> Option<Direction> {
+    Some(match token {
+        "U" => Direction::Up,
+        "D" => Direction::Down,
+        "L" => Direction::Left,
+        "R" => Direction::Right,
+        "UL" => Direction::UpLeft,
+        "UR" => Direction::UpRight,
+        "DL" => Direction::DownLeft,
+        "DR" => Direction::DownRight,
+        _ => return None,
+    })
+}
+
+/// Smallest board a setup may ask for, as the CLI and server enforce
+pub const MIN_BOARD_SIZE: (i32, i32) = (20, 10);
+
+/// Longest board side a setup may ask for; far beyond any screen, and small
+/// enough that a peer can't make us allocate without bound
+pub const MAX_BOARD_SIDE: i32 = 2000;
+
+/// Everything a peer needs to build the same starting `Game` as the host
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameSetup {
+    pub width: i32,
+    pub height: i32,
+    pub player_count: usize,
+    pub seed: u64,
+    pub capture_rule: String,
+    pub movement: MovementMode,
+    pub target_mode: TargetMode,
+}
+
+impl GameSetup {
+    pub fn build(&self) -> Game {
+        let mut game = Game::with_seed(self.width, self.height, self.player_count, self.seed);
+        if let Some(rule) = capture_rule_by_name(&self.capture_rule) {
+            game.set_capture_rule(rule);
+        }
+        game.set_movement_mode(self.movement);
+        game.set_target_mode(self.target_mode);
+        game
+    }
+
+    /// The setup of a running game, for peers that will only mirror it
+    /// (the seed is irrelevant to them and left at 0)
+    pub fn describe(game: &Game) -> Self {
+        Self {
+            width: game.width,
+            height: game.height,
+            player_count: game.players.len(),
+            seed: 0,
+            capture_rule: game.capture_rule.name().to_string(),
+            movement: game.movement,
+            target_mode: game.target_mode,
+        }
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let movement = match self.movement {
+            MovementMode::FourWay => "4",
+            MovementMode::EightWay => "8",
+        };
+        let target_mode = match self.target_mode {
+            TargetMode::Shared => "shared",
+            TargetMode::Competitive => "competitive",
+        };
+        write!(
+            f,
+            "{} {} {} {} {} {} {}",
+            self.player_count, self.seed, self.width, self.height, self.capture_rule, movement, target_mode
+        )
+    }
+
+    /// Whether a game can be built from this setup: a board between
+    /// `MIN_BOARD_SIZE` and `MAX_BOARD_SIDE` and 1-`MAX_PLAYERS` players
+    pub fn is_valid(&self) -> bool {
+        (MIN_BOARD_SIZE.0..=MAX_BOARD_SIDE).contains(&self.width)
+            && (MIN_BOARD_SIZE.1..=MAX_BOARD_SIDE).contains(&self.height)
+            && (1..=MAX_PLAYERS).contains(&self.player_count)
+    }
+
+    /// The setup in `tokens`, if it's well-formed and valid; it comes from a
+    /// peer, so nothing is built from it unchecked
+    fn parse(tokens: &mut SplitWhitespace) -> Option<Self> {
+        let setup = Self {
+            player_count: tokens.next()?.parse().ok()?,
+            seed: tokens.next()?.parse().ok()?,
+            width: tokens.next()?.parse().ok()?,
+            height: tokens.next()?.parse().ok()?,
+            capture_rule: tokens.next()?.to_string(),
+            movement: match tokens.next()? {
+                "4" => MovementMode::FourWay,
+                "8" => MovementMode::EightWay,
+                _ => return None,
+            },
+            target_mode: match tokens.next()? {
+                "shared" => TargetMode::Shared,
+                "competitive" => TargetMode::Competitive,
+                _ => return None,
+            },
+        };
+        setup.is_valid().then_some(setup)
+    }
+}
+
+/// What a display needs to know about one player
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayerState {
+    pub position: Position,
+    pub direction: Direction,
+    pub score: u32,
+    pub alive: bool,
+    pub is_drawing: bool,
+    pub holding: bool,
+    pub draw_speed: DrawSpeed,
+}
+
+/// Everything a display needs apart from the board; small enough to send
+/// in full every tick
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameStatus {
+    pub state: GameState,
+    pub level: u32,
+    pub tick: u64,
+    pub score: u32,
+    pub filled_percentage: f32,
+    pub target_percentage: f32,
+    pub players: Vec<PlayerState>,
+    pub balls: Vec<Ball>,
+}
+
+impl GameStatus {
+    pub fn capture(game: &Game) -> Self {
+        Self {
+            state: game.state,
+            level: game.level,
+            tick: game.tick,
+            score: game.score,
+            filled_percentage: game.filled_percentage,
+            target_percentage: game.target_percentage,
+            players: game
+                .players
+                .iter()
+                .map(|player| PlayerState {
+                    position: player.position,
+                    direction: player.direction,
+                    score: player.score,
+                    alive: player.alive,
+                    is_drawing: player.is_drawing,
+                    holding: player.holding,
+                    draw_speed: player.draw_speed,
+                })
+                .collect(),
+            balls: game.balls.clone(),
+        }
+    }
+
+    pub fn apply(&self, game: &mut Game) {
+        game.state = self.state;
+        game.level = self.level;
+        game.tick = self.tick;
+        game.score = self.score;
+        game.filled_percentage = self.filled_percentage;
+        game.target_percentage = self.target_percentage;
+        game.balls = self.balls.clone();
+
+        // Trails travel as board cells
+        for (player, state) in game.players.iter_mut().zip(&self.players) {
+            player.clear_trail();
+            player.position = state.position;
+            player.direction = state.direction;
+            player.score = state.score;
+            player.alive = state.alive;
+            player.is_drawing = state.is_drawing;
+            player.holding = state.holding;
+            player.draw_speed = state.draw_speed;
+        }
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match self.state {
+            GameState::Playing => "P",
+            GameState::Paused => "Z",
+            GameState::Won => "W",
+            GameState::Lost => "L",
+        };
+        write!(
+            f,
+            "{} {} {} {} {} {} {}",
+            state,
+            self.level,
+            self.tick,
+            self.score,
+            self.filled_percentage,
+            self.target_percentage,
+            self.players.len()
+        )?;
+        for player in &self.players {
+            write!(
+                f,
+                " {},{},{},{},{},{},{},{}",
+                player.position.x,
+                player.position.y,
+                direction_token(player.direction),
+                player.score,
+                player.alive as u8,
+                player.is_drawing as u8,
+                player.holding as u8,
+                player.draw_speed.score_multiplier()
+            )?;
+        }
+        write!(f, " {}", self.balls.len())?;
+        for ball in &self.balls {
+            write!(
+                f,
+                " {},{},{},{}",
+                ball.position.x, ball.position.y, ball.velocity.0, ball.velocity.1
+            )?;
+        }
+        Ok(())
+    }
+
+    fn parse(tokens: &mut SplitWhitespace) -> Option<Self> {
+        let state = match tokens.next()? {
+            "P" => GameState::Playing,
+            "Z" => GameState::Paused,
+            "W" => GameState::Won,
+            "L" => GameState::Lost,
+            _ => return None,
+        };
+        let level = tokens.next()?.parse().ok()?;
+        let tick = tokens.next()?.parse().ok()?;
+        let score = tokens.next()?.parse().ok()?;
+        let filled_percentage = tokens.next()?.parse().ok()?;
+        let target_percentage = tokens.next()?.parse().ok()?;
+
+        let player_count: usize = tokens.next()?.parse().ok()?;
+        let mut players = Vec::new();
+        for _ in 0..player_count {
+            let fields: Vec<&str> = tokens.next()?.split(',').collect();
+            let [x, y, direction, score, alive, is_drawing, holding, speed] = fields[..] else {
+                return None;
+            };
+            players.push(PlayerState {
+                position: Position::new(x.parse().ok()?, y.parse().ok()?),
+                direction: direction_from_token(direction)?,
+                score: score.parse().ok()?,
+                alive: alive == "1",
+                is_drawing: is_drawing == "1",
+                holding: holding == "1",
+                draw_speed: if speed == "2" { DrawSpeed::Slow } else { DrawSpeed::Fast },
+            });
+        }
+
+        let ball_count: usize = tokens.next()?.parse().ok()?;
+        let mut balls = Vec::new();
+        for _ in 0..ball_count {
+            let fields: Vec<i32> = tokens
+                .next()?
+                .split(',')
+                .map(|field| field.parse().ok())
+                .collect::<Option<_>>()?;
+            let [x, y, vx, vy] = fields[..] else {
+                return None;
+            };
+            balls.push(Ball::new(x, y, vx, vy));
+        }
+
+        Some(Self {
+            state,
+            level,
+            tick,
+            score,
+            filled_percentage,
+            target_percentage,
+            players,
+            balls,
+        })
+    }
+}
+
+fn cell_char((cell, speed): CellState) -> char {
+    match (cell, speed) {
+        (Cell::Empty, _) => '.',
+        (Cell::Filled, DrawSpeed::Fast) => '#',
+        (Cell::Filled, DrawSpeed::Slow) => '%',
+        (Cell::Trail, _) => '+',
+    }
+}
+
+fn parse_cell(c: char) -> Option<CellState> {
+    Some(match c {
+        '.' => (Cell::Empty, DrawSpeed::Fast),
+        '#' => (Cell::Filled, DrawSpeed::Fast),
+        '%' => (Cell::Filled, DrawSpeed::Slow),
+        '+' => (Cell::Trail, DrawSpeed::Fast),
+        _ => return None,
+    })
+}
+
+/// Everything a display needs to draw a game it isn't simulating
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateSnapshot {
+    pub status: GameStatus,
+    pub board: BoardSnapshot,
+}
+
+impl StateSnapshot {
+    pub fn capture(game: &Game) -> Self {
+        Self {
+            status: GameStatus::capture(game),
+            board: BoardSnapshot::capture(game),
+        }
+    }
+
+    /// Overwrite `game` with this snapshot. `game` must have been built from
+    /// the same `GameSetup` as the game the snapshot came from; if the board
+    /// doesn't match, `game` is left as it was.
+    pub fn apply(&self, game: &mut Game) -> Result<(), BoardMismatch> {
+        self.board.apply(game)?;
+        self.status.apply(game);
+        Ok(())
+    }
+}
+
+/// The change between two snapshots of the same game: the full status, but
+/// only the cells that changed
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateDelta {
+    pub status: GameStatus,
+    pub board: BoardDelta,
+}
+
+impl StateDelta {
+    pub fn between(from: &StateSnapshot, to: &StateSnapshot) -> Self {
+        Self {
+            status: to.status.clone(),
+            board: from.board.diff(&to.board),
+        }
+    }
+
+    /// Bring a game showing the `from` snapshot up to `to`
+    pub fn apply(&self, game: &mut Game) -> Result<(), BoardMismatch> {
+        self.board.apply(game)?;
+        self.status.apply(game);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    /// Client → host: take the next free seat
+    Join { version: u32 },
+    /// Client → host: take back a seat after a dropped connection
+    Rejoin { version: u32, player: usize },
+    /// Host → client: your seat and the game to build. On a rejoin it's
+    /// followed by every `Step` so far, to be replayed.
+    Welcome { player: usize, setup: GameSetup },
+    /// Host → client: no seat for you
+    Refused { reason: String },
+    /// Client → host: my commands for `tick`, and my `state_hash` just before it
+    Input { tick: u64, hash: u64, commands: Vec<Command> },
+    /// Host → everyone: all commands for `tick`; apply them, then `Game::update`
+    Step { tick: u64, commands: Vec<(usize, Command)> },
+    /// Host → everyone: a peer's state hash didn't match the host's before `tick`
+    Desync { tick: u64 },
+    /// Client → authoritative server: apply these for my player now
+    Commands(Vec<Command>),
+    /// Server or publisher → client: the whole game as of the latest tick
+    Snapshot(StateSnapshot),
+    /// Publisher → spectator: what changed since the last snapshot or delta
+    Delta(StateDelta),
+    /// Publisher → spectator: the game to mirror; a `Snapshot` follows
+    Watch { setup: GameSetup },
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Message::Join { version } => write!(f, "JOIN {}", version),
+            Message::Rejoin { version, player } => write!(f, "REJOIN {} {}", version, player),
+            Message::Welcome { player, setup } => {
+                write!(f, "WELCOME {} ", player)?;
+                setup.write(f)
+            }
+            Message::Refused { reason } => write!(f, "REFUSED {}", reason),
+            Message::Input { tick, hash, commands } => {
+                write!(f, "INPUT {} {:x}", tick, hash)?;
+                for command in commands {
+                    write!(f, " {}", command.token())?;
+                }
+                Ok(())
+            }
+            Message::Step { tick, commands } => {
+                write!(f, "STEP {}", tick)?;
+                for (player, command) in commands {
+                    write!(f, " {}:{}", player, command.token())?;
+                }
+                Ok(())
+            }
+            Message::Desync { tick } => write!(f, "DESYNC {}", tick),
+            Message::Commands(commands) => {
+                write!(f, "CMD")?;
+                for command in commands {
+                    write!(f, " {}", command.token())?;
+                }
+                Ok(())
+            }
+            Message::Snapshot(snapshot) => {
+                write!(f, "SNAP ")?;
+                snapshot.status.write(f)?;
+                // One character per cell, rows separated by '/'
+                write!(f, " ")?;
+                for (y, row) in snapshot.board.rows().enumerate() {
+                    if y > 0 {
+                        write!(f, "/")?;
+                    }
+                    for &cell in row {
+                        write!(f, "{}", cell_char(cell))?;
+                    }
+                }
+                Ok(())
+            }
+            Message::Delta(delta) => {
+                write!(f, "DELTA ")?;
+                delta.status.write(f)?;
+                // Board width, then start:cells for each run of changes, or
+                // '-' when the board didn't change
+                write!(f, " {}", delta.board.width())?;
+                if delta.board.is_empty() {
+                    return write!(f, " -");
+                }
+                for (i, run) in delta.board.runs().iter().enumerate() {
+                    let separator = if i == 0 { ' ' } else { ';' };
+                    write!(f, "{}{}:", separator, run.start)?;
+                    for &cell in &run.cells {
+                        write!(f, "{}", cell_char(cell))?;
+                    }
+                }
+                Ok(())
+            }
+            Message::Watch { setup } => {
+                write!(f, "WATCH ")?;
+                setup.write(f)
+            }
+        }
+    }
+}
+
+fn malformed(line: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("Malformed message: {}", line))
+}
+
+impl Message {
+    /// Parse one line (without its newline)
+    pub fn parse(line: &str) -> io::Result<Self> {
+        let mut tokens = line.split_whitespace();
+        let kind = tokens.next().ok_or_else(|| malformed(line))?;
+
+        let number = |tokens: &mut SplitWhitespace| -> io::Result<u64> {
+            tokens
+                .next()
+                .and_then(|token| token.parse().ok())
+                .ok_or_else(|| malformed(line))
+        };
+
+        let message = match kind {
+            "JOIN" => Message::Join {
+                version: number(&mut tokens)? as u32,
+            },
+            "REJOIN" => Message::Rejoin {
+                version: number(&mut tokens)? as u32,
+                player: number(&mut tokens)? as usize,
+            },
+            "WELCOME" => Message::Welcome {
+                player: number(&mut tokens)? as usize,
+                setup: GameSetup::parse(&mut tokens).ok_or_else(|| malformed(line))?,
+            },
+            "REFUSED" => Message::Refused {
+                reason: tokens.by_ref().collect::<Vec<_>>().join(" "),
+            },
+            "INPUT" => {
+                let tick = number(&mut tokens)?;
+                let hash = tokens
+                    .next()
+                    .and_then(|token| u64::from_str_radix(token, 16).ok())
+                    .ok_or_else(|| malformed(line))?;
+                let commands = tokens
+                    .by_ref()
+                    .map(|token| Command::from_token(token).ok_or_else(|| malformed(line)))
+                    .collect::<io::Result<_>>()?;
+                Message::Input { tick, hash, commands }
+            }
+            "STEP" => {
+                let tick = number(&mut tokens)?;
+                let commands = tokens
+                    .by_ref()
+                    .map(|token| {
+                        let (player, command) = token.split_once(':')?;
+                        Some((player.parse().ok()?, Command::from_token(command)?))
+                    })
+                    .map(|entry| entry.ok_or_else(|| malformed(line)))
+                    .collect::<io::Result<_>>()?;
+                Message::Step { tick, commands }
+            }
+            "DESYNC" => Message::Desync {
+                tick: number(&mut tokens)?,
+            },
+            "CMD" => Message::Commands(
+                tokens
+                    .by_ref()
+                    .map(|token| Command::from_token(token).ok_or_else(|| malformed(line)))
+                    .collect::<io::Result<_>>()?,
+            ),
+            "SNAP" => {
+                let status = GameStatus::parse(&mut tokens).ok_or_else(|| malformed(line))?;
+                let board = tokens
+                    .next()
+                    .and_then(|board| {
+                        let rows = board
+                            .split('/')
+                            .map(|row| row.chars().map(parse_cell).collect::<Option<Vec<_>>>())
+                            .collect::<Option<_>>()?;
+                        BoardSnapshot::from_rows(rows)
+                    })
+                    .ok_or_else(|| malformed(line))?;
+                Message::Snapshot(StateSnapshot { status, board })
+            }
+            "DELTA" => {
+                let status = GameStatus::parse(&mut tokens).ok_or_else(|| malformed(line))?;
+                let width = number(&mut tokens)? as i32;
+                let runs = match tokens.next() {
+                    Some("-") => Vec::new(),
+                    Some(runs) => runs
+                        .split(';')
+                        .map(|run| {
+                            let (start, cells) = run.split_once(':')?;
+                            let cells = cells.chars().map(parse_cell).collect::<Option<Vec<_>>>()?;
+                            (!cells.is_empty()).then_some(())?;
+                            // No run can reach past the largest board we'd build
+                            let start: usize = start.parse().ok()?;
+                            let end = start.checked_add(cells.len())?;
+                            (end <= (MAX_BOARD_SIDE * MAX_BOARD_SIDE) as usize).then_some(())?;
+                            Some(Run { start, cells })
+                        })
+                        .collect::<Option<_>>()
+                        .ok_or_else(|| malformed(line))?,
+                    None => return Err(malformed(line)),
+                };
+                Message::Delta(StateDelta {
+                    status,
+                    board: BoardDelta::from_runs(width, runs),
+                })
+            }
+            "WATCH" => Message::Watch {
+                setup: GameSetup::parse(&mut tokens).ok_or_else(|| malformed(line))?,
+            },
+            _ => return Err(malformed(line)),
+        };
+
+        // Nothing may trail a complete message
+        if tokens.next().is_some() {
+            return Err(malformed(line));
+        }
+
+        Ok(message)
+    }
+}
+
+/// A message pipe to one peer. Neither method blocks: `send` queues what
+/// the connection can't take yet, and `try_recv` writes out the queue
+/// before reading. A closed connection is reported as
+/// `ErrorKind::ConnectionAborted`, and a peer that stops reading as
+/// `ErrorKind::TimedOut` once too much is queued for it.
+pub trait Transport {
+    fn send(&mut self, message: &Message) -> io::Result<()>;
+    fn try_recv(&mut self) -> io::Result<Option<Message>>;
+}
+
+/// Most bytes a peer may leave unread before it's cut off; a few seconds of
+/// messages, with room to spare for a full snapshot
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_QUEUED: usize = 256 * 1024;
+
+/// Longest line a peer may send: a snapshot of the largest board, one
+/// character per cell and a separator per row, plus room for the status
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_LINE: usize = (MAX_BOARD_SIDE * (MAX_BOARD_SIDE + 1)) as usize + 64 * 1024;
+
+/// Bytes waiting for a non-blocking socket. Nothing ever waits on the peer:
+/// what the socket won't take yet stays queued for the next `send` or
+/// `flush`, and a peer that lets more than `MAX_QUEUED` bytes pile up has
+/// stopped reading and is dropped.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Default)]
+pub(crate) struct Outbox {
+    queued: Vec<u8>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Outbox {
+    /// Queue `bytes` and write as much as the socket will take
+    pub(crate) fn send(&mut self, stream: &mut std::net::TcpStream, bytes: &[u8]) -> io::Result<()> {
+        if self.queued.len() > MAX_QUEUED {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "peer stopped reading"));
+        }
+        self.queued.extend_from_slice(bytes);
+        self.flush(stream)
+    }
+
+    /// Write as much of the queue as the socket will take
+    pub(crate) fn flush(&mut self, stream: &mut std::net::TcpStream) -> io::Result<()> {
+        use std::io::Write;
+
+        let mut written = 0;
+        while written < self.queued.len() {
+            match stream.write(&self.queued[written..]) {
+                Ok(0) => return Err(disconnected()),
+                Ok(n) => written += n,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        self.queued.drain(..written);
+        Ok(())
+    }
+}
+
+pub(crate) fn disconnected() -> io::Error {
+    io::Error::new(io::ErrorKind::ConnectionAborted, "peer disconnected")
+}
+
+/// In-process transport, for tests and for running both ends of a session in
+/// one process. Messages still go through the text encoding.
+pub struct LoopbackTransport {
+    outgoing: Sender<String>,
+    incoming: Receiver<String>,
+}
+
+/// Two connected loopback ends
+pub fn loopback_pair() -> (LoopbackTransport, LoopbackTransport) {
+    let (a_tx, b_rx) = mpsc::channel();
+    let (b_tx, a_rx) = mpsc::channel();
+    (
+        LoopbackTransport {
+            outgoing: a_tx,
+            incoming: a_rx,
+        },
+        LoopbackTransport {
+            outgoing: b_tx,
+            incoming: b_rx,
+        },
+    )
+}
+
+impl Transport for LoopbackTransport {
+    fn send(&mut self, message: &Message) -> io::Result<()> {
+        self.outgoing.send(message.to_string()).map_err(|_| disconnected())
+    }
+
+    fn try_recv(&mut self) -> io::Result<Option<Message>> {
+        match self.incoming.try_recv() {
+            Ok(line) => Message::parse(&line).map(Some),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(disconnected()),
+        }
+    }
+}
+
+/// Newline-delimited messages over a non-blocking TCP stream
+#[cfg(not(target_arch = "wasm32"))]
+pub struct TcpTransport {
+    stream: std::net::TcpStream,
+    outbox: Outbox,
+    buffer: Vec<u8>,
+    lines: std::collections::VecDeque<String>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl TcpTransport {
+    pub fn new(stream: std::net::TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        stream.set_nonblocking(true)?;
+        Ok(Self {
+            stream,
+            outbox: Outbox::default(),
+            buffer: Vec::new(),
+            lines: std::collections::VecDeque::new(),
+        })
+    }
+
+    pub fn connect(addr: &str) -> io::Result<Self> {
+        Self::new(std::net::TcpStream::connect(addr)?)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Transport for TcpTransport {
+    fn send(&mut self, message: &Message) -> io::Result<()> {
+        self.outbox.send(&mut self.stream, format!("{}\n", message).as_bytes())
+    }
+
+    fn try_recv(&mut self) -> io::Result<Option<Message>> {
+        use std::io::Read;
+
+        self.outbox.flush(&mut self.stream)?;
+
+        let mut chunk = [0u8; 1024];
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => {
+                    if self.lines.is_empty() {
+                        return Err(disconnected());
+                    }
+                    break;
+                }
+                Ok(n) => self.buffer.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+            // Leave the rest for the next call rather than buffer without bound
+            if self.buffer.len() > MAX_LINE {
+                break;
+            }
+        }
+
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            self.lines
+                .push_back(String::from_utf8_lossy(&line).trim_end().to_string());
+        }
+        if self.buffer.len() > MAX_LINE {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "line too long"));
+        }
+
+        match self.lines.pop_front() {
+            Some(line) => Message::parse(&line).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> GameSetup {
+        GameSetup {
+            width: 30,
+            height: 20,
+            player_count: 3,
+            seed: 99,
+            capture_rule: "qix".to_string(),
+            movement: MovementMode::EightWay,
+            target_mode: TargetMode::Competitive,
+        }
+    }
+
+    #[test]
+    fn test_messages_round_trip() {
+        let messages = vec![
+            Message::Join { version: PROTOCOL_VERSION },
+            Message::Rejoin { version: 1, player: 2 },
+            Message::Welcome { player: 1, setup: setup() },
+            Message::Refused {
+                reason: "game is full".to_string(),
+            },
+            Message::Input {
+                tick: 12,
+                hash: 0xdead_beef,
+                commands: vec![Command::Turn(Direction::UpLeft), Command::Hold],
+            },
+            Message::Input {
+                tick: 0,
+                hash: 0,
+                commands: vec![],
+            },
+            Message::Step {
+                tick: 12,
+                commands: vec![(0, Command::Restart), (2, Command::Turn(Direction::Down))],
+            },
+            Message::Desync { tick: 40 },
+            Message::Commands(vec![Command::Turn(Direction::Left), Command::ToggleSlowDraw]),
+            Message::Commands(vec![]),
+            Message::Watch { setup: setup() },
+        ];
+
+        for message in messages {
+            assert_eq!(Message::parse(&message.to_string()).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn test_snapshot_round_trips_and_applies() {
+        let mut game = setup().build();
+        game.set_player_direction(1, Direction::Down);
+        game.toggle_player_draw_speed(2);
+        for _ in 0..4 {
+            game.update();
+        }
+        game.board.set_speed(0, 0, DrawSpeed::Slow);
+
+        let snapshot = StateSnapshot::capture(&game);
+        let message = Message::Snapshot(snapshot.clone());
+        assert_eq!(Message::parse(&message.to_string()).unwrap(), message);
+
+        // A fresh game from the same setup looks identical once the snapshot is applied
+        let mut copy = setup().build();
+        snapshot.apply(&mut copy).unwrap();
+        assert_eq!(copy.board, game.board);
+        assert_eq!(copy.capture_speed_at(0, 0), DrawSpeed::Slow);
+        assert_eq!(copy.players[1].position, game.players[1].position);
+        assert_eq!(copy.players[2].draw_speed, DrawSpeed::Slow);
+        assert_eq!(copy.filled_percentage, game.filled_percentage);
+        assert_eq!(StateSnapshot::capture(&copy), snapshot);
+    }
+
+    #[test]
+    fn test_delta_carries_only_changed_cells() {
+        let mut game = setup().build();
+        let mut mirror = setup().build();
+        let before = StateSnapshot::capture(&game);
+        before.apply(&mut mirror).unwrap();
+
+        game.set_player_direction(1, Direction::Down);
+        for _ in 0..3 {
+            game.update();
+        }
+        let after = StateSnapshot::capture(&game);
+        let delta = StateDelta::between(&before, &after);
+        assert!(!delta.board.is_empty());
+        assert!(delta.board.changed_cells().count() < game.width as usize * game.height as usize);
+
+        let message = Message::Delta(delta.clone());
+        let Message::Delta(received) = Message::parse(&message.to_string()).unwrap() else {
+            panic!("expected DELTA");
+        };
+        assert_eq!(received, delta);
+        received.apply(&mut mirror).unwrap();
+        assert_eq!(StateSnapshot::capture(&mirror), after);
+
+        // Nothing changed on the board
+        let still = StateDelta::between(&after, &after);
+        assert!(still.board.is_empty());
+        let message = Message::Delta(still);
+        assert_eq!(Message::parse(&message.to_string()).unwrap(), message);
+    }
+
+    #[test]
+    fn test_malformed_messages_are_rejected() {
+        for line in ["", "HELLO", "JOIN", "INPUT 3 zz", "STEP 1 0-U", "STEP 1 0:Q", "WELCOME 0 1", "CMD Q", "SNAP P 1 0 0 0.75 0 0 ..x", "DELTA P 1 0 0 0.75 0 0", "DELTA P 1 0 0 0.75 0 0 30 1,2", "DELTA P 1 0 0 0.75 0 0 30 4:", "DELTA P 1 0 0 0.75 0 0 30 18446744073709551615:.", "DELTA P 1 0 0 0.75 0 0 30 4000000:.", "WATCH 2"] {
+            assert!(Message::parse(line).is_err(), "{:?} should not parse", line);
+        }
+    }
+
+    #[test]
+    fn test_setups_a_game_cant_be_built_from_are_rejected() {
+        for (player_count, width, height) in [(0, 30, 20), (5, 30, 20), (2, 0, 20), (2, -30, 20), (2, 30, 9), (2, 19, 20), (2, 100_000, 20), (2, 30, 2001)] {
+            let setup = GameSetup {
+                player_count,
+                width,
+                height,
+                ..setup()
+            };
+            assert!(!setup.is_valid());
+            let line = Message::Welcome { player: 0, setup }.to_string();
+            assert!(Message::parse(&line).is_err(), "{:?} should not parse", line);
+        }
+        let largest = GameSetup {
+            player_count: MAX_PLAYERS,
+            width: MAX_BOARD_SIDE,
+            height: MIN_BOARD_SIZE.1,
+            ..setup()
+        };
+        let message = Message::Watch { setup: largest };
+        assert_eq!(Message::parse(&message.to_string()).unwrap(), message);
+    }
+
+    #[test]
+    fn test_setup_builds_identical_games() {
+        let a = setup().build();
+        let b = setup().build();
+        assert_eq!(a.players.len(), 3);
+        assert_eq!(a.capture_rule.name(), "qix");
+        assert_eq!(a.state_hash(), b.state_hash());
+    }
+
+    #[test]
+    fn test_loopback_reports_disconnect() {
+        let (mut a, b) = loopback_pair();
+        drop(b);
+        assert_eq!(
+            a.try_recv().unwrap_err().kind(),
+            io::ErrorKind::ConnectionAborted
+        );
+    }
+
+    #[test]
+    fn test_tcp_transport_splits_lines() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let mut client = TcpTransport::connect(&addr).unwrap();
+        let mut server = TcpTransport::new(listener.accept().unwrap().0).unwrap();
+
+        client.send(&Message::Join { version: 1 }).unwrap();
+        client.send(&Message::Desync { tick: 5 }).unwrap();
+
+        let mut received = Vec::new();
+        while received.len() < 2 {
+            if let Some(message) = server.try_recv().unwrap() {
+                received.push(message);
+            }
+        }
+        assert_eq!(received, vec![Message::Join { version: 1 }, Message::Desync { tick: 5 }]);
+    }
+
+    #[test]
+    fn test_a_peer_that_stops_reading_is_dropped_without_blocking() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let mut sender = TcpTransport::connect(&addr).unwrap();
+        // Accepted but never read, so the socket buffers fill up
+        let _stalled = listener.accept().unwrap();
+
+        let message = Message::Refused {
+            reason: "x".repeat(64 * 1024),
+        };
+        let error = (0..10_000)
+            .find_map(|_| sender.send(&message).err())
+            .expect("sending never failed");
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn test_endless_lines_are_refused() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut raw = std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let mut receiver = TcpTransport::new(listener.accept().unwrap().0).unwrap();
+        // Never a newline; the writes block until the receiver reads
+        let writer = std::thread::spawn(move || {
+            let chunk = [b'x'; 64 * 1024];
+            while std::io::Write::write_all(&mut raw, &chunk).is_ok() {}
+        });
+
+        let error = loop {
+            match receiver.try_recv() {
+                Ok(_) => std::thread::sleep(std::time::Duration::from_millis(1)),
+                Err(e) => break e,
+            }
+        };
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        drop(receiver);
+        writer.join().unwrap();
+    }
+}
@@ -1,14 +1,24 @@
 use crate::game::Game;
-use crate::entity::Direction;
+use crate::entity::{Direction, Position};
+use crate::key_bindings::Key;
 use std::io;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Input {
     Direction(Direction),
+    PlayerDirection(usize, Direction), // Turn another local player (index 1 and up)
     Quit,
     Restart,
     NextLevel,
+    ToggleSlowDraw,
+    Hold, // Stop in place on filled territory (toggle)
     Tap, // Mobile tap gesture - handled contextually based on game state
+    Pause, // Pause or resume a local game
+    Confirm, // Pick the highlighted menu item
+    FocusLost, // The terminal or browser tab went to the background; pauses a running game
+    Resize, // The terminal changed size; the renderer has already re-laid out the board
+    MoveToward(Position), // Head the first player toward a board cell (mouse click)
+    Key(Key), // A key with no binding applied, sent while the menu waits for a key to bind
 }
 
 /// Trait that abstracts rendering implementation.
@@ -0,0 +1,343 @@
+//! Authoritative game server for browser clients.
+//!
+//! Clients connect over WebSocket to `/?room=<name>` and get the next free
+//! seat in that room. The server runs each room's `Game`, applies `Commands`
+//! as they arrive, and sends a newly seated client a `Snapshot`, then a `Delta`
+//! after each tick. A room only ticks while all of its seats are taken, and is
+//! dropped once empty.
+
+use crate::game::Game;
+use crate::game_loop::{GameLoop, SystemClock};
+use crate::protocol::{GameSetup, Message, StateDelta, StateSnapshot, Transport};
+use crate::websocket::WebSocketTransport;
+use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::time::{Duration, Instant};
+
+const DEFAULT_ROOM: &str = "lobby";
+
+/// How long a connection gets to finish its WebSocket handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Room {
+    game: Game,
+    setup: GameSetup,
+    seats: Vec<Option<WebSocketTransport>>,
+    /// The state every seated client has, for working out the next delta
+    last: StateSnapshot,
+}
+
+impl Room {
+    fn new(template: &GameSetup) -> Self {
+        let setup = GameSetup {
+            seed: rand::random(),
+            ..template.clone()
+        };
+        let game = setup.build();
+        let seats = (0..game.players.len()).map(|_| None).collect();
+        let last = StateSnapshot::capture(&game);
+        Self { game, setup, seats, last }
+    }
+
+    fn is_full(&self) -> bool {
+        self.seats.iter().all(Option::is_some)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.seats.iter().all(Option::is_none)
+    }
+
+    /// Seat a client, sending it the setup and the current state
+    fn seat(&mut self, mut client: WebSocketTransport) {
+        let Some(player) = self.seats.iter().position(Option::is_none) else {
+            let _ = client.send(&Message::Refused {
+                reason: "room is full".to_string(),
+            });
+            return;
+        };
+
+        let welcome = Message::Welcome {
+            player,
+            setup: self.setup.clone(),
+        };
+        // The next delta is relative to `last`, so late joiners start there too
+        let snapshot = Message::Snapshot(self.last.clone());
+        if client.send(&welcome).is_ok() && client.send(&snapshot).is_ok() {
+            self.seats[player] = Some(client);
+        }
+    }
+
+    /// Apply every command the clients have sent; drop clients that went away
+    fn read_commands(&mut self) {
+        for (player, seat) in self.seats.iter_mut().enumerate() {
+            while let Some(client) = seat {
+                match client.try_recv() {
+                    Ok(Some(Message::Commands(commands))) => {
+                        for command in commands {
+                            command.apply(&mut self.game, player);
+                        }
+                    }
+                    Ok(Some(_)) => {}
+                    Ok(None) => break,
+                    Err(_) => *seat = None,
+                }
+            }
+        }
+    }
+
+    fn broadcast(&mut self, message: &Message) {
+        for seat in &mut self.seats {
+            if let Some(client) = seat {
+                if client.send(message).is_err() {
+                    *seat = None;
+                }
+            }
+        }
+    }
+}
+
+pub struct Server {
+    listener: TcpListener,
+    /// Connections still completing their WebSocket handshake, with when they arrived
+    pending: Vec<(WebSocketTransport, Instant)>,
+    handshake_timeout: Duration,
+    rooms: HashMap<String, Room>,
+    /// Settings for new rooms; each room gets its own seed
+    template: GameSetup,
+}
+
+impl Server {
+    pub fn bind(addr: &str, template: GameSetup) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            pending: Vec::new(),
+            handshake_timeout: HANDSHAKE_TIMEOUT,
+            rooms: HashMap::new(),
+            template,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Connections that haven't finished their handshake yet
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Number of rooms with at least one client
+    pub fn room_count(&self) -> usize {
+        self.rooms.len()
+    }
+
+    /// Accept connections, finish handshakes and apply incoming commands
+    pub fn poll(&mut self) -> io::Result<()> {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, peer)) => match WebSocketTransport::accept(stream) {
+                    Ok(client) => self.pending.push((client, Instant::now())),
+                    // Only this connection is lost; everyone else plays on
+                    Err(e) => eprintln!("Dropped connection from {}: {}", peer, e),
+                },
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+
+        for (mut client, arrived) in std::mem::take(&mut self.pending) {
+            // Drops clients whose handshake failed
+            if client.try_recv().is_err() {
+                continue;
+            }
+            if !client.is_open() {
+                // Silent or slow clients don't get to hold a connection open forever
+                if arrived.elapsed() < self.handshake_timeout {
+                    self.pending.push((client, arrived));
+                }
+                continue;
+            }
+
+            let room = room_name(client.path().unwrap_or("/")).to_string();
+            self.rooms
+                .entry(room)
+                .or_insert_with(|| Room::new(&self.template))
+                .seat(client);
+        }
+
+        for room in self.rooms.values_mut() {
+            room.read_commands();
+        }
+        self.rooms.retain(|_, room| !room.is_empty());
+        Ok(())
+    }
+
+    /// Advance every full room one tick and send out the new state
+    pub fn tick(&mut self) {
+        for room in self.rooms.values_mut().filter(|room| room.is_full()) {
+            room.game.update();
+            let snapshot = StateSnapshot::capture(&room.game);
+            let delta = Message::Delta(StateDelta::between(&room.last, &snapshot));
+            room.broadcast(&delta);
+            room.last = snapshot;
+        }
+    }
+
+    /// Serve forever
+    pub fn run(&mut self) -> io::Result<()> {
+        let mut game_loop = GameLoop::new(SystemClock::default());
+        loop {
+            self.poll()?;
+            while game_loop.next_tick() {
+                self.tick();
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+}
+
+/// The `room` query parameter of a request path like `/?room=team`
+fn room_name(path: &str) -> &str {
+    path.split_once('?')
+        .and_then(|(_, query)| {
+            query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("room="))
+        })
+        .filter(|name| !name.is_empty())
+        .unwrap_or(DEFAULT_ROOM)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::{Direction, MovementMode};
+    use crate::game::TargetMode;
+    use crate::protocol::Command;
+
+    fn template(player_count: usize) -> GameSetup {
+        GameSetup {
+            width: 30,
+            height: 20,
+            player_count,
+            seed: 0,
+            capture_rule: "xonix".to_string(),
+            movement: MovementMode::FourWay,
+            target_mode: TargetMode::Shared,
+        }
+    }
+
+    /// Poll the server until `client` receives a message
+    fn next_message(server: &mut Server, client: &mut WebSocketTransport) -> Message {
+        for _ in 0..1000 {
+            server.poll().unwrap();
+            if let Some(message) = client.try_recv().unwrap() {
+                return message;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        panic!("no message from server");
+    }
+
+    fn join(server: &mut Server, path: &str) -> (WebSocketTransport, usize, Game) {
+        let addr = server.local_addr().unwrap().to_string();
+        let mut client = WebSocketTransport::connect(&addr, path).unwrap();
+        let Message::Welcome { player, setup } = next_message(server, &mut client) else {
+            panic!("expected WELCOME");
+        };
+        let mut game = setup.build();
+        let Message::Snapshot(snapshot) = next_message(server, &mut client) else {
+            panic!("expected SNAP");
+        };
+        snapshot.apply(&mut game).unwrap();
+        (client, player, game)
+    }
+
+    #[test]
+    fn test_room_name_from_path() {
+        assert_eq!(room_name("/?room=team"), "team");
+        assert_eq!(room_name("/?x=1&room=b"), "b");
+        assert_eq!(room_name("/"), DEFAULT_ROOM);
+        assert_eq!(room_name("/?room="), DEFAULT_ROOM);
+    }
+
+    #[test]
+    fn test_clients_share_an_authoritative_game() {
+        let mut server = Server::bind("127.0.0.1:0", template(2)).unwrap();
+        let (mut first, first_seat, mut first_game) = join(&mut server, "/?room=team");
+        assert_eq!(first_seat, 0);
+
+        // Half-full rooms wait
+        server.tick();
+        let start = first_game.players[0].position;
+
+        let (mut second, second_seat, second_game) = join(&mut server, "/?room=team");
+        assert_eq!(second_seat, 1);
+        assert_eq!(second_game.players[0].position, start);
+
+        second.send(&Message::Commands(vec![Command::Turn(Direction::Down)])).unwrap();
+        let mut applied = false;
+        for _ in 0..1000 {
+            server.poll().unwrap();
+            if server.rooms["team"].game.players[1].direction == Direction::Down {
+                applied = true;
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(applied);
+
+        server.tick();
+        let Message::Delta(delta) = next_message(&mut server, &mut first) else {
+            panic!("expected DELTA");
+        };
+        delta.apply(&mut first_game).unwrap();
+        assert_eq!(first_game.players[0].position, start.moved(Direction::Right));
+        assert_eq!(first_game.players[1].direction, Direction::Down);
+        assert_eq!(first_game.board, server.rooms["team"].game.board);
+    }
+
+    #[test]
+    fn test_full_rooms_refuse_and_empty_rooms_close() {
+        let mut server = Server::bind("127.0.0.1:0", template(1)).unwrap();
+        let (first, _, _) = join(&mut server, "/");
+        let (_other_room, _, _) = join(&mut server, "/?room=other");
+        assert_eq!(server.room_count(), 2);
+
+        let addr = server.local_addr().unwrap().to_string();
+        let mut extra = WebSocketTransport::connect(&addr, "/").unwrap();
+        assert!(matches!(next_message(&mut server, &mut extra), Message::Refused { .. }));
+
+        drop(first);
+        for _ in 0..1000 {
+            server.poll().unwrap();
+            if server.room_count() == 1 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(server.room_count(), 1);
+    }
+
+    #[test]
+    fn test_stalled_handshakes_are_dropped() {
+        let mut server = Server::bind("127.0.0.1:0", template(1)).unwrap();
+        server.handshake_timeout = Duration::from_millis(20);
+        let _silent = std::net::TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        for _ in 0..1000 {
+            server.poll().unwrap();
+            if server.pending_count() == 1 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(server.pending_count(), 1);
+
+        std::thread::sleep(Duration::from_millis(30));
+        server.poll().unwrap();
+        assert_eq!(server.pending_count(), 0);
+    }
+}
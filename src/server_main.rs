@@ -0,0 +1,83 @@
+use std::io;
+use axion::capture::capture_rule_by_name;
+use axion::protocol::{GameSetup, MAX_BOARD_SIDE, MIN_BOARD_SIZE};
+use axion::server::Server;
+use axion::{MovementMode, TargetMode, MAX_PLAYERS};
+
+const DEFAULT_PORT: u16 = 7346;
+
+const USAGE: &str = "Usage: axion-server [--port N] [--players 1-4] [--size WIDTHxHEIGHT] \
+                     [--capture-rule xonix|qix|smaller] [--eight-way] [--competitive]";
+
+fn parse_args() -> io::Result<(u16, GameSetup)> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, format!("{}\n{}", msg, USAGE));
+
+    let mut port = DEFAULT_PORT;
+    // Matches the web build's board
+    let mut setup = GameSetup {
+        width: 40,
+        height: 20,
+        player_count: 2,
+        seed: 0,
+        capture_rule: "xonix".to_string(),
+        movement: MovementMode::FourWay,
+        target_mode: TargetMode::Shared,
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| invalid(format!("{} needs a value", name)));
+        match arg.as_str() {
+            "--port" => {
+                let value = value("--port")?;
+                port = value.parse().map_err(|_| invalid(format!("Bad port: {}", value)))?;
+            }
+            "--players" => {
+                let value = value("--players")?;
+                setup.player_count = value
+                    .parse()
+                    .ok()
+                    .filter(|count| (1..=MAX_PLAYERS).contains(count))
+                    .ok_or_else(|| invalid(format!("Player count must be 1-{}: {}", MAX_PLAYERS, value)))?;
+            }
+            "--size" => {
+                let value = value("--size")?;
+                let (width, height) = value
+                    .split_once('x')
+                    .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                    .filter(|&(w, h): &(i32, i32)| {
+                        (MIN_BOARD_SIZE.0..=MAX_BOARD_SIDE).contains(&w) && (MIN_BOARD_SIZE.1..=MAX_BOARD_SIDE).contains(&h)
+                    })
+                    .ok_or_else(|| {
+                        invalid(format!(
+                            "Size must be {}x{} to {}x{}: {}",
+                            MIN_BOARD_SIZE.0, MIN_BOARD_SIZE.1, MAX_BOARD_SIDE, MAX_BOARD_SIDE, value
+                        ))
+                    })?;
+                setup.width = width;
+                setup.height = height;
+            }
+            "--capture-rule" => {
+                let name = value("--capture-rule")?;
+                if capture_rule_by_name(&name).is_none() {
+                    return Err(invalid(format!("Unknown capture rule: {}", name)));
+                }
+                setup.capture_rule = name;
+            }
+            "--eight-way" => setup.movement = MovementMode::EightWay,
+            "--competitive" => setup.target_mode = TargetMode::Competitive,
+            _ => return Err(invalid(format!("Unknown argument: {}", arg))),
+        }
+    }
+
+    Ok((port, setup))
+}
+
+fn main() -> io::Result<()> {
+    let (port, setup) = parse_args()?;
+
+    let mut server = Server::bind(&format!("0.0.0.0:{}", port), setup)?;
+    println!("Axion server listening on ws://{}", server.local_addr()?);
+
+    server.run()
+}
@@ -0,0 +1,300 @@
+//! Board snapshots and the deltas between them.
+//!
+//! Anything that keeps board history (spectators, network snapshots, the web
+//! renderer's fill animation) captures a `BoardSnapshot` per tick and passes
+//! around `BoardDelta`s instead of whole boards. A delta lists runs of
+//! consecutive changed cells in row-major order, so a trail step is one
+//! one-cell run and a capture is roughly one run per row it touches.
+
+use crate::entity::{DrawSpeed, Position};
+use crate::game::{Cell, Game};
+use std::fmt;
+
+/// A board cell and the draw speed it was captured at
+pub type CellState = (Cell, DrawSpeed);
+
+/// A snapshot or delta that doesn't fit the board it was applied to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoardMismatch {
+    pub width: i32,
+    pub height: i32,
+}
+
+impl fmt::Display for BoardMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "update doesn't fit a {}x{} board", self.width, self.height)
+    }
+}
+
+impl std::error::Error for BoardMismatch {}
+
+impl BoardMismatch {
+    fn of(game: &Game) -> Self {
+        Self {
+            width: game.width,
+            height: game.height,
+        }
+    }
+}
+
+/// A copy of a game's board at one tick
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardSnapshot {
+    pub width: i32,
+    pub height: i32,
+    /// Row-major, `width * height` long
+    cells: Vec<CellState>,
+}
+
+impl BoardSnapshot {
+    /// A board of `cells` rows. Returns None if the rows differ in length.
+    pub fn from_rows(rows: Vec<Vec<CellState>>) -> Option<Self> {
+        let width = rows.first().map_or(0, Vec::len);
+        if rows.iter().any(|row| row.len() != width) {
+            return None;
+        }
+        Some(Self {
+            width: width as i32,
+            height: rows.len() as i32,
+            cells: rows.into_iter().flatten().collect(),
+        })
+    }
+
+    pub fn capture(game: &Game) -> Self {
+        Self {
+            width: game.width,
+            height: game.height,
+            cells: (0..game.height)
+                .flat_map(|y| (0..game.width).map(move |x| (game.board.get(x, y), game.board.speed(x, y))))
+                .collect(),
+        }
+    }
+
+    /// Overwrite `game`'s board. Leaves it untouched if the sizes differ.
+    pub fn apply(&self, game: &mut Game) -> Result<(), BoardMismatch> {
+        if (self.width, self.height) != (game.width, game.height) {
+            return Err(BoardMismatch::of(game));
+        }
+        for (i, &(cell, speed)) in self.cells.iter().enumerate() {
+            let (x, y) = ((i % self.width as usize) as i32, (i / self.width as usize) as i32);
+            game.board.set(x, y, cell);
+            game.board.set_speed(x, y, speed);
+        }
+        Ok(())
+    }
+
+    pub fn cell(&self, x: i32, y: i32) -> CellState {
+        self.cells[(y * self.width + x) as usize]
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[CellState]> {
+        self.cells.chunks(self.width.max(1) as usize)
+    }
+
+    /// What changed from `self` to `other`. Both must be the same size.
+    pub fn diff(&self, other: &BoardSnapshot) -> BoardDelta {
+        let mut runs: Vec<Run> = Vec::new();
+        for (i, (old, new)) in self.cells.iter().zip(&other.cells).enumerate() {
+            if old == new {
+                continue;
+            }
+            match runs.last_mut() {
+                Some(run) if run.start + run.cells.len() == i => run.cells.push(*new),
+                _ => runs.push(Run {
+                    start: i,
+                    cells: vec![*new],
+                }),
+            }
+        }
+        BoardDelta {
+            width: self.width,
+            runs,
+        }
+    }
+}
+
+/// Consecutive changed cells, starting at a row-major index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Run {
+    pub start: usize,
+    pub cells: Vec<CellState>,
+}
+
+/// The cells that changed between two snapshots of the same board
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardDelta {
+    width: i32,
+    runs: Vec<Run>,
+}
+
+impl BoardDelta {
+    /// A delta for a board `width` cells wide
+    pub fn from_runs(width: i32, runs: Vec<Run>) -> Self {
+        Self { width, runs }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn runs(&self) -> &[Run] {
+        &self.runs
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+
+    /// Every changed cell with its new state
+    pub fn changed_cells(&self) -> impl Iterator<Item = (Position, CellState)> + '_ {
+        let width = self.width as usize;
+        self.runs.iter().flat_map(move |run| {
+            run.cells.iter().enumerate().map(move |(offset, &cell)| {
+                let i = run.start + offset;
+                (Position::new((i % width) as i32, (i / width) as i32), cell)
+            })
+        })
+    }
+
+    /// Turn the snapshot this delta was taken from into the one it leads to
+    pub fn apply_to(&self, snapshot: &mut BoardSnapshot) {
+        for run in &self.runs {
+            snapshot.cells[run.start..run.start + run.cells.len()].copy_from_slice(&run.cells);
+        }
+    }
+
+    /// Same as `apply_to`, for a game showing the earlier board. Leaves it
+    /// untouched if the delta doesn't fit.
+    pub fn apply(&self, game: &mut Game) -> Result<(), BoardMismatch> {
+        let len = (game.width * game.height) as usize;
+        let outside = |run: &Run| run.start.checked_add(run.cells.len()).is_none_or(|end| end > len);
+        if self.width != game.width || self.runs.iter().any(outside) {
+            return Err(BoardMismatch::of(game));
+        }
+        for (pos, (cell, speed)) in self.changed_cells() {
+            game.board.set(pos.x, pos.y, cell);
+            game.board.set_speed(pos.x, pos.y, speed);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Board;
+    use crate::entity::Direction;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_changes_are_grouped_into_runs() {
+        let mut game = Game::with_seed(20, 10, 1, 3);
+        let before = BoardSnapshot::capture(&game);
+
+        // Three trail cells in a row, one run
+        for _ in 0..3 {
+            game.update();
+        }
+        let drawn = BoardSnapshot::capture(&game);
+        let delta = before.diff(&drawn);
+        assert_eq!(delta.runs().len(), 1);
+        assert_eq!(delta.changed_cells().count(), 3);
+        assert!(delta.changed_cells().all(|(pos, (cell, _))| cell == Cell::Trail && pos.y == 5));
+
+        // Back to the edge captures a block, one run per row
+        game.set_direction(Direction::Up);
+        for _ in 0..6 {
+            game.update();
+        }
+        let captured = BoardSnapshot::capture(&game);
+        let delta = drawn.diff(&captured);
+        assert!(delta.runs().len() <= game.height as usize);
+
+        let mut copy = drawn.clone();
+        delta.apply_to(&mut copy);
+        assert_eq!(copy, captured);
+        assert!(captured.diff(&captured).is_empty());
+    }
+
+    #[test]
+    fn test_rows_must_be_equal_length() {
+        let row = vec![(Cell::Empty, DrawSpeed::Fast); 3];
+        assert!(BoardSnapshot::from_rows(vec![row.clone(), row[..2].to_vec()]).is_none());
+        let board = BoardSnapshot::from_rows(vec![row.clone(), row]).unwrap();
+        assert_eq!((board.width, board.height), (3, 2));
+    }
+
+    fn cell_state_strategy() -> impl Strategy<Value = CellState> {
+        (0..4u8).prop_map(|n| match n {
+            0 => (Cell::Empty, DrawSpeed::Fast),
+            1 => (Cell::Filled, DrawSpeed::Fast),
+            2 => (Cell::Filled, DrawSpeed::Slow),
+            _ => (Cell::Trail, DrawSpeed::Fast),
+        })
+    }
+
+    /// Two boards of the same size
+    fn board_pair_strategy() -> impl Strategy<Value = (BoardSnapshot, BoardSnapshot)> {
+        (1..12i32, 1..12i32).prop_flat_map(|(width, height)| {
+            let size = (width * height) as usize;
+            let board = move |cells| BoardSnapshot { width, height, cells };
+            (
+                prop::collection::vec(cell_state_strategy(), size).prop_map(board),
+                prop::collection::vec(cell_state_strategy(), size).prop_map(board),
+            )
+        })
+    }
+
+    #[test]
+    fn test_updates_for_another_board_are_refused() {
+        let mut game = Game::with_seed(20, 10, 1, 3);
+        let before = BoardSnapshot::capture(&game);
+        let mismatch = Err(BoardMismatch { width: 20, height: 10 });
+
+        let bigger = BoardSnapshot::capture(&Game::with_seed(30, 10, 1, 3));
+        assert_eq!(bigger.apply(&mut game), mismatch);
+
+        let trail = vec![(Cell::Trail, DrawSpeed::Fast)];
+        let narrower = BoardDelta::from_runs(10, vec![Run { start: 0, cells: trail.clone() }]);
+        assert_eq!(narrower.apply(&mut game), mismatch);
+        let past_the_end = BoardDelta::from_runs(20, vec![Run { start: 200, cells: trail.clone() }]);
+        assert_eq!(past_the_end.apply(&mut game), mismatch);
+        // The end of the run would wrap around
+        let wrapping = BoardDelta::from_runs(20, vec![Run { start: usize::MAX, cells: trail }]);
+        assert_eq!(wrapping.apply(&mut game), mismatch);
+
+        assert_eq!(BoardSnapshot::capture(&game), before);
+    }
+
+    proptest! {
+        /// Applying the diff from a to b turns a into b
+        #[test]
+        fn prop_apply_diff_restores_target((a, b) in board_pair_strategy()) {
+            let delta = a.diff(&b);
+            let mut patched = a.clone();
+            delta.apply_to(&mut patched);
+            prop_assert_eq!(&patched, &b);
+
+            // And the same through a game
+            // Tiny boards leave no room to spawn balls, so resize afterwards
+            let mut game = Game::with_seed(20, 10, 1, 0);
+            game.board = Board::new(a.width, a.height);
+            (game.width, game.height) = (a.width, a.height);
+            a.apply(&mut game).unwrap();
+            delta.apply(&mut game).unwrap();
+            prop_assert_eq!(BoardSnapshot::capture(&game), b);
+        }
+
+        /// A delta only lists cells that differ, and runs never touch
+        #[test]
+        fn prop_diff_is_minimal((a, b) in board_pair_strategy()) {
+            let delta = a.diff(&b);
+            for (pos, state) in delta.changed_cells() {
+                prop_assert_ne!(a.cell(pos.x, pos.y), state);
+            }
+            for pair in delta.runs().windows(2) {
+                prop_assert!(pair[0].start + pair[0].cells.len() < pair[1].start);
+            }
+        }
+    }
+}
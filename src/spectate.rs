@@ -0,0 +1,203 @@
+//! Read-only spectators for a game running in another process.
+//!
+//! The playing process owns a `Publisher` and hands it the game after every
+//! tick. A spectator that connects gets `Watch` and a full `Snapshot`, then
+//! one `Delta` per tick with just the cells that changed. Spectators never
+//! send anything back.
+
+use crate::game::Game;
+use crate::protocol::{GameSetup, Message, StateDelta, StateSnapshot, TcpTransport, Transport};
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+
+pub struct Publisher {
+    listener: TcpListener,
+    spectators: Vec<TcpTransport>,
+    /// What every connected spectator is currently showing
+    last: Option<(GameSetup, StateSnapshot)>,
+}
+
+impl Publisher {
+    pub fn bind(addr: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            spectators: Vec::new(),
+            last: None,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn spectator_count(&self) -> usize {
+        self.spectators.len()
+    }
+
+    /// Accept new spectators and bring everyone up to date with `game`.
+    /// Spectators that went away or fell too far behind are dropped; nothing
+    /// a spectator does can stall or end the game being published.
+    pub fn publish(&mut self, game: &Game) {
+        let setup = GameSetup::describe(game);
+        let snapshot = StateSnapshot::capture(game);
+
+        // A new level or a restart can change the board size; start everyone over
+        let update = match &self.last {
+            Some((last_setup, last)) if *last_setup == setup => Some(Message::Delta(StateDelta::between(last, &snapshot))),
+            _ => None,
+        };
+        let watch = Message::Watch { setup: setup.clone() };
+        let full = Message::Snapshot(snapshot.clone());
+
+        let send = |spectator: &mut TcpTransport, message: &Option<Message>| match message {
+            Some(delta) => spectator.send(delta),
+            None => spectator.send(&watch).and_then(|_| spectator.send(&full)),
+        };
+        self.spectators.retain_mut(|spectator| send(spectator, &update).is_ok());
+
+        // Late joiners start from the full snapshot. A failed accept only
+        // loses that spectator; try the rest again next tick.
+        while let Ok((stream, _)) = self.listener.accept() {
+            let Ok(mut spectator) = TcpTransport::new(stream) else {
+                continue;
+            };
+            if send(&mut spectator, &None).is_ok() {
+                self.spectators.push(spectator);
+            }
+        }
+
+        self.last = Some((setup, snapshot));
+    }
+}
+
+/// The watching end: mirrors the published game
+pub struct Spectator {
+    transport: TcpTransport,
+    /// None until the first `Watch` and `Snapshot` arrive
+    pub game: Option<Game>,
+}
+
+impl Spectator {
+    pub fn connect(addr: &str) -> io::Result<Self> {
+        Ok(Self {
+            transport: TcpTransport::connect(addr)?,
+            game: None,
+        })
+    }
+
+    /// Apply everything the publisher has sent so far
+    pub fn pump(&mut self) -> io::Result<()> {
+        while let Some(message) = self.transport.try_recv()? {
+            match message {
+                Message::Watch { setup } => self.game = Some(setup.build()),
+                // An update that doesn't fit means we've lost track of the game
+                Message::Snapshot(snapshot) => {
+                    if let Some(game) = &mut self.game {
+                        snapshot.apply(game).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                    }
+                }
+                Message::Delta(delta) => {
+                    if let Some(game) = &mut self.game {
+                        delta.apply(game).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::Direction;
+    use std::time::Duration;
+
+    /// Publish until `spectator` has caught up with `game`
+    fn catch_up(publisher: &mut Publisher, spectator: &mut Spectator, game: &Game) {
+        for _ in 0..1000 {
+            publisher.publish(game);
+            std::thread::sleep(Duration::from_millis(1));
+            spectator.pump().unwrap();
+            if spectator
+                .game
+                .as_ref()
+                .is_some_and(|mirror| StateSnapshot::capture(mirror) == StateSnapshot::capture(game))
+            {
+                return;
+            }
+        }
+        panic!("spectator never caught up");
+    }
+
+    #[test]
+    fn test_spectators_follow_the_game() {
+        let mut publisher = Publisher::bind("127.0.0.1:0").unwrap();
+        let addr = publisher.local_addr().unwrap().to_string();
+        let mut game = Game::with_seed(30, 20, 2, 5);
+
+        let mut early = Spectator::connect(&addr).unwrap();
+        catch_up(&mut publisher, &mut early, &game);
+
+        game.set_player_direction(1, Direction::Down);
+        for _ in 0..5 {
+            game.update();
+            publisher.publish(&game);
+        }
+
+        // Joins mid-game and starts from a full snapshot
+        let mut late = Spectator::connect(&addr).unwrap();
+        catch_up(&mut publisher, &mut late, &game);
+        catch_up(&mut publisher, &mut early, &game);
+        assert_eq!(publisher.spectator_count(), 2);
+
+        game.update();
+        publisher.publish(&game);
+        catch_up(&mut publisher, &mut late, &game);
+        let mirror = late.game.as_ref().unwrap();
+        assert_eq!(mirror.board, game.board);
+        assert_eq!(mirror.players[1].position, game.players[1].position);
+    }
+
+    #[test]
+    fn test_gone_spectators_are_dropped() {
+        let mut publisher = Publisher::bind("127.0.0.1:0").unwrap();
+        let addr = publisher.local_addr().unwrap().to_string();
+        let game = Game::with_seed(30, 20, 1, 5);
+
+        let mut spectator = Spectator::connect(&addr).unwrap();
+        catch_up(&mut publisher, &mut spectator, &game);
+        drop(spectator);
+
+        for _ in 0..1000 {
+            publisher.publish(&game);
+            if publisher.spectator_count() == 0 {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        panic!("spectator was never dropped");
+    }
+
+    #[test]
+    fn test_spectators_that_stop_reading_are_dropped() {
+        let mut publisher = Publisher::bind("127.0.0.1:0").unwrap();
+        let _stalled = std::net::TcpStream::connect(publisher.local_addr().unwrap()).unwrap();
+        // Alternating board sizes makes every publish a full snapshot
+        let games = [Game::with_seed(300, 200, 1, 5), Game::with_seed(200, 300, 1, 5)];
+
+        let mut joined = false;
+        for i in 0..1000 {
+            publisher.publish(&games[i % 2]);
+            joined |= publisher.spectator_count() == 1;
+            if joined && publisher.spectator_count() == 0 {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        panic!("stalled spectator was never dropped");
+    }
+}
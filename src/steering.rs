@@ -0,0 +1,161 @@
+//! Heading a player toward a chosen cell, for mouse control.
+//!
+//! A click only names a cell; `Steering` turns it into one `Direction` per
+//! tick, the way a player at the keys would, so the game itself never knows
+//! about targets.
+
+use crate::entity::{Direction, MovementMode, Position};
+use crate::game::{Cell, Game, GameState};
+
+/// The way for player `idx` to head next to get closer to `target`: along
+/// the axis with further to go, or diagonally in eight-way movement while
+/// both are off. Turns the game won't take (reversing while drawing) and
+/// steps onto a trail are skipped for the next best. None if there's
+/// nowhere to go.
+pub fn direction_toward(game: &Game, idx: usize, target: Position) -> Option<Direction> {
+    let player = game.players.get(idx)?;
+    let (dx, dy) = (target.x - player.position.x, target.y - player.position.y);
+    let horizontal = match dx.signum() {
+        1 => Some(Direction::Right),
+        -1 => Some(Direction::Left),
+        _ => None,
+    };
+    let vertical = match dy.signum() {
+        1 => Some(Direction::Down),
+        -1 => Some(Direction::Up),
+        _ => None,
+    };
+    let diagonal = match (game.movement, dx.signum(), dy.signum()) {
+        (MovementMode::EightWay, 1, 1) => Some(Direction::DownRight),
+        (MovementMode::EightWay, 1, -1) => Some(Direction::UpRight),
+        (MovementMode::EightWay, -1, 1) => Some(Direction::DownLeft),
+        (MovementMode::EightWay, -1, -1) => Some(Direction::UpLeft),
+        _ => None,
+    };
+    let (first, second) = if dx.abs() >= dy.abs() {
+        (horizontal, vertical)
+    } else {
+        (vertical, horizontal)
+    };
+
+    [diagonal, first, second].into_iter().flatten().find(|&direction| {
+        let next = player.position.moved(direction);
+        let reverses = player.is_drawing && direction == player.direction.opposite();
+        !reverses && game.cell_at(next.x, next.y) != Cell::Trail
+    })
+}
+
+/// A cell the first player is being steered toward
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Steering {
+    target: Option<Position>,
+}
+
+impl Steering {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Head for `target` from the next tick, or stop steering with None
+    pub fn set_target(&mut self, target: Option<Position>) {
+        self.target = target;
+    }
+
+    pub fn target(&self) -> Option<Position> {
+        self.target
+    }
+
+    /// Turn the first player toward the target; call before each tick.
+    /// Steering stops on arrival, holding there if it's filled territory,
+    /// and waits while the game is paused.
+    pub fn steer(&mut self, game: &mut Game) {
+        let Some(target) = self.target else {
+            return;
+        };
+        if matches!(game.state, GameState::Won | GameState::Lost) {
+            self.target = None;
+            return;
+        }
+        if game.state != GameState::Playing {
+            return;
+        }
+        let target = Position::new(target.x.clamp(0, game.width - 1), target.y.clamp(0, game.height - 1));
+        let Some(player) = game.players.first() else {
+            return;
+        };
+        if player.position == target {
+            self.target = None;
+            if !player.holding && game.cell_at(target.x, target.y) == Cell::Filled {
+                game.toggle_hold();
+            }
+            return;
+        }
+        if let Some(direction) = direction_toward(game, 0, target) {
+            game.set_direction(direction);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heads_along_the_longer_axis() {
+        let game = Game::with_seed(30, 20, 1, 1);
+        // The player starts at (0, 10)
+        assert_eq!(direction_toward(&game, 0, Position::new(20, 12)), Some(Direction::Right));
+        assert_eq!(direction_toward(&game, 0, Position::new(2, 0)), Some(Direction::Up));
+        assert_eq!(direction_toward(&game, 0, Position::new(0, 10)), None);
+
+        let mut game = Game::with_seed(30, 20, 1, 1);
+        game.set_movement_mode(MovementMode::EightWay);
+        assert_eq!(direction_toward(&game, 0, Position::new(20, 12)), Some(Direction::DownRight));
+    }
+
+    #[test]
+    fn test_never_turns_onto_the_trail() {
+        let mut game = Game::with_seed(30, 20, 1, 1);
+        game.balls.clear();
+        game.set_direction(Direction::Right);
+        for _ in 0..4 {
+            game.update();
+        }
+        game.set_direction(Direction::Down);
+        game.update();
+        // At (4, 11) with the trail straight up, so up-and-left goes left first
+        assert_eq!(game.players[0].position, Position::new(4, 11));
+        assert_eq!(direction_toward(&game, 0, Position::new(2, 5)), Some(Direction::Left));
+        assert_eq!(direction_toward(&game, 0, Position::new(4, 5)), None);
+    }
+
+    #[test]
+    fn test_steering_stops_and_holds_on_arrival() {
+        let mut game = Game::with_seed(30, 20, 1, 1);
+        game.balls.clear();
+        let mut steering = Steering::new();
+        steering.set_target(Some(Position::new(0, 14)));
+        for _ in 0..10 {
+            steering.steer(&mut game);
+            game.update();
+        }
+        assert_eq!(game.players[0].position, Position::new(0, 14));
+        assert!(game.players[0].holding);
+        assert_eq!(steering.target(), None);
+    }
+
+    #[test]
+    fn test_steering_waits_while_paused() {
+        let mut game = Game::with_seed(30, 20, 1, 1);
+        let mut steering = Steering::new();
+        steering.set_target(Some(Position::new(0, 14)));
+        game.pause();
+        steering.steer(&mut game);
+        assert_eq!(game.players[0].direction, Direction::Right);
+        assert_eq!(steering.target(), Some(Position::new(0, 14)));
+
+        game.resume();
+        steering.steer(&mut game);
+        assert_eq!(game.players[0].direction, Direction::Down);
+    }
+}
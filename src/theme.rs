@@ -0,0 +1,302 @@
+//! How the terminal renderer draws each part of the board.
+//!
+//! A theme is a palette (which colors, if any) plus a glyph set (which
+//! characters). The default draws cells as colored blanks, which says
+//! nothing without color, so a theme without a palette always uses
+//! characters that tell the cells apart on their own.
+
+use crate::entity::DrawSpeed;
+use crate::game::Cell;
+use crossterm::style::{self, Color};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Palette {
+    #[default]
+    Standard,
+    /// Okabe-Ito colors, told apart with any common form of color blindness
+    Colorblind,
+    /// No color at all; only the glyphs tell cells apart
+    Mono,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Glyphs {
+    /// Cells are blanks in their color; `@@` players and `()` balls
+    #[default]
+    Plain,
+    /// `#` filled, `%` slow-drawn, `+` trail, `o` ball, `1`-`4` players
+    Ascii,
+    /// Block characters, one terminal column per cell instead of two
+    Unicode,
+}
+
+/// Colors for each part of the screen
+struct Colors {
+    players: [Color; 4],
+    ball: Color,
+    empty: Color,
+    filled: Color,
+    /// Territory captured in slow draw stands out from fast captures
+    filled_slow: Color,
+    trail: Color,
+    /// Status lines and warnings
+    highlight: Color,
+    won: Color,
+    lost: Color,
+    menu: Color,
+}
+
+const STANDARD: Colors = Colors {
+    players: [Color::Green, Color::Cyan, Color::Magenta, Color::White],
+    ball: Color::Red,
+    empty: Color::Black,
+    filled: Color::Blue,
+    filled_slow: Color::DarkMagenta,
+    trail: Color::Yellow,
+    highlight: Color::Yellow,
+    won: Color::Green,
+    lost: Color::Red,
+    menu: Color::White,
+};
+
+const COLORBLIND: Colors = Colors {
+    players: [
+        Color::White,
+        Color::Rgb { r: 230, g: 159, b: 0 },
+        Color::Rgb { r: 204, g: 121, b: 167 },
+        Color::Rgb { r: 0, g: 158, b: 115 },
+    ],
+    ball: Color::Rgb { r: 213, g: 94, b: 0 },
+    empty: Color::Black,
+    filled: Color::Rgb { r: 0, g: 114, b: 178 },
+    filled_slow: Color::Rgb { r: 86, g: 180, b: 233 },
+    trail: Color::Rgb { r: 240, g: 228, b: 66 },
+    highlight: Color::Rgb { r: 240, g: 228, b: 66 },
+    won: Color::Rgb { r: 0, g: 158, b: 115 },
+    lost: Color::Rgb { r: 213, g: 94, b: 0 },
+    menu: Color::White,
+};
+
+/// Colors and text for one drawn cell; no color leaves the terminal's own
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Style {
+    pub background: Option<Color>,
+    pub foreground: Option<Color>,
+    pub text: &'static str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Theme {
+    palette: Palette,
+    glyphs: Glyphs,
+}
+
+impl Theme {
+    /// `glyphs` in `palette`. Plain glyphs need color, so without a palette
+    /// they become ASCII.
+    pub fn new(palette: Palette, glyphs: Glyphs) -> Self {
+        let glyphs = match (palette, glyphs) {
+            (Palette::Mono, Glyphs::Plain) => Glyphs::Ascii,
+            (_, glyphs) => glyphs,
+        };
+        Self { palette, glyphs }
+    }
+
+    /// A theme by its command-line name
+    pub fn by_name(name: &str) -> Option<Self> {
+        let (palette, glyphs) = match name {
+            "default" => (Palette::Standard, Glyphs::Plain),
+            "colorblind" => (Palette::Colorblind, Glyphs::Plain),
+            "ascii" => (Palette::Standard, Glyphs::Ascii),
+            "unicode" => (Palette::Standard, Glyphs::Unicode),
+            "mono" => (Palette::Mono, Glyphs::Ascii),
+            _ => return None,
+        };
+        Some(Self::new(palette, glyphs))
+    }
+
+    /// The default theme, or plain ASCII when `NO_COLOR` is set or the
+    /// terminal reports fewer than 8 colors (e.g. `TERM=dumb`)
+    pub fn detect() -> Self {
+        Self::detect_from(
+            std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()),
+            std::env::var("TERM").is_ok_and(|term| term == "dumb"),
+            style::available_color_count(),
+        )
+    }
+
+    fn detect_from(no_color: bool, dumb: bool, colors: u16) -> Self {
+        if no_color || dumb || colors < 8 {
+            Self::new(Palette::Mono, Glyphs::Ascii)
+        } else {
+            Self::default()
+        }
+    }
+
+    pub fn palette(&self) -> Palette {
+        self.palette
+    }
+
+    pub fn glyphs(&self) -> Glyphs {
+        self.glyphs
+    }
+
+    /// Terminal columns per cell at full size
+    pub fn cell_width(&self) -> u16 {
+        match self.glyphs {
+            Glyphs::Unicode => 1,
+            Glyphs::Plain | Glyphs::Ascii => 2,
+        }
+    }
+
+    fn colors(&self) -> Option<&'static Colors> {
+        match self.palette {
+            Palette::Standard => Some(&STANDARD),
+            Palette::Colorblind => Some(&COLORBLIND),
+            Palette::Mono => None,
+        }
+    }
+
+    /// Player `idx`'s color, for their score
+    pub fn player_color(&self, idx: usize) -> Option<Color> {
+        self.colors().map(|colors| colors.players[idx])
+    }
+
+    pub fn highlight(&self) -> Option<Color> {
+        self.colors().map(|colors| colors.highlight)
+    }
+
+    pub fn won(&self) -> Option<Color> {
+        self.colors().map(|colors| colors.won)
+    }
+
+    pub fn lost(&self) -> Option<Color> {
+        self.colors().map(|colors| colors.lost)
+    }
+
+    /// Background of the pause menu, drawn in black on it
+    pub fn menu(&self) -> Option<Color> {
+        self.colors().map(|colors| colors.menu)
+    }
+
+    pub fn ball_color(&self) -> Option<Color> {
+        self.colors().map(|colors| colors.ball)
+    }
+
+    /// The color `cell` is drawn in
+    pub fn cell_color(&self, cell: Cell, speed: DrawSpeed) -> Option<Color> {
+        let colors = self.colors()?;
+        Some(match (cell, speed) {
+            (Cell::Empty, _) => colors.empty,
+            (Cell::Filled, DrawSpeed::Fast) => colors.filled,
+            (Cell::Filled, DrawSpeed::Slow) => colors.filled_slow,
+            (Cell::Trail, _) => colors.trail,
+        })
+    }
+
+    /// Player `idx` in a cell `wide` two columns or one
+    pub fn player(&self, idx: usize, wide: bool) -> Style {
+        const PLAIN: [&str; 2] = ["@", "@@"];
+        const NUMBERED: [[&str; 2]; 4] = [["1", "@1"], ["2", "@2"], ["3", "@3"], ["4", "@4"]];
+        let text = match self.glyphs {
+            Glyphs::Plain => PLAIN[wide as usize],
+            Glyphs::Ascii | Glyphs::Unicode => NUMBERED[idx][wide as usize],
+        };
+        Style {
+            background: self.player_color(idx),
+            foreground: self.colors().map(|_| Color::Black),
+            text,
+        }
+    }
+
+    pub fn ball(&self, wide: bool) -> Style {
+        let text = match (self.glyphs, wide) {
+            (_, true) => "()",
+            (Glyphs::Unicode, false) => "●",
+            (Glyphs::Plain | Glyphs::Ascii, false) => "o",
+        };
+        Style {
+            background: self.colors().map(|colors| colors.empty),
+            foreground: self.ball_color(),
+            text,
+        }
+    }
+
+    pub fn cell(&self, cell: Cell, speed: DrawSpeed, wide: bool) -> Style {
+        let (Some(colors), Some(color)) = (self.colors(), self.cell_color(cell, speed)) else {
+            return Style {
+                background: None,
+                foreground: None,
+                text: self.cell_text(cell, speed, wide),
+            };
+        };
+        match self.glyphs {
+            Glyphs::Plain => Style {
+                background: Some(color),
+                foreground: None,
+                text: if wide { "  " } else { " " },
+            },
+            // Colored characters on the empty color, so the shapes still show
+            Glyphs::Ascii | Glyphs::Unicode => Style {
+                background: Some(colors.empty),
+                foreground: Some(color),
+                text: self.cell_text(cell, speed, wide),
+            },
+        }
+    }
+
+    fn cell_text(&self, cell: Cell, speed: DrawSpeed, wide: bool) -> &'static str {
+        let (narrow, double) = match (self.glyphs, cell, speed) {
+            (_, Cell::Empty, _) => (" ", "  "),
+            (Glyphs::Plain | Glyphs::Ascii, Cell::Filled, DrawSpeed::Fast) => ("#", "##"),
+            (Glyphs::Plain | Glyphs::Ascii, Cell::Filled, DrawSpeed::Slow) => ("%", "%%"),
+            (Glyphs::Plain | Glyphs::Ascii, Cell::Trail, _) => ("+", "++"),
+            (Glyphs::Unicode, Cell::Filled, DrawSpeed::Fast) => ("█", "██"),
+            (Glyphs::Unicode, Cell::Filled, DrawSpeed::Slow) => ("▓", "▓▓"),
+            (Glyphs::Unicode, Cell::Trail, _) => ("░", "░░"),
+        };
+        if wide {
+            double
+        } else {
+            narrow
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_color_falls_back_to_ascii() {
+        let mono = Theme::new(Palette::Mono, Glyphs::Ascii);
+        assert_eq!(Theme::detect_from(true, false, 256), mono);
+        assert_eq!(Theme::detect_from(false, true, 8), mono);
+        assert_eq!(Theme::detect_from(false, false, 2), mono);
+        assert_eq!(Theme::detect_from(false, false, 256), Theme::default());
+        assert_eq!(Theme::new(Palette::Mono, Glyphs::Plain), mono);
+    }
+
+    #[test]
+    fn test_cells_differ_without_color() {
+        for name in ["mono", "ascii", "unicode"] {
+            let theme = Theme::by_name(name).unwrap();
+            let wide = theme.cell_width() == 2;
+            let mut texts = vec![
+                theme.player(0, wide).text,
+                theme.player(1, wide).text,
+                theme.ball(wide).text,
+                theme.cell(Cell::Empty, DrawSpeed::Fast, wide).text,
+                theme.cell(Cell::Filled, DrawSpeed::Fast, wide).text,
+                theme.cell(Cell::Filled, DrawSpeed::Slow, wide).text,
+                theme.cell(Cell::Trail, DrawSpeed::Fast, wide).text,
+            ];
+            let count = texts.len();
+            texts.sort();
+            texts.dedup();
+            assert_eq!(texts.len(), count, "{} repeats a glyph", name);
+        }
+        assert!(Theme::by_name("mono").unwrap().player_color(0).is_none());
+        assert!(Theme::by_name("plaid").is_none());
+    }
+}
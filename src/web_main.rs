@@ -1,47 +1,209 @@
-use crate::{Game, GameState, Input, Renderer, WebRenderer};
+use crate::capture::capture_rule_by_name;
+use crate::protocol::{Command, Message};
+use crate::{Clock, Game, GameLoop, GameState, Input, MovementMode, PauseMenu, Renderer, TargetMode, WebRenderer};
 use std::cell::RefCell;
+use std::collections::VecDeque;
 use std::rc::Rc;
+use std::time::Duration;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
+use web_sys::{MessageEvent, WebSocket};
 
 const GAME_WIDTH: i32 = 40;
 const GAME_HEIGHT: i32 = 20;
-const GAME_UPDATE_INTERVAL: f64 = 100.0; // 10 Hz game logic
 
-struct GameLoop {
+/// Connection to an `axion-server`; the server runs the game and we mirror it
+struct ServerConnection {
+    socket: WebSocket,
+    incoming: Rc<RefCell<VecDeque<String>>>,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+}
+
+impl ServerConnection {
+    fn open(url: &str) -> Result<Self, JsValue> {
+        let socket = WebSocket::new(url)?;
+        let incoming = Rc::new(RefCell::new(VecDeque::new()));
+
+        let queue = incoming.clone();
+        let on_message = Closure::wrap(Box::new(move |event: MessageEvent| {
+            if let Some(text) = event.data().as_string() {
+                queue.borrow_mut().push_back(text);
+            }
+        }) as Box<dyn FnMut(MessageEvent)>);
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+        Ok(Self {
+            socket,
+            incoming,
+            _on_message: on_message,
+        })
+    }
+
+    fn send(&self, message: &Message) {
+        // Inputs sent before the socket opens are dropped, like keys pressed while loading
+        if self.socket.ready_state() == WebSocket::OPEN {
+            let _ = self.socket.send_with_str(&message.to_string());
+        }
+    }
+
+    fn next_message(&self) -> Option<Message> {
+        let text = self.incoming.borrow_mut().pop_front()?;
+        match Message::parse(&text) {
+            Ok(message) => Some(message),
+            Err(e) => {
+                web_sys::console::error_1(&e.to_string().into());
+                None
+            }
+        }
+    }
+}
+
+/// Browser time from `performance.now()`
+struct PerformanceClock(web_sys::Performance);
+
+impl Clock for PerformanceClock {
+    fn now(&self) -> Duration {
+        Duration::from_secs_f64(self.0.now() / 1000.0)
+    }
+}
+
+struct WebGame {
     game: Game,
     renderer: WebRenderer,
-    last_update: f64,
+    game_loop: GameLoop<PerformanceClock>,
+    menu: PauseMenu,
+    /// Set when playing on a server (`?server=ws://host:port`)
+    server: Option<ServerConnection>,
 }
 
-impl GameLoop {
+impl WebGame {
     fn new() -> Result<Self, JsValue> {
-        let game = Game::new(GAME_WIDTH, GAME_HEIGHT);
+        // Game options come from the page URL, e.g. ?rule=qix&moves=8&players=2
+        let players = query_param("players")
+            .and_then(|count| count.parse().ok())
+            .unwrap_or(1);
+        let mut game = Game::with_players(GAME_WIDTH, GAME_HEIGHT, players);
+
+        if let Some(rule) = query_param("rule").and_then(|name| capture_rule_by_name(&name)) {
+            game.set_capture_rule(rule);
+        }
+        if query_param("moves").as_deref() == Some("8") {
+            game.set_movement_mode(MovementMode::EightWay);
+        }
+        if query_param("target").as_deref() == Some("competitive") {
+            game.set_target_mode(TargetMode::Competitive);
+        }
+
+        // With ?server=..., this game is only a mirror until the server's WELCOME arrives
+        let server = match query_param("server") {
+            Some(url) => {
+                let room = query_param("room").unwrap_or_else(|| "lobby".to_string());
+                // The name comes from the page URL and may hold '&', '#' or spaces
+                let room = String::from(js_sys::encode_uri_component(&room));
+                Some(ServerConnection::open(&format!("{}/?room={}", url.trim_end_matches('/'), room))?)
+            }
+            None => None,
+        };
+
         let mut renderer = WebRenderer::new("gameCanvas")?;
         renderer.init().map_err(|e| JsValue::from_str(&e.to_string()))?;
 
         let window = web_sys::window().ok_or("no window")?;
         let performance = window.performance().ok_or("no performance")?;
-        let last_update = performance.now();
 
         Ok(Self {
             game,
             renderer,
-            last_update,
+            game_loop: GameLoop::new(PerformanceClock(performance)),
+            menu: PauseMenu::new(),
+            server,
         })
     }
 
-    fn update_frame(&mut self, current_time: f64) -> Result<(), JsValue> {
-        // Poll for input
-        if let Some(input) = self
+    /// Forward input to the server and mirror its state
+    fn update_from_server(&mut self) -> Result<(), JsValue> {
+        let Some(server) = &self.server else {
+            return Ok(());
+        };
+
+        let mut commands = Vec::new();
+        while let Some(input) = self
+            .renderer
+            .poll_input()
+            .map_err(|e| JsValue::from_str(&e.to_string()))?
+        {
+            // Taps mean the same as on a local game
+            let input = match (input, self.game.state) {
+                (Input::Tap, GameState::Won) => Input::NextLevel,
+                (Input::Tap, GameState::Lost) => Input::Restart,
+                (Input::Tap, GameState::Playing) => Input::Hold,
+                (input, _) => input,
+            };
+            commands.extend(Command::from_input(input));
+        }
+        if !commands.is_empty() {
+            server.send(&Message::Commands(commands));
+        }
+
+        while let Some(message) = server.next_message() {
+            match message {
+                Message::Welcome { setup, .. } => self.game = setup.build(),
+                Message::Snapshot(snapshot) => {
+                    if let Err(e) = snapshot.apply(&mut self.game) {
+                        web_sys::console::error_1(&format!("Bad snapshot from server: {}", e).into());
+                    }
+                }
+                Message::Delta(delta) => {
+                    if let Err(e) = delta.apply(&mut self.game) {
+                        web_sys::console::error_1(&format!("Bad delta from server: {}", e).into());
+                    }
+                }
+                Message::Refused { reason } => {
+                    web_sys::console::error_1(&format!("Server refused: {}", reason).into());
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn update_frame(&mut self) -> Result<(), JsValue> {
+        if self.server.is_some() {
+            // The renderer interpolates between server snapshots like local ticks
+            self.update_from_server()?;
+            return self
+                .renderer
+                .render(&self.game)
+                .map_err(|e| JsValue::from_str(&e.to_string()));
+        }
+
+        // Drain queued input (several key presses or swipes can land in one frame)
+        while let Some(input) = self
             .renderer
             .poll_input()
             .map_err(|e| JsValue::from_str(&e.to_string()))?
         {
+            // The pause menu gets all input while paused
+            if self.game.state == GameState::Paused {
+                let before = self.renderer.key_bindings().clone();
+                if self.menu.handle(input, &mut self.game, &mut self.renderer.key_bindings_mut()) {
+                    // In web, we can't really quit, just log it
+                    web_sys::console::log_1(&"Game quit".into());
+                }
+                if *self.renderer.key_bindings() != before {
+                    self.renderer.save_key_bindings();
+                }
+                continue;
+            }
+
             match input {
                 Input::Direction(direction) => {
                     self.game.set_direction(direction);
                 }
+                Input::PlayerDirection(idx, direction) => {
+                    self.game.set_player_direction(idx, direction);
+                }
                 Input::Quit => {
                     web_sys::console::log_1(&"Game quit".into());
                     // In web, we can't really quit, just log it
@@ -49,6 +211,12 @@ impl GameLoop {
                 Input::Restart => {
                     self.game.reset();
                 }
+                Input::ToggleSlowDraw => {
+                    self.game.toggle_draw_speed();
+                }
+                Input::Hold => {
+                    self.game.toggle_hold();
+                }
                 Input::NextLevel if self.game.state == GameState::Won => {
                     self.game.next_level();
                 }
@@ -64,18 +232,33 @@ impl GameLoop {
                             self.game.reset();
                         }
                         GameState::Playing => {
-                            // Ignore taps during active gameplay to avoid accidental restarts
+                            // Tap to stop on safe territory (never restarts mid-game)
+                            self.game.toggle_hold();
                         }
+                        GameState::Paused => {}
                     }
                 }
+                // Hiding the tab only pauses; it never resumes
+                Input::Pause | Input::FocusLost => {
+                    self.game.pause();
+                    self.menu = PauseMenu::new();
+                }
                 _ => {}
             }
         }
+        self.renderer
+            .set_menu((self.game.state == GameState::Paused).then_some(self.menu));
+
+        // Time spent paused is never caught up after resuming
+        if self.game.state == GameState::Paused {
+            self.game_loop.pause();
+        } else if self.game_loop.is_paused() {
+            self.game_loop.resume();
+        }
 
-        // Update game logic at fixed rate
-        if current_time - self.last_update >= GAME_UPDATE_INTERVAL {
+        // Update game logic at fixed rate, catching up after a slow frame
+        while self.game_loop.next_tick() {
             self.game.update();
-            self.last_update = current_time;
         }
 
         // Render (renderer manages its own frame rate)
@@ -87,6 +270,12 @@ impl GameLoop {
     }
 }
 
+/// Read a query string parameter from the page URL
+fn query_param(name: &str) -> Option<String> {
+    let search = web_sys::window()?.location().search().ok()?;
+    web_sys::UrlSearchParams::new_with_str(&search).ok()?.get(name)
+}
+
 #[wasm_bindgen]
 pub fn start_game() -> Result<(), JsValue> {
     // Set panic hook for better error messages
@@ -97,7 +286,7 @@ pub fn start_game() -> Result<(), JsValue> {
 
     // Create game loop
     web_sys::console::log_1(&"[WASM] Creating game loop...".into());
-    let game_loop = match GameLoop::new() {
+    let game_loop = match WebGame::new() {
         Ok(gl) => {
             web_sys::console::log_1(&"[WASM] Game loop created successfully!".into());
             Rc::new(RefCell::new(gl))
@@ -111,7 +300,6 @@ pub fn start_game() -> Result<(), JsValue> {
     // Setup requestAnimationFrame loop
     web_sys::console::log_1(&"[WASM] Setting up animation loop...".into());
     let window = web_sys::window().ok_or("no window")?;
-    let performance = window.performance().ok_or("no performance")?;
 
     // Create closure for animation frame
     let f: Rc<RefCell<Option<Closure<dyn FnMut()>>>> = Rc::new(RefCell::new(None));
@@ -119,10 +307,8 @@ pub fn start_game() -> Result<(), JsValue> {
 
     let game_loop_clone = game_loop.clone();
     *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
-        let current_time = performance.now();
-
         // Update game frame
-        if let Err(e) = game_loop_clone.borrow_mut().update_frame(current_time) {
+        if let Err(e) = game_loop_clone.borrow_mut().update_frame() {
             web_sys::console::error_1(&e);
             return; // Stop loop on error
         }
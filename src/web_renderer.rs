@@ -1,8 +1,13 @@
 use crate::ball_trail::BallTrail;
-use crate::entity::{Direction, Position};
-use crate::game::{Cell, Game, GameState};
+use crate::entity::{Direction, DrawSpeed, MovementMode, Position};
+use crate::game::{Cell, Game, GameState, TargetMode};
+use crate::game_loop::TICK_INTERVAL;
+use crate::key_bindings::{Key, KeyBindings};
+use crate::menu::PauseMenu;
 use crate::renderer::{Input, Renderer};
-use std::cell::RefCell;
+use crate::snapshot::BoardSnapshot;
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::VecDeque;
 use std::io;
 use std::rc::Rc;
 use wasm_bindgen::prelude::*;
@@ -11,46 +16,35 @@ use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, HtmlElement, Keyboard
 
 const CELL_SIZE: f64 = 16.0;
 const TARGET_FRAME_TIME: f64 = 16.0; // ~60 FPS
-const GAME_UPDATE_INTERVAL: f64 = 100.0; // Game logic updates at 10 Hz
 const SWIPE_THRESHOLD: f64 = 30.0; // Minimum distance in pixels to register a swipe
+const KEY_BINDINGS_STORAGE_KEY: &str = "axion.keys"; // localStorage entry, in the key_bindings text format
 
 // Colors (retro palette)
 const COLOR_EMPTY: &str = "#000000";
 const COLOR_FILLED: &str = "#0000AA";
+const COLOR_FILLED_SLOW: &str = "#AA00AA";
 const COLOR_TRAIL: &str = "#FFFF55";
-const COLOR_PLAYER: &str = "#55FF55";
+/// One color per player slot
+const COLOR_PLAYERS: [&str; 4] = ["#55FF55", "#55FFFF", "#FF55FF", "#FFFFFF"];
 const COLOR_BALL: &str = "#FF5555";
 const COLOR_UI: &str = "#FFFFFF";
 
 /// Snapshot of game state for interpolation
 #[derive(Clone)]
 struct GameSnapshot {
-    player_pos: Position,
+    player_positions: Vec<Position>,
     ball_positions: Vec<Position>,
-    board_hash: u64, // Simple hash to detect board changes
+    board: BoardSnapshot,
 }
 
 impl GameSnapshot {
-    fn from_game(game: &Game) -> Self {
+    fn from_game(game: &Game, board: BoardSnapshot) -> Self {
         Self {
-            player_pos: game.player.position,
+            player_positions: game.players.iter().map(|p| p.position).collect(),
             ball_positions: game.balls.iter().map(|b| b.position).collect(),
-            board_hash: Self::hash_board(&game.board),
+            board,
         }
     }
-
-    fn hash_board(board: &[Vec<Cell>]) -> u64 {
-        // Simple hash: count filled cells
-        let mut count = 0u64;
-        for row in board {
-            for cell in row {
-                if matches!(cell, Cell::Filled) {
-                    count += 1;
-                }
-            }
-        }
-        count
-    }
 }
 
 // BallTrail moved to ball_trail.rs module for testing
@@ -96,11 +90,19 @@ pub struct WebRenderer {
     ball_trails: Vec<BallTrail>,
     fill_animation: Option<FloodFillAnimation>,
 
-    // Input state
-    pending_input: Rc<RefCell<Option<Input>>>,
+    // Input state (queued so quick key presses and swipes between frames aren't lost)
+    pending_input: Rc<RefCell<VecDeque<Input>>>,
 
     // Touch state
     touch_start_pos: Rc<RefCell<Option<(f64, f64)>>>,
+    eight_way: Rc<RefCell<bool>>, // Swipes resolve to 8 directions when set
+
+    // Key state, shared with the keyboard listener
+    bindings: Rc<RefCell<KeyBindings>>,
+    capture_keys: Rc<RefCell<bool>>, // Send raw keys for the menu to bind when set
+
+    // Drawn over the board while the game is paused
+    menu: Option<PauseMenu>,
 }
 
 impl WebRenderer {
@@ -123,8 +125,9 @@ impl WebRenderer {
         // Get device pixel ratio for high DPI displays
         let device_pixel_ratio = window.device_pixel_ratio();
 
-        let pending_input = Rc::new(RefCell::new(None));
+        let pending_input = Rc::new(RefCell::new(VecDeque::new()));
         let touch_start_pos = Rc::new(RefCell::new(None));
+        let bindings = Rc::new(RefCell::new(stored_key_bindings(&window)));
 
         Ok(Self {
             canvas,
@@ -138,26 +141,55 @@ impl WebRenderer {
             fill_animation: None,
             pending_input,
             touch_start_pos,
+            eight_way: Rc::new(RefCell::new(false)),
+            bindings,
+            capture_keys: Rc::new(RefCell::new(false)),
+            menu: None,
         })
     }
 
+    /// Show `menu` over the board while the game is paused
+    pub fn set_menu(&mut self, menu: Option<PauseMenu>) {
+        self.menu = menu;
+        *self.capture_keys.borrow_mut() = menu.is_some_and(|menu| menu.waiting_for_key());
+    }
+
+    pub fn key_bindings(&self) -> Ref<'_, KeyBindings> {
+        self.bindings.borrow()
+    }
+
+    /// For the pause menu to rebind keys in; `save_key_bindings` keeps them
+    pub fn key_bindings_mut(&self) -> RefMut<'_, KeyBindings> {
+        self.bindings.borrow_mut()
+    }
+
+    /// Keep the current key bindings in localStorage for the next visit
+    pub fn save_key_bindings(&self) {
+        let text = self.bindings.borrow().to_config();
+        if let Ok(Some(storage)) = self.window.local_storage() {
+            if storage.set_item(KEY_BINDINGS_STORAGE_KEY, &text).is_err() {
+                web_sys::console::warn_1(&"Couldn't save key bindings".into());
+            }
+        }
+    }
+
     fn setup_keyboard_listener(&self) {
         let pending_input = self.pending_input.clone();
+        let bindings = self.bindings.clone();
+        let capture_keys = self.capture_keys.clone();
 
         let closure = Closure::wrap(Box::new(move |event: KeyboardEvent| {
-            let input = match event.key().as_str() {
-                "ArrowUp" => Some(Input::Direction(Direction::Up)),
-                "ArrowDown" => Some(Input::Direction(Direction::Down)),
-                "ArrowLeft" => Some(Input::Direction(Direction::Left)),
-                "ArrowRight" => Some(Input::Direction(Direction::Right)),
-                "q" | "Q" => Some(Input::Quit),
-                "r" | "R" => Some(Input::Restart),
-                " " => Some(Input::NextLevel),
-                _ => None,
+            let Some(key) = Key::from_web(&event.key()) else {
+                return;
+            };
+            let input = if *capture_keys.borrow() {
+                Some(Input::Key(key))
+            } else {
+                bindings.borrow().input_for(key)
             };
 
             if let Some(input) = input {
-                *pending_input.borrow_mut() = Some(input);
+                pending_input.borrow_mut().push_back(input);
                 event.prevent_default();
             }
         }) as Box<dyn FnMut(KeyboardEvent)>);
@@ -169,9 +201,30 @@ impl WebRenderer {
         closure.forget(); // Keep listener alive
     }
 
+    /// Pause when the tab is hidden, so the game doesn't carry on unseen
+    fn setup_visibility_listener(&self) {
+        let pending_input = self.pending_input.clone();
+        let Some(document) = self.window.document() else {
+            return;
+        };
+
+        let watched = document.clone();
+        let closure = Closure::wrap(Box::new(move || {
+            if watched.hidden() {
+                pending_input.borrow_mut().push_back(Input::FocusLost);
+            }
+        }) as Box<dyn FnMut()>);
+
+        document
+            .add_event_listener_with_callback("visibilitychange", closure.as_ref().unchecked_ref())
+            .unwrap();
+        closure.forget();
+    }
+
     fn setup_touch_listeners(&self) {
         let pending_input = self.pending_input.clone();
         let touch_start_pos = self.touch_start_pos.clone();
+        let eight_way = self.eight_way.clone();
         let canvas = self.canvas.clone();
 
         // TouchStart: Record initial position
@@ -224,22 +277,7 @@ impl WebRenderer {
 
                     // Determine if swipe was strong enough and which direction
                     let input = if abs_dx > SWIPE_THRESHOLD || abs_dy > SWIPE_THRESHOLD {
-                        // This is a swipe - primary direction is the one with larger delta
-                        if abs_dx > abs_dy {
-                            // Horizontal swipe
-                            if dx > 0.0 {
-                                Some(Input::Direction(Direction::Right))
-                            } else {
-                                Some(Input::Direction(Direction::Left))
-                            }
-                        } else {
-                            // Vertical swipe
-                            if dy > 0.0 {
-                                Some(Input::Direction(Direction::Down))
-                            } else {
-                                Some(Input::Direction(Direction::Up))
-                            }
-                        }
+                        Some(Input::Direction(swipe_direction(dx, dy, *eight_way.borrow())))
                     } else {
                         // This is a tap (movement was below threshold)
                         // Send Tap input - will be handled contextually based on game state
@@ -248,7 +286,7 @@ impl WebRenderer {
 
                     // If we detected a valid input, register it and vibrate
                     if let Some(input) = input {
-                        *pending_input_clone.borrow_mut() = Some(input);
+                        pending_input_clone.borrow_mut().push_back(input);
 
                         // Haptic feedback (vibrate for 50ms)
                         // Try to vibrate - this will fail silently if not supported
@@ -296,32 +334,29 @@ impl WebRenderer {
     fn calculate_interpolation_alpha(&self) -> f64 {
         let now = self.current_time();
         let elapsed = now - self.last_update_time;
-        (elapsed / GAME_UPDATE_INTERVAL).min(1.0)
+        (elapsed / (TICK_INTERVAL.as_secs_f64() * 1000.0)).min(1.0)
     }
 
     fn lerp(a: i32, b: i32, alpha: f64) -> f64 {
         a as f64 + (b - a) as f64 * alpha
     }
 
-    fn detect_board_changes(&self, game: &Game) -> Vec<(i32, i32)> {
-        let mut newly_filled = Vec::new();
-
-        if let Some(prev) = &self.last_game_snapshot {
-            // If board hash changed, scan for new filled cells
-            let current_hash = GameSnapshot::hash_board(&game.board);
-            if current_hash != prev.board_hash {
-                for y in 0..game.height {
-                    for x in 0..game.width {
-                        if game.cell_at(x, y) == Cell::Filled {
-                            // This is a newly filled cell (from trail completion)
-                            newly_filled.push((x, y));
-                        }
-                    }
-                }
-            }
+    fn detect_board_changes(&self, board: &BoardSnapshot) -> Vec<(i32, i32)> {
+        let Some(prev) = &self.last_game_snapshot else {
+            return Vec::new();
+        };
+        // A new level starts over on a different board; nothing to animate
+        if (prev.board.width, prev.board.height) != (board.width, board.height) {
+            return Vec::new();
         }
 
-        newly_filled
+        // Cells that turned filled since the last tick (from trail completion)
+        prev.board
+            .diff(board)
+            .changed_cells()
+            .filter(|(_, (cell, _))| *cell == Cell::Filled)
+            .map(|(pos, _)| (pos.x, pos.y))
+            .collect()
     }
 
     fn draw_cell(&self, x: i32, y: i32, color: &str) {
@@ -351,7 +386,10 @@ impl WebRenderer {
                 let cell = game.cell_at(x, y);
                 let color = match cell {
                     Cell::Empty => COLOR_EMPTY,
-                    Cell::Filled => COLOR_FILLED,
+                    Cell::Filled => match game.capture_speed_at(x, y) {
+                        DrawSpeed::Fast => COLOR_FILLED,
+                        DrawSpeed::Slow => COLOR_FILLED_SLOW,
+                    },
                     Cell::Trail => COLOR_TRAIL,
                 };
                 self.draw_cell(x, y, color);
@@ -403,8 +441,8 @@ impl WebRenderer {
         }
     }
 
-    fn draw_player(&self, x: f64, y: f64) {
-        self.draw_cell_f64(x, y, COLOR_PLAYER);
+    fn draw_player(&self, x: f64, y: f64, idx: usize) {
+        self.draw_cell_f64(x, y, COLOR_PLAYERS[idx]);
 
         // Draw "@" symbol
         self.context.set_fill_style_str("#000000");
@@ -446,33 +484,176 @@ impl WebRenderer {
         self.context.set_text_baseline("top");
 
         let info = format!(
-            "Level: {}  Score: {}  Filled: {:.1}%  Target: {:.0}%",
+            "Level: {}  Score: {}  Filled: {:.1}%  Target: {:.0}%  Draw: {}{}",
             game.level,
             game.score,
             game.filled_percentage * 100.0,
-            game.target_percentage * 100.0
+            game.target_percentage * 100.0,
+            game.players[0].draw_speed.label(),
+            if game.players[0].holding { "  HOLD" } else { "" }
         );
         self.context.fill_text(&info, 5.0, y_offset).unwrap();
 
-        let controls = "Controls: Arrow Keys / Swipe | Tap / R: Restart";
-        self.context.fill_text(controls, 5.0, y_offset + 20.0).unwrap();
+        let mut line_y = y_offset + 20.0;
+
+        // One score per player when sharing the board
+        if game.players.len() > 1 {
+            let mut x = 5.0;
+            for (idx, player) in game.players.iter().enumerate() {
+                let label = format!(
+                    "P{}: {}{}",
+                    idx + 1,
+                    player.score,
+                    if player.alive { "" } else { " (OUT)" }
+                );
+                self.context.set_fill_style_str(COLOR_PLAYERS[idx]);
+                self.context.fill_text(&label, x, line_y).unwrap();
+                x += 130.0;
+            }
+            self.context.set_fill_style_str(COLOR_UI);
+            line_y += 20.0;
+        }
+
+        let keys = self.bindings.borrow();
+        let mut controls = format!("Controls: {} / Swipe", keys.describe_moves(0));
+        if game.players.len() > 1 {
+            controls.push_str(&format!(" | P2: {}", keys.describe_moves(1)));
+        }
+        controls.push_str(&format!(
+            " | Tap / {}: Hold | {}: Slow draw | {}: Restart",
+            keys.describe(Input::Hold),
+            keys.describe(Input::ToggleSlowDraw),
+            keys.describe(Input::Restart)
+        ));
+        self.context.fill_text(&controls, 5.0, line_y).unwrap();
+        line_y += 20.0;
 
         match game.state {
             GameState::Won => {
+                let headline = match (game.target_mode, game.winner()) {
+                    (TargetMode::Competitive, Some(idx)) => format!("PLAYER {} WINS!", idx + 1),
+                    (TargetMode::Competitive, None) => "DRAW!".to_string(),
+                    (TargetMode::Shared, _) => "YOU WIN!".to_string(),
+                };
+                let message = format!(
+                    "{} Tap screen or press {} for next level",
+                    headline,
+                    keys.describe(Input::NextLevel).to_uppercase()
+                );
                 self.context.set_fill_style_str("#55FF55");
-                self.context
-                    .fill_text("YOU WIN! Tap screen or press SPACE for next level", 5.0, y_offset + 40.0)
-                    .unwrap();
+                self.context.fill_text(&message, 5.0, line_y).unwrap();
             }
             GameState::Lost => {
                 self.context.set_fill_style_str("#FF5555");
-                self.context
-                    .fill_text("GAME OVER! Tap screen or press R to restart", 5.0, y_offset + 40.0)
-                    .unwrap();
+                let message = format!("GAME OVER! Tap screen or press {} to restart", keys.describe(Input::Restart));
+                self.context.fill_text(&message, 5.0, line_y).unwrap();
+            }
+            GameState::Paused => {
+                self.context.set_fill_style_str(COLOR_TRAIL);
+                let message = format!(
+                    "PAUSED - Swipe or {}/{} to choose, tap or {} to pick, {} to resume",
+                    keys.describe(Input::Direction(Direction::Up)),
+                    keys.describe(Input::Direction(Direction::Down)),
+                    keys.describe(Input::Confirm),
+                    keys.describe(Input::Pause).replace('/', " / ")
+                );
+                self.context.fill_text(&message, 5.0, line_y).unwrap();
             }
             GameState::Playing => {}
         }
     }
+
+    /// A dimmed board with the menu's title and items centered on it
+    fn draw_menu(&self, menu: &PauseMenu, game: &Game) {
+        let board_width = game.width as f64 * CELL_SIZE;
+        let board_height = game.height as f64 * CELL_SIZE;
+        self.context.set_fill_style_str("rgba(0, 0, 0, 0.7)");
+        self.context.fill_rect(0.0, 0.0, board_width, board_height);
+
+        const LINE_HEIGHT: f64 = 22.0;
+        let center_x = board_width / 2.0;
+        // Title, a gap and the notice take three lines; long pages scroll
+        let visible = menu.visible_items(((board_height / LINE_HEIGHT) as usize).saturating_sub(3));
+        let notice = menu.notice();
+        let lines = visible.len() + 2 + notice.is_some() as usize;
+        let mut y = (board_height - LINE_HEIGHT * lines as f64) / 2.0;
+
+        self.context.set_text_align("center");
+        self.context.set_text_baseline("top");
+        self.context.set_font("bold 18px monospace");
+        self.context.set_fill_style_str(COLOR_UI);
+        self.context.fill_text(menu.title(), center_x, y).unwrap();
+        y += LINE_HEIGHT * 2.0;
+
+        self.context.set_font("16px monospace");
+        let bindings = self.bindings.borrow();
+        for idx in visible {
+            let label = PauseMenu::label(menu.items()[idx], game, &bindings);
+            if idx == menu.selected() {
+                self.context.set_fill_style_str(COLOR_TRAIL);
+                self.context.fill_text(&format!("> {} <", label), center_x, y).unwrap();
+            } else {
+                self.context.set_fill_style_str(COLOR_UI);
+                self.context.fill_text(&label, center_x, y).unwrap();
+            }
+            y += LINE_HEIGHT;
+        }
+        if let Some(notice) = notice {
+            self.context.set_fill_style_str(COLOR_TRAIL);
+            self.context.fill_text(&notice, center_x, y).unwrap();
+        }
+    }
+}
+
+/// Key bindings saved by an earlier visit, or the defaults
+fn stored_key_bindings(window: &web_sys::Window) -> KeyBindings {
+    let Some(text) = window
+        .local_storage()
+        .ok()
+        .flatten()
+        .and_then(|storage| storage.get_item(KEY_BINDINGS_STORAGE_KEY).ok().flatten())
+    else {
+        return KeyBindings::default();
+    };
+    KeyBindings::parse(&text).unwrap_or_else(|e| {
+        web_sys::console::warn_1(&format!("Ignoring saved key bindings:\n{}", e).into());
+        KeyBindings::default()
+    })
+}
+
+/// Resolve a swipe vector (screen coordinates, y down) to a direction
+fn swipe_direction(dx: f64, dy: f64, eight_way: bool) -> Direction {
+    if eight_way {
+        // Eight 45-degree sectors centered on each heading
+        let sector = (dy.atan2(dx) / std::f64::consts::FRAC_PI_4).round() as i32;
+        return match sector.rem_euclid(8) {
+            0 => Direction::Right,
+            1 => Direction::DownRight,
+            2 => Direction::Down,
+            3 => Direction::DownLeft,
+            4 => Direction::Left,
+            5 => Direction::UpLeft,
+            6 => Direction::Up,
+            _ => Direction::UpRight,
+        };
+    }
+
+    // Primary direction is the one with larger delta
+    if dx.abs() > dy.abs() {
+        // Horizontal swipe
+        if dx > 0.0 {
+            Direction::Right
+        } else {
+            Direction::Left
+        }
+    } else {
+        // Vertical swipe
+        if dy > 0.0 {
+            Direction::Down
+        } else {
+            Direction::Up
+        }
+    }
 }
 
 impl Renderer for WebRenderer {
@@ -480,6 +661,7 @@ impl Renderer for WebRenderer {
         // Setup input listeners
         self.setup_keyboard_listener();
         self.setup_touch_listeners();
+        self.setup_visibility_listener();
 
         // Initialize time
         self.last_update_time = self.current_time();
@@ -498,11 +680,13 @@ impl Renderer for WebRenderer {
             return Ok(());
         }
         self.last_render_time = now;
+        *self.eight_way.borrow_mut() = game.movement == MovementMode::EightWay;
 
-        // Check if game updated (board hash changed)
+        // Check if game updated (board changed)
+        let board = BoardSnapshot::capture(game);
         let game_updated = if let Some(prev) = &self.last_game_snapshot {
-            GameSnapshot::hash_board(&game.board) != prev.board_hash ||
-            game.player.position != prev.player_pos
+            board != prev.board ||
+            game.players.iter().map(|p| p.position).ne(prev.player_positions.iter().copied())
         } else {
             true
         };
@@ -510,13 +694,13 @@ impl Renderer for WebRenderer {
         // If game updated, capture snapshot and trigger effects
         if game_updated {
             // Detect newly filled cells for animation
-            let newly_filled = self.detect_board_changes(game);
+            let newly_filled = self.detect_board_changes(&board);
             if !newly_filled.is_empty() && self.fill_animation.is_none() {
                 self.fill_animation = Some(FloodFillAnimation::new(newly_filled, now));
             }
 
             // Update snapshot
-            self.last_game_snapshot = Some(GameSnapshot::from_game(game));
+            self.last_game_snapshot = Some(GameSnapshot::from_game(game, board));
             self.last_update_time = now;
 
             // Initialize ball trails if needed
@@ -528,7 +712,9 @@ impl Renderer for WebRenderer {
         // Set canvas size based on game dimensions
         // Display size (CSS pixels)
         let display_width = (game.width as f64 * CELL_SIZE) as u32;
-        let display_height = (game.height as f64 * CELL_SIZE + 80.0) as u32;
+        // Room below the board for the info lines (plus per-player scores)
+        let ui_height = if game.players.len() > 1 { 100.0 } else { 80.0 };
+        let display_height = (game.height as f64 * CELL_SIZE + ui_height) as u32;
 
         // Internal resolution (actual pixels, scaled for high DPI)
         let pixel_width = (display_width as f64 * self.device_pixel_ratio) as u32;
@@ -599,13 +785,20 @@ impl Renderer for WebRenderer {
             }
         }
 
-        // Draw player at interpolated position
-        if let Some(prev) = &self.last_game_snapshot {
-            let px = Self::lerp(prev.player_pos.x, game.player.position.x, alpha);
-            let py = Self::lerp(prev.player_pos.y, game.player.position.y, alpha);
-            self.draw_player(px, py);
-        } else {
-            self.draw_player(game.player.position.x as f64, game.player.position.y as f64);
+        // Draw players at interpolated positions (knocked-out players aren't drawn)
+        for (idx, player) in game.players.iter().enumerate().filter(|(_, p)| p.alive) {
+            match self.last_game_snapshot.as_ref().and_then(|prev| prev.player_positions.get(idx)) {
+                Some(prev_pos) => {
+                    let px = Self::lerp(prev_pos.x, player.position.x, alpha);
+                    let py = Self::lerp(prev_pos.y, player.position.y, alpha);
+                    self.draw_player(px, py, idx);
+                }
+                None => self.draw_player(player.position.x as f64, player.position.y as f64, idx),
+            }
+        }
+
+        if let (GameState::Paused, Some(menu)) = (game.state, &self.menu) {
+            self.draw_menu(menu, game);
         }
 
         // Draw UI
@@ -620,6 +813,6 @@ impl Renderer for WebRenderer {
     }
 
     fn poll_input(&mut self) -> io::Result<Option<Input>> {
-        Ok(self.pending_input.borrow_mut().take())
+        Ok(self.pending_input.borrow_mut().pop_front())
     }
 }
@@ -0,0 +1,392 @@
+//! Minimal WebSocket (RFC 6455) transport: the opening handshake and
+//! unfragmented text frames, which is all the browser client sends.
+//!
+//! Each text frame carries one protocol `Message`, without the trailing
+//! newline used over raw TCP.
+
+use crate::protocol::{disconnected, Message, Outbox, Transport};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use sha1::{Digest, Sha1};
+use std::collections::VecDeque;
+use std::io::{self, Read};
+use std::net::TcpStream;
+
+/// Appended to the client's key before hashing, per RFC 6455
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Largest frame we accept; real messages are a few kilobytes
+const MAX_PAYLOAD: u64 = 1 << 20;
+
+/// Largest handshake we accept; a browser's is well under a kilobyte
+const MAX_HANDSHAKE: usize = 8 * 1024;
+
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+/// The `Sec-WebSocket-Accept` value for a client's `Sec-WebSocket-Key`
+fn accept_key(key: &str) -> String {
+    BASE64.encode(Sha1::digest(format!("{}{}", key, HANDSHAKE_GUID).as_bytes()))
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+/// Find `name`'s value in an HTTP header block (names are case-insensitive)
+fn header<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+    head.lines().skip(1).find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim().eq_ignore_ascii_case(name).then(|| value.trim())
+    })
+}
+
+enum Phase {
+    /// Server side, waiting for the client's upgrade request
+    AwaitingRequest,
+    /// Client side, waiting for `101 Switching Protocols` with this accept value
+    AwaitingResponse { expected_accept: String },
+    Open,
+}
+
+pub struct WebSocketTransport {
+    stream: TcpStream,
+    outbox: Outbox,
+    phase: Phase,
+    /// Masking is required from clients and forbidden from servers
+    is_client: bool,
+    /// Request path, once the client's handshake has arrived (server side)
+    path: Option<String>,
+    buffer: Vec<u8>,
+    messages: VecDeque<String>,
+    closed: bool,
+}
+
+impl WebSocketTransport {
+    /// Server side of a freshly accepted connection. The handshake completes
+    /// inside `try_recv`, so this never blocks.
+    pub fn accept(stream: TcpStream) -> io::Result<Self> {
+        Self::with_phase(stream, Phase::AwaitingRequest, false)
+    }
+
+    /// Connect to `ws://addr{path}`. The handshake response is read by `try_recv`.
+    pub fn connect(addr: &str, path: &str) -> io::Result<Self> {
+        let mut stream = TcpStream::connect(addr)?;
+        let key = BASE64.encode(rand::random::<[u8; 16]>());
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            path, addr, key
+        );
+        io::Write::write_all(&mut stream, request.as_bytes())?;
+        Self::with_phase(
+            stream,
+            Phase::AwaitingResponse {
+                expected_accept: accept_key(&key),
+            },
+            true,
+        )
+    }
+
+    fn with_phase(stream: TcpStream, phase: Phase, is_client: bool) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        stream.set_nonblocking(true)?;
+        Ok(Self {
+            stream,
+            outbox: Outbox::default(),
+            phase,
+            is_client,
+            path: None,
+            buffer: Vec::new(),
+            messages: VecDeque::new(),
+            closed: false,
+        })
+    }
+
+    pub fn is_open(&self) -> bool {
+        matches!(self.phase, Phase::Open)
+    }
+
+    /// The path the client asked for, e.g. `/?room=team`
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    fn send_frame(&mut self, opcode: u8, payload: &[u8]) -> io::Result<()> {
+        let mut frame = vec![0x80 | opcode];
+        let mask_bit = if self.is_client { 0x80 } else { 0 };
+        match payload.len() {
+            len if len < 126 => frame.push(mask_bit | len as u8),
+            len if len <= u16::MAX as usize => {
+                frame.push(mask_bit | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(mask_bit | 127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+
+        if self.is_client {
+            let mask: [u8; 4] = rand::random();
+            frame.extend_from_slice(&mask);
+            frame.extend(payload.iter().enumerate().map(|(i, byte)| byte ^ mask[i % 4]));
+        } else {
+            frame.extend_from_slice(payload);
+        }
+
+        self.outbox.send(&mut self.stream, &frame)
+    }
+
+    /// Consume the handshake if it has fully arrived. Returns false if more bytes are needed.
+    fn finish_handshake(&mut self) -> io::Result<bool> {
+        let Some(end) = self.buffer.windows(4).position(|w| w == b"\r\n\r\n") else {
+            if self.buffer.len() > MAX_HANDSHAKE {
+                return Err(invalid("WebSocket handshake too large"));
+            }
+            return Ok(false);
+        };
+        if end > MAX_HANDSHAKE {
+            return Err(invalid("WebSocket handshake too large"));
+        }
+        let head = String::from_utf8_lossy(&self.buffer[..end]).to_string();
+        self.buffer.drain(..end + 4);
+
+        match &self.phase {
+            Phase::AwaitingRequest => {
+                let path = head
+                    .lines()
+                    .next()
+                    .and_then(|line| line.strip_prefix("GET "))
+                    .and_then(|rest| rest.split_whitespace().next())
+                    .ok_or_else(|| invalid("expected a GET request"))?;
+                let key = header(&head, "Sec-WebSocket-Key").ok_or_else(|| invalid("missing Sec-WebSocket-Key"))?;
+                let response = format!(
+                    "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                     Sec-WebSocket-Accept: {}\r\n\r\n",
+                    accept_key(key)
+                );
+                self.path = Some(path.to_string());
+                self.outbox.send(&mut self.stream, response.as_bytes())?;
+            }
+            Phase::AwaitingResponse { expected_accept } => {
+                let switching = head.lines().next().is_some_and(|line| line.contains(" 101 "));
+                if !switching || header(&head, "Sec-WebSocket-Accept") != Some(expected_accept.as_str()) {
+                    return Err(invalid("WebSocket handshake rejected"));
+                }
+            }
+            Phase::Open => {}
+        }
+
+        self.phase = Phase::Open;
+        Ok(true)
+    }
+
+    /// Decode one complete frame from the buffer. Returns false if more bytes are needed.
+    fn read_frame(&mut self) -> io::Result<bool> {
+        if self.buffer.len() < 2 {
+            return Ok(false);
+        }
+        let (first, second) = (self.buffer[0], self.buffer[1]);
+        let fin = first & 0x80 != 0;
+        let opcode = first & 0x0F;
+        let masked = second & 0x80 != 0;
+        // RFC 6455 section 5.1: clients always mask, servers never do
+        if masked == self.is_client {
+            return Err(invalid(if self.is_client {
+                "masked frame from server"
+            } else {
+                "unmasked frame from client"
+            }));
+        }
+
+        let mut offset = 2;
+        let len = match second & 0x7F {
+            126 => {
+                if self.buffer.len() < 4 {
+                    return Ok(false);
+                }
+                offset = 4;
+                u16::from_be_bytes([self.buffer[2], self.buffer[3]]) as u64
+            }
+            127 => {
+                if self.buffer.len() < 10 {
+                    return Ok(false);
+                }
+                offset = 10;
+                u64::from_be_bytes(self.buffer[2..10].try_into().unwrap())
+            }
+            len => len as u64,
+        };
+        if len > MAX_PAYLOAD {
+            return Err(invalid("WebSocket frame too large"));
+        }
+
+        let mask_len = if masked { 4 } else { 0 };
+        let total = offset + mask_len + len as usize;
+        if self.buffer.len() < total {
+            return Ok(false);
+        }
+
+        let mask: Vec<u8> = self.buffer[offset..offset + mask_len].to_vec();
+        let mut payload: Vec<u8> = self.buffer.drain(..total).skip(offset + mask_len).collect();
+        if masked {
+            for (i, byte) in payload.iter_mut().enumerate() {
+                *byte ^= mask[i % 4];
+            }
+        }
+
+        match opcode {
+            OPCODE_TEXT if fin => {
+                let text = String::from_utf8(payload).map_err(|_| invalid("WebSocket text is not UTF-8"))?;
+                self.messages.push_back(text);
+            }
+            OPCODE_PING => self.send_frame(OPCODE_PONG, &payload)?,
+            OPCODE_PONG => {}
+            OPCODE_CLOSE => {
+                let _ = self.send_frame(OPCODE_CLOSE, &payload);
+                self.closed = true;
+            }
+            _ => return Err(invalid("unsupported WebSocket frame")),
+        }
+        Ok(true)
+    }
+}
+
+impl Transport for WebSocketTransport {
+    fn send(&mut self, message: &Message) -> io::Result<()> {
+        if !self.is_open() {
+            return Err(invalid("WebSocket handshake not finished"));
+        }
+        self.send_frame(OPCODE_TEXT, message.to_string().as_bytes())
+    }
+
+    fn try_recv(&mut self) -> io::Result<Option<Message>> {
+        self.outbox.flush(&mut self.stream)?;
+        let mut chunk = [0u8; 4096];
+        while !self.closed {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => self.closed = true,
+                Ok(n) => self.buffer.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+            // Don't keep reading a handshake that can't be valid
+            if !self.is_open() && self.buffer.len() > MAX_HANDSHAKE {
+                break;
+            }
+        }
+
+        if !self.is_open() {
+            self.finish_handshake()?;
+        }
+        if self.is_open() {
+            while self.read_frame()? {}
+        }
+
+        match self.messages.pop_front() {
+            Some(text) => Message::parse(&text).map(Some),
+            None if self.closed => Err(disconnected()),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::Command;
+    use crate::entity::Direction;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_accept_key() {
+        // Example from RFC 6455 section 1.3
+        assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    /// A server-side transport for whatever raw bytes the test writes
+    fn accept_raw() -> (TcpStream, WebSocketTransport) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let raw = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let server = WebSocketTransport::accept(listener.accept().unwrap().0).unwrap();
+        (raw, server)
+    }
+
+    /// Poll `server` until it fails
+    fn error_from(server: &mut WebSocketTransport) -> io::Error {
+        for _ in 0..1000 {
+            if let Err(e) = server.try_recv() {
+                return e;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        panic!("no error from server");
+    }
+
+    #[test]
+    fn test_oversized_handshakes_are_refused() {
+        let (mut raw, mut server) = accept_raw();
+        io::Write::write_all(&mut raw, b"GET / HTTP/1.1\r\n").unwrap();
+        io::Write::write_all(&mut raw, format!("X-Padding: {}\r\n", "x".repeat(MAX_HANDSHAKE)).as_bytes()).unwrap();
+        assert_eq!(error_from(&mut server).to_string(), "WebSocket handshake too large");
+    }
+
+    #[test]
+    fn test_unmasked_client_frames_are_refused() {
+        let (mut raw, mut server) = accept_raw();
+        io::Write::write_all(
+            &mut raw,
+            b"GET / HTTP/1.1\r\nUpgrade: websocket\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n",
+        )
+        .unwrap();
+        while !server.is_open() {
+            assert!(server.try_recv().unwrap().is_none());
+        }
+        let text = b"CMD U";
+        let mut frame = vec![0x80 | OPCODE_TEXT, text.len() as u8];
+        frame.extend_from_slice(text);
+        io::Write::write_all(&mut raw, &frame).unwrap();
+        assert_eq!(error_from(&mut server).to_string(), "unmasked frame from client");
+    }
+
+    #[test]
+    fn test_handshake_and_messages_both_ways() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let mut client = WebSocketTransport::connect(&addr, "/?room=test").unwrap();
+        let mut server = WebSocketTransport::accept(listener.accept().unwrap().0).unwrap();
+
+        while !server.is_open() {
+            assert!(server.try_recv().unwrap().is_none());
+        }
+        assert_eq!(server.path(), Some("/?room=test"));
+        while !client.is_open() {
+            assert!(client.try_recv().unwrap().is_none());
+        }
+
+        // Long enough to need the 16-bit length form
+        let commands = Message::Commands(vec![Command::Turn(Direction::Up); 100]);
+        client.send(&commands).unwrap();
+        server.send(&Message::Desync { tick: 3 }).unwrap();
+
+        let receive = |transport: &mut WebSocketTransport| loop {
+            if let Some(message) = transport.try_recv().unwrap() {
+                return message;
+            }
+        };
+        assert_eq!(receive(&mut server), commands);
+        assert_eq!(receive(&mut client), Message::Desync { tick: 3 });
+
+        drop(client);
+        let error = loop {
+            match server.try_recv() {
+                Ok(_) => continue,
+                Err(e) => break e,
+            }
+        };
+        assert_eq!(error.kind(), io::ErrorKind::ConnectionAborted);
+    }
+}